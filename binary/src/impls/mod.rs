@@ -14,7 +14,6 @@ pub use sequence::*;
 /// This macro is used to generate the implementations for wrapping generic rust types to
 /// abstract away the serialization and deserialization over the wire.
 ///
-
 #[macro_export]
 macro_rules! generate {
     ($name:ident, <$($gen:ident: $gen_constraint:ident),*>, $type:ty $(,$lifetime:tt)?) => {
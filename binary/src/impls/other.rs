@@ -23,10 +23,10 @@ impl Decode<'_> for bool {
 }
 
 impl<T: Encode> Encode for Option<T> {
-    fn encode(&self, mut w: &mut Writer) {
+    fn encode(&self, w: &mut Writer) {
         match self {
             Some(t) => {
-                true.encode(&mut w);
+                true.encode(w);
                 t.encode(w)
             }
             None => false.encode(w),
@@ -55,10 +55,7 @@ impl Decode<'_> for Uuid {
         let slice = &r[..16];
         r.advance(16);
 
-        match Uuid::from_slice_le(&slice) {
-            Ok(v) => Some(v),
-            Err(_) => None
-        }
+        Uuid::from_slice_le(slice).ok()
     }
 }
 
@@ -32,26 +32,24 @@ pub fn derive_packet(item: StdTokenStream) -> StdTokenStream {
     }
 }
 
-fn get_encoding_type(attrs: &Vec<Attribute>) -> Option<Expr> {
+fn get_encoding_type(attrs: &[Attribute]) -> Option<Expr> {
     let mut encoding_type = None;
 
     for attr in attrs {
-        if attr.path().is_ident("encoding") {
-            if let Err(_) = attr.parse_nested_meta(|meta| {
-                if meta.path.is_ident("type") {
-                    encoding_type = Some(meta.value()?.parse::<Expr>()?);
-                }
-                Ok(())
-            }) {
-                return None;
+        if attr.path().is_ident("encoding") && attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("type") {
+                encoding_type = Some(meta.value()?.parse::<Expr>()?);
             }
+            Ok(())
+        }).is_err() {
+            return None;
         }
     }
 
     encoding_type
 }
 
-fn should_skip(attrs: &Vec<Attribute>) -> bool {
+fn should_skip(attrs: &[Attribute]) -> bool {
     for attr in attrs.iter() {
         if attr.path().is_ident("skip") {
             return true;
@@ -0,0 +1,92 @@
+use std::io;
+use bytes::{Bytes, BytesMut};
+use binary::{w32, Decode, Encode, Numeric, Writer};
+use raknet::capture::{Direction, Kind, Recorder};
+use raknet::{DisconnectReason, RakConn, Reliability};
+use crate::metrics::GameMetrics;
+use crate::packet::{Disconnect, Packet};
+
+/// Writes a packet to a batch of game packets, prefixed with its length. The batch is sent in a
+/// single RakNet message starting with the Game packet ID.
+pub fn write_batched<'a>(pk: &impl Packet<'a>, w: &mut Writer) {
+    let mut buf = BytesMut::new();
+    pk.write(&mut buf);
+
+    w32::from_usize(buf.len()).encode(w);
+    w.extend_from_slice(&buf);
+}
+
+/// Splits a batch of game packets sent in a RakNet message into the packets it contains, each
+/// starting with its header. Returns None if the message is not an uncompressed batch, for
+/// example when reading the messages of a capture.
+pub fn read_batch(mut msg: &[u8]) -> Option<Vec<&[u8]>> {
+    if raknet::packet::PacketId::decode(&mut msg)? != raknet::packet::PacketId::Game {
+        return None;
+    }
+
+    let mut packets = Vec::new();
+    while !msg.is_empty() {
+        let len = w32::decode(&mut msg)?.to_usize();
+        if msg.len() < len {
+            return None;
+        }
+
+        let (pk, rest) = msg.split_at(len);
+        packets.push(pk);
+        msg = rest;
+    }
+
+    Some(packets)
+}
+
+/// Sends a single game packet to the connection. The batch is sent uncompressed, as it is before
+/// compression is negotiated with NetworkSettings.
+pub fn send<'a>(conn: &RakConn, pk: &impl Packet<'a>) -> io::Result<()> {
+    conn.send(batch(pk), Reliability::ReliableOrdered)
+}
+
+/// Sends a single game packet to the connection like [`send`], recording it in the metrics passed.
+pub fn send_with_metrics<'a>(conn: &RakConn, pk: &impl Packet<'a>, metrics: &GameMetrics) -> io::Result<()> {
+    let msg = batch(pk);
+    metrics.record(Direction::Outbound, &msg);
+
+    conn.send(msg, Reliability::ReliableOrdered)
+}
+
+/// Records the game packets of a RakNet message carrying an uncompressed batch, as sent by [`send`],
+/// with the recorder passed. Messages that are not such a batch are not recorded.
+pub fn record(recorder: &Recorder, conn: &RakConn, direction: Direction, msg: &[u8]) {
+    if let Some(packets) = read_batch(msg) {
+        record_packets(recorder, conn, direction, &packets);
+    }
+}
+
+/// Records game packets of a batch, each starting with its header, with the recorder passed. It is
+/// used for batches that were decompressed before reading them.
+pub fn record_packets(recorder: &Recorder, conn: &RakConn, direction: Direction, packets: &[&[u8]]) {
+    for pk in packets {
+        recorder.record(direction, Kind::Packet, conn.local_addr, conn.remote_addr, pk);
+    }
+}
+
+/// Returns an uncompressed batch holding only the packet passed.
+fn batch<'a>(pk: &impl Packet<'a>) -> Bytes {
+    let mut w = BytesMut::new();
+    raknet::packet::PacketId::Game.encode(&mut w);
+    write_batched(pk, &mut w);
+
+    w.freeze()
+}
+
+/// Disconnects the client, showing the message passed on its disconnection screen. The Disconnect
+/// packet is flushed to the client before the RakNet connection is closed, so that the client
+/// shows the message instead of a timeout.
+pub fn disconnect(conn: &RakConn, message: &str) -> io::Result<()> {
+    send(conn, &Disconnect {
+        message,
+        ..Default::default()
+    })?;
+
+    conn.close(DisconnectReason::Closed);
+    Ok(())
+}
@@ -0,0 +1,364 @@
+use std::fs;
+use std::io;
+use std::path::Path;
+use bytes::{BufMut, BytesMut};
+use num_traits::{FromPrimitive, ToPrimitive};
+use binary::{Decode, Encode, Reader, w32, Writer};
+use crate::nbt::file::{decode_root, encode_root};
+use crate::nbt::{remove_key, Compound, LittleEndian, OwnedCompound, Tag};
+use crate::packet::StartGame;
+use crate::types::game_rule::{GameRule, GameRuleValue};
+use crate::types::world::{Difficulty, ExperimentData, GameType, Generator};
+use crate::types::{BlockPos, SliceU32, UBlockPos};
+
+/// The storage version written to new level.dat files.
+pub const STORAGE_VERSION: i32 = 10;
+
+/// The names of the game rules as stored in level.dat. Game rules are stored as top level keys in
+/// lower case, with booleans stored as bytes and integers as ints.
+const GAME_RULES: [&str; 36] = [
+    "commandblockoutput",
+    "commandblocksenabled",
+    "dodaylightcycle",
+    "doentitydrops",
+    "dofiretick",
+    "doimmediaterespawn",
+    "doinsomnia",
+    "dolimitedcrafting",
+    "domobloot",
+    "domobspawning",
+    "dotiledrops",
+    "doweathercycle",
+    "drowningdamage",
+    "falldamage",
+    "firedamage",
+    "freezedamage",
+    "functioncommandlimit",
+    "keepinventory",
+    "maxcommandchainlength",
+    "mobgriefing",
+    "naturalregeneration",
+    "playerssleepingpercentage",
+    "projectilescanbreakblocks",
+    "pvp",
+    "randomtickspeed",
+    "recipesunlock",
+    "respawnblocksexplode",
+    "sendcommandfeedback",
+    "showbordereffect",
+    "showcoordinates",
+    "showdaysplayed",
+    "showdeathmessages",
+    "showtags",
+    "spawnradius",
+    "tntexplodes",
+    "tntexplosiondropdecay"
+];
+
+/// The keys of the experiments compound that are not experiments themselves.
+const EXPERIMENTS_EVER_USED: &str = "experiments_ever_used";
+const SAVED_WITH_TOGGLED_EXPERIMENTS: &str = "saved_with_toggled_experiments";
+
+/// Removes the key from the compound and returns the value of its tag if it is of the variant
+/// passed. The function it is used in returns None if the tag is of another variant.
+macro_rules! take {
+    ($compound:ident, $key:expr, $variant:ident) => {
+        match remove_key(&mut $compound, $key) {
+            Some(Tag::$variant(v)) => Some(v),
+            Some(_) => return None,
+            None => None
+        }
+    };
+}
+
+/// Removes the key from the compound like [`take!`] and returns its value converted to the enum
+/// passed. Values the enum does not know are put back into the compound, so that they are kept in
+/// `extra` and saved again as they were.
+macro_rules! take_enum {
+    ($compound:ident, $key:expr, $type:ident) => {
+        match take!($compound, $key, Int) {
+            Some(v) => match $type::from_i32(v) {
+                Some(v) => Some(v),
+                None => {
+                    $compound.insert($key, Tag::Int(v));
+                    None
+                }
+            },
+            None => None
+        }
+    };
+}
+
+/// LevelDat is the level.dat file of a Bedrock world. The file holds a header of the storage version
+/// and the length of the payload, both little endian ints, followed by a compound encoded with
+/// [`LittleEndian`] NBT.
+///
+/// The common fields of the compound are exposed as struct members. All other keys are kept in
+/// `extra`, so that a file can be loaded and saved again without losing any of its contents.
+#[derive(Debug, Clone)]
+pub struct LevelDat {
+    /// The storage version of the world, found in the header of the file.
+    pub storage_version: i32,
+    /// The name of the world, as shown in the world list and above the player list.
+    pub level_name: String,
+    /// The world spawn. A Y of 32767 means the spawn is on the highest block at X and Z.
+    pub spawn: BlockPos,
+    /// The default game mode of players in the world.
+    ///
+    /// If the file holds a game mode, difficulty or generator that is not known, the value is kept
+    /// in `extra` and saved instead of the field, which holds its default.
+    pub game_type: GameType,
+    pub difficulty: Difficulty,
+    /// The generator of the world. Bedrock stores 1 for the infinite overworld generator.
+    pub generator: Generator,
+    /// The seed used to generate the world.
+    pub seed: i64,
+    /// The time of day in ticks.
+    pub time: i64,
+    /// The game rules stored in the world. Rules the world never set are absent, and take their
+    /// default value in game.
+    pub game_rules: Vec<GameRule>,
+    /// The experiments stored in the world, either enabled or disabled.
+    pub experiments: Vec<ExperimentData>,
+    /// Specifies if any experiment was ever enabled in the world.
+    pub experiments_ever_used: bool,
+    /// Specifies if the world was last saved with any experiment toggled.
+    pub saved_with_toggled_experiments: bool,
+    pub commands_enabled: bool,
+    pub rain_level: f32,
+    pub lightning_level: f32,
+    /// All keys of the compound that are not exposed as struct members above.
+    pub extra: OwnedCompound
+}
+
+impl Default for LevelDat {
+    fn default() -> Self {
+        Self {
+            storage_version: STORAGE_VERSION,
+            level_name: String::from("Bedrock level"),
+            spawn: BlockPos { x: 0, y: 32767, z: 0 },
+            game_type: GameType::Survival,
+            difficulty: Difficulty::Normal,
+            generator: Generator::Overworld,
+            seed: 0,
+            time: 0,
+            game_rules: Vec::new(),
+            experiments: Vec::new(),
+            experiments_ever_used: false,
+            saved_with_toggled_experiments: false,
+            commands_enabled: false,
+            rain_level: 0.0,
+            lightning_level: 0.0,
+            extra: OwnedCompound::new()
+        }
+    }
+}
+
+impl LevelDat {
+    /// Reads the level.dat file at the path passed.
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Self> {
+        let buf = fs::read(path)?;
+        LevelDat::decode(&mut &buf[..]).ok_or_else(|| {
+            io::Error::new(io::ErrorKind::InvalidData, "invalid level.dat")
+        })
+    }
+
+    /// Writes the level.dat file to the path passed, replacing the file if it exists.
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let mut w = BytesMut::new();
+        self.encode(&mut w);
+
+        fs::write(path, w)
+    }
+
+    /// Fills out the fields of the StartGame packet that are stored in the world. Fields of the
+    /// packet that are specific to the player or the server are left untouched.
+    pub fn fill_start_game(&self, pk: &mut StartGame) {
+        pk.world_name = self.level_name.clone();
+        pk.world_seed = self.seed;
+        pk.world_game_mode = self.game_type.clone();
+        pk.difficulty = self.difficulty;
+        pk.generator = self.generator.clone();
+        pk.world_spawn = UBlockPos { x: self.spawn.x, y: self.spawn.y, z: self.spawn.z };
+        pk.time = self.time;
+        pk.game_rules = self.game_rules.clone();
+        pk.experiments = SliceU32::new(self.experiments.clone());
+        pk.experiments_previously_toggled = self.experiments_ever_used;
+        pk.commands_enabled = self.commands_enabled;
+        pk.rain_level = self.rain_level;
+        pk.lightning_level = self.lightning_level;
+    }
+
+    /// Builds the level.dat file from the fields of a StartGame packet, such as for creating a
+    /// new world from the settings a server was started with.
+    pub fn from_start_game(pk: &StartGame) -> Self {
+        Self {
+            level_name: pk.world_name.clone(),
+            spawn: BlockPos { x: pk.world_spawn.x, y: pk.world_spawn.y, z: pk.world_spawn.z },
+            game_type: pk.world_game_mode.clone(),
+            difficulty: pk.difficulty,
+            generator: pk.generator.clone(),
+            seed: pk.world_seed,
+            time: pk.time,
+            game_rules: pk.game_rules.clone(),
+            experiments: pk.experiments.to_vec(),
+            experiments_ever_used: pk.experiments_previously_toggled,
+            commands_enabled: pk.commands_enabled,
+            rain_level: pk.rain_level,
+            lightning_level: pk.lightning_level,
+            ..Default::default()
+        }
+    }
+
+    /// Takes the fields exposed as struct members out of the root compound. Keys of which the
+    /// tag is of an unexpected type fail the decoding, while unknown enum values are kept in
+    /// `extra`.
+    fn from_compound(storage_version: i32, mut root: Compound) -> Option<Self> {
+        let mut level = Self { storage_version, ..Default::default() };
+
+        if let Some(v) = take!(root, "LevelName", String) {
+            level.level_name = String::from(v);
+        }
+        if let Some(v) = take!(root, "SpawnX", Int) {
+            level.spawn.x = v;
+        }
+        if let Some(v) = take!(root, "SpawnY", Int) {
+            level.spawn.y = v;
+        }
+        if let Some(v) = take!(root, "SpawnZ", Int) {
+            level.spawn.z = v;
+        }
+        if let Some(v) = take_enum!(root, "GameType", GameType) {
+            level.game_type = v;
+        }
+        if let Some(v) = take_enum!(root, "Difficulty", Difficulty) {
+            level.difficulty = v;
+        }
+        if let Some(v) = take_enum!(root, "Generator", Generator) {
+            level.generator = v;
+        }
+        if let Some(v) = take!(root, "RandomSeed", Long) {
+            level.seed = v;
+        }
+        if let Some(v) = take!(root, "Time", Long) {
+            level.time = v;
+        }
+        if let Some(v) = take!(root, "commandsEnabled", Byte) {
+            level.commands_enabled = v != 0;
+        }
+        if let Some(v) = take!(root, "rainLevel", Float) {
+            level.rain_level = v;
+        }
+        if let Some(v) = take!(root, "lightningLevel", Float) {
+            level.lightning_level = v;
+        }
+
+        for name in GAME_RULES {
+            let Some(tag) = remove_key(&mut root, name) else {
+                continue;
+            };
+            let value = match tag {
+                Tag::Byte(v) => GameRuleValue::Bool(v != 0),
+                Tag::Int(v) => GameRuleValue::Int(w32::new(v as u32)),
+                Tag::Float(v) => GameRuleValue::Float(v),
+                _ => return None
+            };
+
+            level.game_rules.push(GameRule {
+                name: String::from(name),
+                can_be_modified_by_player: true,
+                value
+            });
+        }
+
+        if let Some(tag) = remove_key(&mut root, "experiments") {
+            let Tag::Compound(experiments) = tag else {
+                return None;
+            };
+
+            for (name, tag) in experiments {
+                let Tag::Byte(enabled) = tag else {
+                    return None;
+                };
+                let enabled = enabled != 0;
+                match name {
+                    EXPERIMENTS_EVER_USED => level.experiments_ever_used = enabled,
+                    SAVED_WITH_TOGGLED_EXPERIMENTS => level.saved_with_toggled_experiments = enabled,
+                    _ => level.experiments.push(ExperimentData { name: String::from(name), enabled })
+                }
+            }
+        }
+
+        level.extra = root.into_iter().map(|(k, v)| (String::from(k), v.into())).collect();
+        Some(level)
+    }
+
+    /// Builds the root compound from the struct members and the extra keys.
+    fn to_compound(&self) -> Compound<'_> {
+        let mut root: Compound = self.extra.iter().map(|(k, v)| (k.as_str(), v.as_tag())).collect();
+
+        root.insert("LevelName", Tag::String(&self.level_name));
+        root.insert("SpawnX", Tag::Int(self.spawn.x));
+        root.insert("SpawnY", Tag::Int(self.spawn.y));
+        root.insert("SpawnZ", Tag::Int(self.spawn.z));
+        // Values that were not known when loading are kept in extra.
+        root.entry("GameType").or_insert(Tag::Int(self.game_type.to_i32().unwrap()));
+        root.entry("Difficulty").or_insert(Tag::Int(self.difficulty.to_i32().unwrap()));
+        root.entry("Generator").or_insert(Tag::Int(self.generator.to_i32().unwrap()));
+        root.insert("RandomSeed", Tag::Long(self.seed));
+        root.insert("Time", Tag::Long(self.time));
+        root.insert("commandsEnabled", Tag::Byte(self.commands_enabled as i8));
+        root.insert("rainLevel", Tag::Float(self.rain_level));
+        root.insert("lightningLevel", Tag::Float(self.lightning_level));
+
+        for rule in &self.game_rules {
+            let value = match &rule.value {
+                GameRuleValue::Bool(v) => Tag::Byte(*v as i8),
+                GameRuleValue::Int(v) => Tag::Int(v.clone().value() as i32),
+                GameRuleValue::Float(v) => Tag::Float(*v)
+            };
+            root.insert(&rule.name, value);
+        }
+
+        let mut experiments = Compound::new();
+        for experiment in &self.experiments {
+            experiments.insert(experiment.name.as_str(), Tag::Byte(experiment.enabled as i8));
+        }
+        experiments.insert(EXPERIMENTS_EVER_USED, Tag::Byte(self.experiments_ever_used as i8));
+        experiments.insert(SAVED_WITH_TOGGLED_EXPERIMENTS, Tag::Byte(self.saved_with_toggled_experiments as i8));
+        root.insert("experiments", Tag::Compound(experiments));
+
+        root
+    }
+}
+
+impl Encode for LevelDat {
+    fn encode(&self, w: &mut Writer) {
+        let mut payload = BytesMut::new();
+        encode_root::<LittleEndian>("", &Tag::Compound(self.to_compound()), &mut payload);
+
+        self.storage_version.encode(w);
+        (payload.len() as i32).encode(w);
+        w.put_slice(&payload);
+    }
+}
+
+impl Decode<'_> for LevelDat {
+    fn decode(r: &mut Reader) -> Option<Self> {
+        let storage_version = i32::decode(r)?;
+        let len = usize::try_from(i32::decode(r)?).ok()?;
+        if r.len() < len {
+            return None;
+        }
+
+        let (mut payload, rest) = r.split_at(len);
+        *r = rest;
+
+        let (_, root) = decode_root::<LittleEndian>(&mut payload)?;
+        let Tag::Compound(root) = root else {
+            return None;
+        };
+
+        Self::from_compound(storage_version, root)
+    }
+}
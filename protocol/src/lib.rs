@@ -1,3 +1,6 @@
+pub mod conn;
+pub mod level_dat;
+pub mod metrics;
 pub mod nbt;
 pub mod types;
 pub mod packet;
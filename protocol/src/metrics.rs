@@ -0,0 +1,139 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+use binary::{w32, Decode, Numeric};
+use num_traits::FromPrimitive;
+use raknet::capture::Direction;
+use raknet::metrics::{Exposition, MetricType};
+use crate::conn::read_batch;
+use crate::packet::PacketId;
+
+/// The bits of the header of a game packet that hold its ID. The bits above hold the IDs of the
+/// sub-clients sending and receiving the packet.
+const PACKET_ID_MASK: usize = 0x3ff;
+
+/// Traffic is the game packet traffic of connections in a single direction.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Traffic {
+    /// The amount of batches of game packets.
+    pub batches: u64,
+    /// The amount of bytes of the batches as sent over RakNet, after compression.
+    pub batch_bytes: u64,
+    /// The amount of bytes of the packets in the batches, before compression.
+    pub packet_bytes: u64,
+    /// The amount of packets by their ID.
+    pub packets: HashMap<PacketId, u64>,
+    /// The amount of packets with an ID that is not known.
+    pub unknown_packets: u64
+}
+
+impl Traffic {
+    /// Returns the ratio between the size of the packets before compression and the size of the
+    /// batches they were sent in, or 0 if there was no traffic. It is slightly below 1 for
+    /// uncompressed batches, as batches also hold the length of every packet.
+    pub fn compression_ratio(&self) -> f64 {
+        if self.batch_bytes == 0 {
+            return 0.0;
+        }
+        self.packet_bytes as f64 / self.batch_bytes as f64
+    }
+}
+
+/// GameStats is a snapshot of the game packet traffic recorded by [`GameMetrics`].
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct GameStats {
+    pub sent: Traffic,
+    pub received: Traffic
+}
+
+/// GameMetrics records the game packets sent and received over connections, by their ID and
+/// along with the compression ratio of their batches. It may be shared by any amount of
+/// connections.
+#[derive(Debug, Default)]
+pub struct GameMetrics {
+    stats: Mutex<GameStats>
+}
+
+impl GameMetrics {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Records a RakNet message carrying an uncompressed batch of game packets, as sent by
+    /// [`conn::send`](crate::conn::send). Messages that are not such a batch are ignored.
+    pub fn record(&self, direction: Direction, msg: &[u8]) {
+        if let Some(packets) = read_batch(msg) {
+            self.record_batch(direction, msg.len(), &packets);
+        }
+    }
+
+    /// Records a batch of game packets of which the message was the size passed, with the
+    /// packets it contained after decompression.
+    pub fn record_batch(&self, direction: Direction, size: usize, packets: &[&[u8]]) {
+        let mut stats = self.stats.lock().unwrap();
+        let traffic = match direction {
+            Direction::Inbound => &mut stats.received,
+            Direction::Outbound => &mut stats.sent
+        };
+
+        traffic.batches += 1;
+        traffic.batch_bytes += size as u64;
+
+        for mut pk in packets.iter().copied() {
+            traffic.packet_bytes += pk.len() as u64;
+
+            match decode_id(&mut pk) {
+                Some(id) => *traffic.packets.entry(id).or_default() += 1,
+                None => traffic.unknown_packets += 1
+            }
+        }
+    }
+
+    /// Returns a snapshot of the traffic recorded so far.
+    pub fn stats(&self) -> GameStats {
+        self.stats.lock().unwrap().clone()
+    }
+
+    /// Writes the metrics of the traffic recorded so far in the Prometheus text format, labelled
+    /// with their direction and the packet ID.
+    pub fn write_metrics(&self, e: &mut Exposition) {
+        let stats = self.stats();
+        let directions = [("sent", &stats.sent), ("received", &stats.received)];
+
+        e.describe("game_batches_total", MetricType::Counter, "Batches of game packets.");
+        for (direction, traffic) in directions {
+            e.sample("game_batches_total", &[("direction", direction)], traffic.batches as f64);
+        }
+
+        e.describe("game_batch_bytes_total", MetricType::Counter, "Bytes of batches of game packets after compression.");
+        for (direction, traffic) in directions {
+            e.sample("game_batch_bytes_total", &[("direction", direction)], traffic.batch_bytes as f64);
+        }
+
+        e.describe("game_packet_bytes_total", MetricType::Counter, "Bytes of game packets before compression.");
+        for (direction, traffic) in directions {
+            e.sample("game_packet_bytes_total", &[("direction", direction)], traffic.packet_bytes as f64);
+        }
+
+        e.describe("game_compression_ratio", MetricType::Gauge, "Ratio between the size of game packets and the batches they were sent in.");
+        for (direction, traffic) in directions {
+            e.sample("game_compression_ratio", &[("direction", direction)], traffic.compression_ratio());
+        }
+
+        e.describe("game_packets_total", MetricType::Counter, "Game packets by their ID.");
+        for (direction, traffic) in directions {
+            let mut packets: Vec<_> = traffic.packets.iter().map(|(id, &count)| (format!("{id:?}"), count)).collect();
+            packets.sort();
+
+            for (id, count) in packets {
+                e.sample("game_packets_total", &[("direction", direction), ("packet", &id)], count as f64);
+            }
+            e.sample("game_packets_total", &[("direction", direction), ("packet", "Unknown")], traffic.unknown_packets as f64);
+        }
+    }
+}
+
+/// Decodes the header of a game packet and returns the ID it holds, if the ID is known.
+fn decode_id(pk: &mut &[u8]) -> Option<PacketId> {
+    let header = w32::decode(pk)?.to_usize();
+    PacketId::from_usize(header & PACKET_ID_MASK)
+}
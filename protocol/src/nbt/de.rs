@@ -0,0 +1,252 @@
+use serde::de::value::BorrowedStrDeserializer;
+use serde::de::{self, Deserialize, DeserializeSeed, IntoDeserializer, Visitor};
+use serde::forward_to_deserialize_any;
+use binary::Reader;
+use crate::nbt::file::decode_root;
+use crate::nbt::{Compound, Encoding, SerdeError, Tag};
+
+impl de::Error for SerdeError {
+    fn custom<T: std::fmt::Display>(msg: T) -> Self {
+        Self::new(msg)
+    }
+}
+
+/// Deserializes a value from the tag. Strings and byte arrays may be borrowed from the tag.
+///
+/// Tags are deserialized as they are serialized by [`to_tag`](crate::nbt::to_tag). Byte, int and
+/// long arrays deserialize into any sequence of numbers, as lists do.
+pub fn from_tag<'de, T: Deserialize<'de>>(tag: Tag<'de>) -> Result<T, SerdeError> {
+    T::deserialize(Deserializer::new(tag))
+}
+
+/// Decodes a tag with the [`Encoding`] passed from the Reader, in the same way as
+/// [`NBT`](crate::nbt::NBT), and deserializes a value from it.
+pub fn from_reader<'de, E: Encoding, T: Deserialize<'de>>(r: &mut Reader<'de>) -> Result<T, SerdeError> {
+    let (_, tag) = decode_root::<E>(r).ok_or_else(|| SerdeError::new("invalid NBT"))?;
+    from_tag(tag)
+}
+
+/// Deserializer deserializes values from a [`Tag`].
+pub struct Deserializer<'de> {
+    tag: Tag<'de>
+}
+
+impl<'de> Deserializer<'de> {
+    pub fn new(tag: Tag<'de>) -> Self {
+        Self { tag }
+    }
+}
+
+impl<'de> IntoDeserializer<'de, SerdeError> for Tag<'de> {
+    type Deserializer = Deserializer<'de>;
+
+    fn into_deserializer(self) -> Deserializer<'de> {
+        Deserializer::new(self)
+    }
+}
+
+impl<'de> de::Deserializer<'de> for Deserializer<'de> {
+    type Error = SerdeError;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, SerdeError> {
+        match self.tag {
+            Tag::End => visitor.visit_unit(),
+            Tag::Byte(v) => visitor.visit_i8(v),
+            Tag::Short(v) => visitor.visit_i16(v),
+            Tag::Int(v) => visitor.visit_i32(v),
+            Tag::Long(v) => visitor.visit_i64(v),
+            Tag::Float(v) => visitor.visit_f32(v),
+            Tag::Double(v) => visitor.visit_f64(v),
+            Tag::ByteArray(v) => visitor.visit_seq(SeqAccess::new(v.iter().map(|v| Tag::Byte(*v)))),
+            Tag::String(v) => visitor.visit_borrowed_str(v),
+            Tag::List(v) => visitor.visit_seq(SeqAccess::new(v.into_iter())),
+            Tag::Compound(v) => visitor.visit_map(MapAccess::new(v)),
+            Tag::IntArray(v) => visitor.visit_seq(SeqAccess::new(v.into_iter().map(Tag::Int))),
+            Tag::LongArray(v) => visitor.visit_seq(SeqAccess::new(v.into_iter().map(Tag::Long)))
+        }
+    }
+
+    fn deserialize_bool<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, SerdeError> {
+        match self.tag {
+            Tag::Byte(v) => visitor.visit_bool(v != 0),
+            _ => self.deserialize_any(visitor)
+        }
+    }
+
+    fn deserialize_u8<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, SerdeError> {
+        match self.tag {
+            Tag::Byte(v) => visitor.visit_u8(v as u8),
+            _ => self.deserialize_any(visitor)
+        }
+    }
+
+    fn deserialize_u16<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, SerdeError> {
+        match self.tag {
+            Tag::Short(v) => visitor.visit_u16(v as u16),
+            _ => self.deserialize_any(visitor)
+        }
+    }
+
+    fn deserialize_u32<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, SerdeError> {
+        match self.tag {
+            Tag::Int(v) => visitor.visit_u32(v as u32),
+            _ => self.deserialize_any(visitor)
+        }
+    }
+
+    fn deserialize_u64<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, SerdeError> {
+        match self.tag {
+            Tag::Long(v) => visitor.visit_u64(v as u64),
+            _ => self.deserialize_any(visitor)
+        }
+    }
+
+    fn deserialize_bytes<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, SerdeError> {
+        match self.tag {
+            Tag::ByteArray(v) => {
+                let bytes: &'de [u8] = unsafe { std::mem::transmute(v) };
+                visitor.visit_borrowed_bytes(bytes)
+            }
+            _ => self.deserialize_any(visitor)
+        }
+    }
+
+    fn deserialize_byte_buf<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, SerdeError> {
+        self.deserialize_bytes(visitor)
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, SerdeError> {
+        // Values that are None are left out of compounds, so any tag present is Some.
+        visitor.visit_some(self)
+    }
+
+    fn deserialize_unit<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, SerdeError> {
+        visitor.visit_unit()
+    }
+
+    fn deserialize_unit_struct<V: Visitor<'de>>(self, _name: &'static str, visitor: V) -> Result<V::Value, SerdeError> {
+        visitor.visit_unit()
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(self, _name: &'static str, visitor: V) -> Result<V::Value, SerdeError> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(self, _name: &'static str, _variants: &'static [&'static str], visitor: V) -> Result<V::Value, SerdeError> {
+        match self.tag {
+            Tag::String(v) => visitor.visit_enum(BorrowedStrDeserializer::new(v)),
+            Tag::Compound(v) if v.len() == 1 => {
+                let (name, value) = v.into_iter().next().unwrap();
+                visitor.visit_enum(EnumAccess { name, value })
+            }
+            tag => Err(SerdeError::new(format!("cannot deserialize enum from {:?}", tag.id())))
+        }
+    }
+
+    fn deserialize_ignored_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, SerdeError> {
+        visitor.visit_unit()
+    }
+
+    forward_to_deserialize_any! {
+        i8 i16 i32 i64 i128 u128 f32 f64 char str string seq tuple tuple_struct map struct identifier
+    }
+}
+
+/// SeqAccess deserializes the elements of lists and arrays. The elements of arrays are passed as
+/// tags, so that they deserialize into unsigned integers in the same way as other tags.
+struct SeqAccess<I> {
+    iter: I
+}
+
+impl<I> SeqAccess<I> {
+    fn new(iter: I) -> Self {
+        Self { iter }
+    }
+}
+
+impl<'de, I: ExactSizeIterator<Item = Tag<'de>>> de::SeqAccess<'de> for SeqAccess<I> {
+    type Error = SerdeError;
+
+    fn next_element_seed<T: DeserializeSeed<'de>>(&mut self, seed: T) -> Result<Option<T::Value>, SerdeError> {
+        match self.iter.next() {
+            Some(v) => seed.deserialize(Deserializer::new(v)).map(Some),
+            None => Ok(None)
+        }
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.iter.len())
+    }
+}
+
+/// MapAccess deserializes the keys and values of compounds.
+struct MapAccess<'de> {
+    iter: <Compound<'de> as IntoIterator>::IntoIter,
+    value: Option<Tag<'de>>
+}
+
+impl<'de> MapAccess<'de> {
+    fn new(compound: Compound<'de>) -> Self {
+        Self { iter: compound.into_iter(), value: None }
+    }
+}
+
+impl<'de> de::MapAccess<'de> for MapAccess<'de> {
+    type Error = SerdeError;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(&mut self, seed: K) -> Result<Option<K::Value>, SerdeError> {
+        match self.iter.next() {
+            Some((key, value)) => {
+                self.value = Some(value);
+                seed.deserialize(BorrowedStrDeserializer::new(key)).map(Some)
+            }
+            None => Ok(None)
+        }
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(&mut self, seed: V) -> Result<V::Value, SerdeError> {
+        let value = self.value.take().ok_or_else(|| SerdeError::new("value deserialized before its key"))?;
+        seed.deserialize(Deserializer::new(value))
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.iter.len())
+    }
+}
+
+/// EnumAccess deserializes the variants of enums that are not unit variants, which are
+/// serialized as a compound with the name of the variant as only key.
+struct EnumAccess<'de> {
+    name: &'de str,
+    value: Tag<'de>
+}
+
+impl<'de> de::EnumAccess<'de> for EnumAccess<'de> {
+    type Error = SerdeError;
+    type Variant = Deserializer<'de>;
+
+    fn variant_seed<V: DeserializeSeed<'de>>(self, seed: V) -> Result<(V::Value, Deserializer<'de>), SerdeError> {
+        let variant = seed.deserialize(BorrowedStrDeserializer::<SerdeError>::new(self.name))?;
+        Ok((variant, Deserializer::new(self.value)))
+    }
+}
+
+impl<'de> de::VariantAccess<'de> for Deserializer<'de> {
+    type Error = SerdeError;
+
+    fn unit_variant(self) -> Result<(), SerdeError> {
+        Ok(())
+    }
+
+    fn newtype_variant_seed<T: DeserializeSeed<'de>>(self, seed: T) -> Result<T::Value, SerdeError> {
+        seed.deserialize(self)
+    }
+
+    fn tuple_variant<V: Visitor<'de>>(self, _len: usize, visitor: V) -> Result<V::Value, SerdeError> {
+        de::Deserializer::deserialize_seq(self, visitor)
+    }
+
+    fn struct_variant<V: Visitor<'de>>(self, _fields: &'static [&'static str], visitor: V) -> Result<V::Value, SerdeError> {
+        de::Deserializer::deserialize_map(self, visitor)
+    }
+}
@@ -1,8 +1,35 @@
-use binary::{Decode, Encode, Reader, RefString, v32, v64, w32, Writer};
+use std::borrow::Cow;
+use bytes::{Buf, BufMut};
+use binary::{b16, b32, b64, d32, d64, n16, Decode, Encode, Reader, RefString, v32, v64, w32, Writer};
 
 /// Encoding is the trait implemented for the various types of NBT Encoding supported 
 /// by the NBT Library
 pub trait Encoding {
+    /// Reads a short. Shorts, floats and doubles are little endian unless overridden.
+    fn read_short(r: &mut Reader) -> Option<i16> {
+        i16::decode(r)
+    }
+
+    fn write_short(w: &mut Writer, val: i16) {
+        val.encode(w);
+    }
+
+    fn read_float(r: &mut Reader) -> Option<f32> {
+        f32::decode(r)
+    }
+
+    fn write_float(w: &mut Writer, val: f32) {
+        val.encode(w);
+    }
+
+    fn read_double(r: &mut Reader) -> Option<f64> {
+        f64::decode(r)
+    }
+
+    fn write_double(w: &mut Writer, val: f64) {
+        val.encode(w);
+    }
+
     fn read_int(r: &mut Reader) -> Option<i32>;
     fn write_int(w: &mut Writer, val: i32);
 
@@ -11,6 +38,12 @@ pub trait Encoding {
 
     fn read_str<'a>(r: &mut Reader<'a>) -> Option<&'a str>;
     fn write_str(w: &mut Writer, val: &str);
+
+    /// Reads a string like [`Encoding::read_str`], but decodes it into an owned string if it
+    /// cannot be borrowed from the Reader. Strings are borrowed unless overridden.
+    fn read_string<'a>(r: &mut Reader<'a>) -> Option<Cow<'a, str>> {
+        Some(Cow::Borrowed(Self::read_str(r)?))
+    }
 }
 
 /// NetworkLittleEndian encoding is used for encoding NBT objects over the network and the wire. It encodes
@@ -23,6 +56,18 @@ pub struct NetworkLittleEndian;
 #[derive(Default, Debug, PartialEq, Clone, Copy)]
 pub struct LittleEndian;
 
+/// BigEndian encoding is the encoding of NBT files of Java Edition, such as structures and schematics. Numbers are
+/// big endian and strings are encoded in modified UTF-8, prefixed with their length as an unsigned short.
+///
+/// Modified UTF-8 only differs from UTF-8 for strings holding NUL or characters outside the Basic Multilingual
+/// Plane. As [`Tag`](crate::nbt::Tag) borrows its strings from the input, such strings fail decoding into a
+/// [`Tag`](crate::nbt::Tag), but are decoded into an [`OwnedTag`](crate::nbt::OwnedTag) with
+/// [`decode_owned`](crate::nbt::decode_owned) or [`OwnedNBT`](crate::nbt::OwnedNBT).
+///
+/// Strings longer than 65535 bytes in modified UTF-8 are cut off at the last character that fits when encoded.
+#[derive(Default, Debug, PartialEq, Clone, Copy)]
+pub struct BigEndian;
+
 impl Encoding for NetworkLittleEndian {
     fn read_int(r: &mut Reader) -> Option<i32> {
         Some(v32::decode(r)?.value())
@@ -51,7 +96,7 @@ impl Encoding for NetworkLittleEndian {
 
 impl Encoding for LittleEndian {
     fn read_int(r: &mut Reader) -> Option<i32> {
-        Some(i32::decode(r)?)
+        i32::decode(r)
     }
 
     fn write_int(w: &mut Writer, val: i32) {
@@ -59,7 +104,7 @@ impl Encoding for LittleEndian {
     }
 
     fn read_long(r: &mut Reader) -> Option<i64> {
-        Some(i64::decode(r)?)
+        i64::decode(r)
     }
 
     fn write_long(w: &mut Writer, val: i64) {
@@ -73,4 +118,153 @@ impl Encoding for LittleEndian {
     fn write_str(w: &mut Writer, val: &str) {
         RefString::<u16>::new(val).encode(w);
     }
-}
\ No newline at end of file
+}
+
+impl Encoding for BigEndian {
+    fn read_short(r: &mut Reader) -> Option<i16> {
+        Some(b16::decode(r)?.value())
+    }
+
+    fn write_short(w: &mut Writer, val: i16) {
+        b16::new(val).encode(w);
+    }
+
+    fn read_float(r: &mut Reader) -> Option<f32> {
+        Some(d32::decode(r)?.value())
+    }
+
+    fn write_float(w: &mut Writer, val: f32) {
+        d32::new(val).encode(w);
+    }
+
+    fn read_double(r: &mut Reader) -> Option<f64> {
+        Some(d64::decode(r)?.value())
+    }
+
+    fn write_double(w: &mut Writer, val: f64) {
+        d64::new(val).encode(w);
+    }
+
+    fn read_int(r: &mut Reader) -> Option<i32> {
+        Some(b32::decode(r)?.value())
+    }
+
+    fn write_int(w: &mut Writer, val: i32) {
+        b32::new(val).encode(w);
+    }
+
+    fn read_long(r: &mut Reader) -> Option<i64> {
+        Some(b64::decode(r)?.value())
+    }
+
+    fn write_long(w: &mut Writer, val: i64) {
+        b64::new(val).encode(w);
+    }
+
+    fn read_str<'a>(r: &mut Reader<'a>) -> Option<&'a str> {
+        let len = n16::decode(r)?.value() as usize;
+        if r.remaining() < len {
+            return None;
+        }
+
+        // Modified UTF-8 that is also valid UTF-8 encodes the same string.
+        let val = std::str::from_utf8(&r[..len]).ok()?;
+        r.advance(len);
+        Some(val)
+    }
+
+    fn read_string<'a>(r: &mut Reader<'a>) -> Option<Cow<'a, str>> {
+        let len = n16::decode(r)?.value() as usize;
+        if r.remaining() < len {
+            return None;
+        }
+
+        let (buf, rest) = r.split_at(len);
+        *r = rest;
+        match std::str::from_utf8(buf) {
+            Ok(val) => Some(Cow::Borrowed(val)),
+            Err(_) => Some(Cow::Owned(decode_modified_utf8(buf)?))
+        }
+    }
+
+    fn write_str(w: &mut Writer, val: &str) {
+        // The length is encoded as an unsigned short, so longer strings cannot be encoded whole.
+        let val = truncate_modified_utf8(val, u16::MAX as usize);
+        if !val.contains(|c: char| c == '\0' || c > '\u{ffff}') {
+            n16::new(val.len() as u16).encode(w);
+            w.put_slice(val.as_bytes());
+            return;
+        }
+
+        let encoded = encode_modified_utf8(val);
+        n16::new(encoded.len() as u16).encode(w);
+        w.put_slice(&encoded);
+    }
+}
+
+/// Encodes a string in modified UTF-8: NUL is encoded in two bytes, and characters outside the Basic
+/// Multilingual Plane are encoded as a surrogate pair of three bytes each.
+pub fn encode_modified_utf8(val: &str) -> Vec<u8> {
+    let mut buf = Vec::with_capacity(val.len());
+
+    for unit in val.encode_utf16() {
+        match unit {
+            0x0001..=0x007f => buf.push(unit as u8),
+            0x0000 | 0x0080..=0x07ff => {
+                buf.push(0xc0 | (unit >> 6) as u8);
+                buf.push(0x80 | (unit & 0x3f) as u8);
+            }
+            _ => {
+                buf.push(0xe0 | (unit >> 12) as u8);
+                buf.push(0x80 | ((unit >> 6) & 0x3f) as u8);
+                buf.push(0x80 | (unit & 0x3f) as u8);
+            }
+        }
+    }
+
+    buf
+}
+
+/// Returns the longest prefix of the string that is at most `max` bytes long when encoded in modified UTF-8.
+fn truncate_modified_utf8(val: &str, max: usize) -> &str {
+    let mut len = 0;
+    for (i, c) in val.char_indices() {
+        len += match c {
+            '\0' => 2,
+            '\u{10000}'.. => 6,
+            c => c.len_utf8()
+        };
+        if len > max {
+            return &val[..i];
+        }
+    }
+    val
+}
+
+/// Decodes a string encoded in modified UTF-8. Returns None if the bytes are not valid modified UTF-8.
+pub fn decode_modified_utf8(buf: &[u8]) -> Option<String> {
+    let mut units = Vec::with_capacity(buf.len());
+    let mut i = 0;
+
+    while i < buf.len() {
+        let continuation = |j: usize| buf.get(j).filter(|b| *b & 0xc0 == 0x80).map(|b| (b & 0x3f) as u16);
+
+        match buf[i] {
+            b @ 0x01..=0x7f => {
+                units.push(b as u16);
+                i += 1;
+            }
+            b @ 0xc0..=0xdf => {
+                units.push(((b & 0x1f) as u16) << 6 | continuation(i + 1)?);
+                i += 2;
+            }
+            b @ 0xe0..=0xef => {
+                units.push(((b & 0x0f) as u16) << 12 | continuation(i + 1)? << 6 | continuation(i + 2)?);
+                i += 3;
+            }
+            _ => return None
+        }
+    }
+
+    String::from_utf16(&units).ok()
+}
@@ -0,0 +1,120 @@
+use std::borrow::Cow;
+use std::fs;
+use std::io::{self, Read, Write};
+use std::path::Path;
+use bytes::BytesMut;
+use flate2::read::{GzDecoder, ZlibDecoder};
+use flate2::write::{GzEncoder, ZlibEncoder};
+use binary::Reader;
+use crate::nbt::{decode, decode_owned, decode_tag_id, encode, encode_tag_id, Encoding, OwnedTag, Tag};
+
+/// Compression is the compression of an NBT file.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Compression {
+    /// The file is not compressed.
+    #[default]
+    None,
+    /// The file is compressed with gzip, as most NBT files of Java Edition are.
+    Gzip,
+    /// The file is compressed with zlib, as the chunks of Java Edition region files are.
+    Zlib
+}
+
+impl Compression {
+    /// Detects the compression of a file from its first bytes. Files that start with neither a
+    /// gzip nor a zlib header are assumed to be uncompressed.
+    pub fn detect(buf: &[u8]) -> Self {
+        match buf {
+            [0x1f, 0x8b, ..] => Compression::Gzip,
+            // The compression method must be deflate, and the header is a multiple of 31.
+            [cmf, flg, ..] if cmf & 0x0f == 8 && (*cmf as u16 * 256 + *flg as u16).is_multiple_of(31) => Compression::Zlib,
+            _ => Compression::None
+        }
+    }
+}
+
+/// Decompresses the contents of an NBT file, detecting its compression with
+/// [`Compression::detect`]. Uncompressed contents are borrowed.
+pub fn decompress(buf: &[u8]) -> io::Result<(Cow<'_, [u8]>, Compression)> {
+    let compression = Compression::detect(buf);
+
+    let mut out = Vec::new();
+    match compression {
+        Compression::None => return Ok((Cow::Borrowed(buf), compression)),
+        Compression::Gzip => GzDecoder::new(buf).read_to_end(&mut out)?,
+        Compression::Zlib => ZlibDecoder::new(buf).read_to_end(&mut out)?
+    };
+
+    Ok((Cow::Owned(out), compression))
+}
+
+/// Compresses the contents of an NBT file with the compression passed.
+pub fn compress(buf: &[u8], compression: Compression) -> io::Result<Vec<u8>> {
+    match compression {
+        Compression::None => Ok(buf.to_vec()),
+        Compression::Gzip => {
+            let mut encoder = GzEncoder::new(Vec::new(), flate2::Compression::default());
+            encoder.write_all(buf)?;
+            encoder.finish()
+        }
+        Compression::Zlib => {
+            let mut encoder = ZlibEncoder::new(Vec::new(), flate2::Compression::default());
+            encoder.write_all(buf)?;
+            encoder.finish()
+        }
+    }
+}
+
+/// Reads the NBT file at the path passed and returns its decompressed contents, along with the
+/// compression detected. The root tag is decoded from the contents with [`decode_root`].
+pub fn read(path: impl AsRef<Path>) -> io::Result<(Vec<u8>, Compression)> {
+    let buf = fs::read(path)?;
+    let (contents, compression) = decompress(&buf)?;
+
+    Ok((contents.into_owned(), compression))
+}
+
+/// Writes the root tag to the NBT file at the path passed with the name, [`Encoding`] and
+/// compression passed, replacing the file if it exists.
+pub fn write<E: Encoding>(path: impl AsRef<Path>, name: &str, tag: &Tag, compression: Compression) -> io::Result<()> {
+    let mut w = BytesMut::new();
+    encode_root::<E>(name, tag, &mut w);
+
+    fs::write(path, compress(&w, compression)?)
+}
+
+/// Decodes the root tag of an NBT file along with its name. Unlike [`NBT`](crate::nbt::NBT),
+/// which discards the name, the name is returned so that the file can be written back as it was.
+pub fn decode_root<'a, E: Encoding>(r: &mut Reader<'a>) -> Option<(&'a str, Tag<'a>)> {
+    let id = decode_tag_id(r)?;
+    let name = E::read_str(r)?;
+
+    Some((name, decode::<E>(id, r)?))
+}
+
+/// Decodes the root tag of an NBT file along with its name like [`decode_root`], but into an
+/// [`OwnedTag`] with [`decode_owned`], so that strings that cannot be borrowed are decoded as well.
+pub fn decode_root_owned<E: Encoding>(r: &mut Reader) -> Option<(String, OwnedTag)> {
+    let id = decode_tag_id(r)?;
+    let name = E::read_string(r)?.into_owned();
+
+    Some((name, decode_owned::<E>(id, r)?))
+}
+
+/// Encodes the root tag of an NBT file with the name passed.
+pub fn encode_root<E: Encoding>(name: &str, tag: &Tag, w: &mut BytesMut) {
+    encode_tag_id(tag.id(), w);
+    E::write_str(w, name);
+    encode::<E>(tag, w);
+}
+
+/// Reads the NBT file at the path passed, which may be compressed with gzip or zlib, and calls the
+/// function passed with the name and root tag of the file, which borrows from its contents.
+pub fn load<E: Encoding, T>(path: impl AsRef<Path>, f: impl FnOnce(&str, Tag) -> T) -> io::Result<T> {
+    let (buf, _) = read(path)?;
+    let (name, tag) = decode_root::<E>(&mut &buf[..]).ok_or_else(|| {
+        io::Error::new(io::ErrorKind::InvalidData, "invalid NBT")
+    })?;
+
+    Ok(f(name, tag))
+}
@@ -0,0 +1,316 @@
+use serde_json::{Map, Number, Value};
+use binary::{Decode, Encode, Reader, Writer};
+use crate::nbt::tag::{push_index, push_key};
+use crate::nbt::{Encoding, OwnedCompound, OwnedList, OwnedNBT, OwnedTag, Tag, TagError, TagErrorKind, TagId, NBT};
+
+/// JsonFormat is implemented for the formats tags are converted to and from JSON with, which are
+/// [`LossyJson`] and [`TypedJson`].
+pub trait JsonFormat {
+    /// Converts the tag to JSON.
+    fn to_json(tag: &Tag) -> Value;
+
+    /// Converts the JSON to a tag. The error holds the path of the value that could not be
+    /// converted, formatted as accepted by [`Tag::path`].
+    fn from_json(value: &Value) -> Result<OwnedTag, TagError>;
+}
+
+/// LossyJson converts tags to JSON as they would naturally be written: numbers as numbers, strings
+/// as strings, lists and arrays as arrays and compounds as objects. The types of tags are lost, so
+/// JSON is converted back to the smallest of int, long and double that holds its numbers, booleans
+/// to bytes and arrays to lists. Floats that are not finite are converted to null, and keys of
+/// which the value is null are left out.
+#[derive(Default, Debug, PartialEq, Clone, Copy)]
+pub struct LossyJson;
+
+/// TypedJson converts tags to JSON that holds the type of every tag, so that it is converted back
+/// to the same tag. Every tag is converted to an object such as `{"type":"short","value":3}`.
+/// Compounds hold an object of converted tags, and lists the `element_type` of the list along with
+/// an array of converted tags. Floats that are not finite are converted to the strings `NaN`,
+/// `Infinity` and `-Infinity`. Tags of the type `end` are not converted back, and only empty lists
+/// may have elements of the type `end`.
+#[derive(Default, Debug, PartialEq, Clone, Copy)]
+pub struct TypedJson;
+
+/// Decodes a tag with the [`Encoding`] passed from the Reader, in the same way as [`NBT`], and
+/// converts it to JSON with the [`JsonFormat`] passed.
+pub fn decode_json<E: Encoding, F: JsonFormat>(r: &mut Reader) -> Option<Value> {
+    Some(F::to_json(&NBT::<E>::decode(r)?.value()))
+}
+
+/// Converts the JSON to a tag with the [`JsonFormat`] passed and encodes it with the [`Encoding`]
+/// passed to the Writer, in the same way as [`NBT`]. Nothing is written if the JSON could not be
+/// converted.
+pub fn encode_json<E: Encoding, F: JsonFormat>(value: &Value, w: &mut Writer) -> Result<(), TagError> {
+    OwnedNBT::<E>::new(F::from_json(value)?).encode(w);
+    Ok(())
+}
+
+impl JsonFormat for LossyJson {
+    fn to_json(tag: &Tag) -> Value {
+        match tag {
+            Tag::End => Value::Null,
+            Tag::Byte(v) => Value::from(*v),
+            Tag::Short(v) => Value::from(*v),
+            Tag::Int(v) => Value::from(*v),
+            Tag::Long(v) => Value::from(*v),
+            Tag::Float(v) => Value::from(widen(*v)),
+            Tag::Double(v) => Value::from(*v),
+            Tag::ByteArray(v) => Value::from(v.to_vec()),
+            Tag::String(v) => Value::from(*v),
+            Tag::List(v) => v.iter().map(Self::to_json).collect(),
+            Tag::Compound(v) => Value::Object(v.iter().map(|(key, tag)| (key.to_string(), Self::to_json(tag))).collect()),
+            Tag::IntArray(v) => Value::from(v.clone()),
+            Tag::LongArray(v) => Value::from(v.clone())
+        }
+    }
+
+    fn from_json(value: &Value) -> Result<OwnedTag, TagError> {
+        from_lossy(value, &mut String::new())
+    }
+}
+
+fn from_lossy(value: &Value, path: &mut String) -> Result<OwnedTag, TagError> {
+    match value {
+        Value::Bool(v) => Ok(OwnedTag::Byte(*v as i8)),
+        Value::Number(v) => number(v).ok_or_else(|| invalid(value, path)),
+        Value::String(v) => Ok(OwnedTag::String(v.clone())),
+        Value::Array(values) => {
+            let mut tags = Vec::with_capacity(values.len());
+            for (index, value) in values.iter().enumerate() {
+                let len = push_index(path, index);
+                tags.push(from_lossy(value, path)?);
+                path.truncate(len);
+            }
+            homogenize(tags).map_err(|kind| TagError::new(kind, path.as_str()))
+        }
+        Value::Object(values) => {
+            let mut compound = OwnedCompound::with_capacity(values.len());
+            for (key, value) in values.iter().filter(|(_, value)| !value.is_null()) {
+                let len = push_key(path, key);
+                compound.insert(key.clone(), from_lossy(value, path)?);
+                path.truncate(len);
+            }
+            Ok(OwnedTag::Compound(compound))
+        }
+        Value::Null => Err(invalid(value, path))
+    }
+}
+
+/// Returns the smallest of int, long and double that holds the number.
+fn number(v: &Number) -> Option<OwnedTag> {
+    match v.as_i64() {
+        Some(v) => Some(i32::try_from(v).map_or(OwnedTag::Long(v), OwnedTag::Int)),
+        None if v.is_f64() => Some(OwnedTag::Double(v.as_f64()?)),
+        None => None
+    }
+}
+
+/// Returns a list of the tags, which must be of the same type, except for numbers, which are all
+/// converted to the widest type among them.
+fn homogenize(tags: Vec<OwnedTag>) -> Result<OwnedTag, TagErrorKind> {
+    let rank = |id| match id {
+        TagId::Int => Some(0),
+        TagId::Long => Some(1),
+        TagId::Double => Some(2),
+        _ => None
+    };
+
+    let Some(first) = tags.first().map(OwnedTag::id) else {
+        return Ok(OwnedTag::List(OwnedList::new()));
+    };
+    let mut widest = first;
+    for tag in &tags {
+        let id = tag.id();
+        match (rank(widest), rank(id)) {
+            (Some(a), Some(b)) if b > a => widest = id,
+            (Some(_), Some(_)) => {}
+            _ if id != widest => return Err(TagErrorKind::MixedList { expected: widest, found: id }),
+            _ => {}
+        }
+    }
+
+    let tags = tags.into_iter().map(|tag| match (widest, tag) {
+        (TagId::Long, OwnedTag::Int(v)) => OwnedTag::Long(v.into()),
+        (TagId::Double, OwnedTag::Int(v)) => OwnedTag::Double(v.into()),
+        (TagId::Double, OwnedTag::Long(v)) => OwnedTag::Double(v as f64),
+        (_, tag) => tag
+    });
+    Ok(OwnedTag::List(tags.collect()))
+}
+
+impl JsonFormat for TypedJson {
+    fn to_json(tag: &Tag) -> Value {
+        let value = match tag {
+            Tag::End => Value::Null,
+            Tag::Byte(v) => Value::from(*v),
+            Tag::Short(v) => Value::from(*v),
+            Tag::Int(v) => Value::from(*v),
+            Tag::Long(v) => Value::from(*v),
+            Tag::Float(v) => float(widen(*v)),
+            Tag::Double(v) => float(*v),
+            Tag::ByteArray(v) => Value::from(v.to_vec()),
+            Tag::String(v) => Value::from(*v),
+            Tag::List(v) => {
+                let mut object = typed(tag.id(), v.iter().map(Self::to_json).collect());
+                object.insert("element_type".into(), type_name(v.element_type()).into());
+                return Value::Object(object);
+            }
+            Tag::Compound(v) => Value::Object(v.iter().map(|(key, tag)| (key.to_string(), Self::to_json(tag))).collect()),
+            Tag::IntArray(v) => Value::from(v.clone()),
+            Tag::LongArray(v) => Value::from(v.clone())
+        };
+        Value::Object(typed(tag.id(), value))
+    }
+
+    fn from_json(value: &Value) -> Result<OwnedTag, TagError> {
+        from_typed(value, &mut String::new())
+    }
+}
+
+fn from_typed(json: &Value, path: &mut String) -> Result<OwnedTag, TagError> {
+    let err = |kind| TagError::new(kind, path.as_str());
+    let Value::Object(object) = json else {
+        return Err(invalid(json, path));
+    };
+    let field = |key: &str| object.get(key).ok_or_else(|| err(TagErrorKind::MissingKey(key.to_owned())));
+
+    // End tags only mark the end of compounds and are not values, so they are rejected rather
+    // than put into the tree, where they would corrupt the NBT it is encoded into.
+    let id = field("type")?.as_str().and_then(type_id).filter(|id| *id != TagId::End).ok_or_else(|| invalid(&object["type"], path))?;
+    let value = field("value")?;
+    let mismatch = || invalid(value, path);
+
+    let tag = match id {
+        TagId::Byte => OwnedTag::Byte(int(value).ok_or_else(mismatch)?),
+        TagId::Short => OwnedTag::Short(int(value).ok_or_else(mismatch)?),
+        TagId::Int => OwnedTag::Int(int(value).ok_or_else(mismatch)?),
+        TagId::Long => OwnedTag::Long(int(value).ok_or_else(mismatch)?),
+        TagId::Float => OwnedTag::Float(float_of(value).ok_or_else(mismatch)? as f32),
+        TagId::Double => OwnedTag::Double(float_of(value).ok_or_else(mismatch)?),
+        TagId::ByteArray => OwnedTag::ByteArray(array(value).ok_or_else(mismatch)?),
+        TagId::String => OwnedTag::String(value.as_str().ok_or_else(mismatch)?.to_owned()),
+        TagId::List => {
+            let element_type = field("element_type")?;
+            let element_type = element_type.as_str().and_then(type_id).ok_or_else(|| invalid(element_type, path))?;
+            let values = value.as_array().ok_or_else(mismatch)?;
+            // Only empty lists have elements of the type end.
+            if element_type == TagId::End && !values.is_empty() {
+                return Err(invalid(&object["element_type"], path));
+            }
+
+            let mut list = OwnedList::with_element_type(element_type);
+            list.reserve(values.len());
+            for (index, value) in values.iter().enumerate() {
+                let len = push_index(path, index);
+                let tag = from_typed(value, path)?;
+                if tag.id() != element_type {
+                    return Err(TagError::new(TagErrorKind::MixedList { expected: element_type, found: tag.id() }, path.as_str()));
+                }
+                list.push(tag);
+                path.truncate(len);
+            }
+            OwnedTag::List(list)
+        }
+        TagId::Compound => {
+            let values = value.as_object().ok_or_else(mismatch)?;
+
+            let mut compound = OwnedCompound::with_capacity(values.len());
+            for (key, value) in values {
+                let len = push_key(path, key);
+                compound.insert(key.clone(), from_typed(value, path)?);
+                path.truncate(len);
+            }
+            OwnedTag::Compound(compound)
+        }
+        TagId::IntArray => OwnedTag::IntArray(array(value).ok_or_else(mismatch)?),
+        TagId::LongArray => OwnedTag::LongArray(array(value).ok_or_else(mismatch)?),
+        TagId::End => unreachable!("end tags are rejected above")
+    };
+    Ok(tag)
+}
+
+/// Returns an object holding the name of the type passed and the value.
+fn typed(id: TagId, value: Value) -> Map<String, Value> {
+    let mut object = Map::with_capacity(2);
+    object.insert("type".into(), type_name(id).into());
+    object.insert("value".into(), value);
+    object
+}
+
+fn type_name(id: TagId) -> &'static str {
+    match id {
+        TagId::End => "end",
+        TagId::Byte => "byte",
+        TagId::Short => "short",
+        TagId::Int => "int",
+        TagId::Long => "long",
+        TagId::Float => "float",
+        TagId::Double => "double",
+        TagId::ByteArray => "byte_array",
+        TagId::String => "string",
+        TagId::List => "list",
+        TagId::Compound => "compound",
+        TagId::IntArray => "int_array",
+        TagId::LongArray => "long_array"
+    }
+}
+
+fn type_id(name: &str) -> Option<TagId> {
+    let id = match name {
+        "end" => TagId::End,
+        "byte" => TagId::Byte,
+        "short" => TagId::Short,
+        "int" => TagId::Int,
+        "long" => TagId::Long,
+        "float" => TagId::Float,
+        "double" => TagId::Double,
+        "byte_array" => TagId::ByteArray,
+        "string" => TagId::String,
+        "list" => TagId::List,
+        "compound" => TagId::Compound,
+        "int_array" => TagId::IntArray,
+        "long_array" => TagId::LongArray,
+        _ => return None
+    };
+    Some(id)
+}
+
+/// Returns the float as the double with the shortest decimal representation that converts back to
+/// it, so that 0.1 is not converted to 0.10000000149011612.
+fn widen(v: f32) -> f64 {
+    v.to_string().parse().unwrap_or(v.into())
+}
+
+/// Converts a float to JSON, converting floats that are not finite to strings.
+fn float(v: f64) -> Value {
+    match v {
+        v if v.is_nan() => "NaN".into(),
+        f64::INFINITY => "Infinity".into(),
+        f64::NEG_INFINITY => "-Infinity".into(),
+        v => v.into()
+    }
+}
+
+fn float_of(value: &Value) -> Option<f64> {
+    match value.as_str() {
+        Some("NaN") => Some(f64::NAN),
+        Some("Infinity") => Some(f64::INFINITY),
+        Some("-Infinity") => Some(f64::NEG_INFINITY),
+        Some(_) => None,
+        None => value.as_f64()
+    }
+}
+
+/// Returns the integer if it is in the range of the type passed.
+fn int<T: TryFrom<i64>>(value: &Value) -> Option<T> {
+    T::try_from(value.as_i64()?).ok()
+}
+
+fn array<T: TryFrom<i64>>(value: &Value) -> Option<Vec<T>> {
+    value.as_array()?.iter().map(int).collect()
+}
+
+/// Returns the error for JSON that cannot be converted to a tag.
+fn invalid(value: &Value, path: &str) -> TagError {
+    TagError::new(TagErrorKind::InvalidValue(value.to_string()), path)
+}
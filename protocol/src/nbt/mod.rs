@@ -1,21 +1,37 @@
+pub mod de;
 pub mod encoding;
+pub mod file;
+pub mod json;
+pub mod patch;
+pub mod ser;
+pub mod snbt;
 pub mod tag;
+pub mod visit;
 
+pub use de::{from_reader, from_tag};
 pub use encoding::*;
+pub use patch::{diff, merge, Change, ListMerge, Op, Patch};
+pub use ser::{byte_array, int_array, long_array, to_tag, to_writer, SerdeError};
 pub use tag::*;
+pub use visit::{skip_tag, visit, ArrayIter, Visit, Visitor};
 
 use bytes::BufMut;
 use binary::{generate, Decode, Encode, Reader, Writer};
 
+/// The maximum depth of nested compounds and lists decoded, visited or skipped. Deeper tags fail
+/// decoding rather than overflowing the stack.
+pub(crate) const MAX_DEPTH: usize = 512;
+
 generate!(NBT, <E: Encoding>, Tag<'a>, 'a);
 generate!(NBTCompound, <E: Encoding>, Compound<'a>, 'a);
 generate!(NBTList, <E: Encoding>, List<'a>, 'a);
+generate!(OwnedNBT, <E: Encoding>, OwnedTag);
 
 impl<'a, E: Encoding> Encode for NBT<'a, E> {
     fn encode(&self, w: &mut Writer) {
         encode_tag_id(self.id(), w);
         E::write_str(w, "");
-        encode::<E>(&self, w);
+        encode::<E>(self, w);
     }
 }
 
@@ -27,6 +43,22 @@ impl<'a, E: Encoding> Decode<'a> for NBT<'a, E> {
     }
 }
 
+impl<E: Encoding> Encode for OwnedNBT<E> {
+    fn encode(&self, w: &mut Writer) {
+        encode_tag_id(self.id(), w);
+        E::write_str(w, "");
+        encode::<E>(&self.as_tag(), w);
+    }
+}
+
+impl<E: Encoding> Decode<'_> for OwnedNBT<E> {
+    fn decode(r: &mut Reader) -> Option<Self> {
+        let tag = decode_tag_id(r)?;
+        E::read_string(r)?;
+        Some(decode_owned::<E>(tag, r)?.into())
+    }
+}
+
 impl<'a, E:Encoding> Encode for NBTCompound<'a, E> {
     fn encode(&self, w: &mut Writer) {
         encode_tag_id(TagId::Compound, w);
@@ -96,7 +128,8 @@ impl<'a, E: Encoding> Decode<'a> for NBTList<'a, E> {
             len = 0;
         }
 
-        let mut list = List::with_capacity(len as usize);
+        let mut list = List::with_element_type(list_type);
+        list.reserve((len as usize).min(r.len()));
 
         for _ in 0..len {
             if let Some(element) = decode::<E>(list_type, r) {
@@ -119,7 +152,7 @@ pub fn encode_tag_id(id: TagId, w: &mut Writer) {
 /// Decodes a [`TagId`] from the Reader and returns it.
 #[inline]
 pub fn decode_tag_id(r: &mut Reader) -> Option<TagId> {
-    Some(TagId::from_byte(u8::decode(r)?)?)
+    TagId::from_byte(u8::decode(r)?)
 }
 
 /// Encodes a [`Tag`] of the specified [`TagId`] to the [`Writer`].
@@ -128,11 +161,11 @@ pub fn encode<E: Encoding>(tag: &Tag, w: &mut Writer) {
     match tag {
         Tag::End => {},
         Tag::Byte(v) => v.encode(w),
-        Tag::Short(v) => v.encode(w),
+        Tag::Short(v) => E::write_short(w, *v),
         Tag::Int(v) => E::write_int(w, *v),
         Tag::Long(v) => E::write_long(w, *v),
-        Tag::Float(v) => v.encode(w),
-        Tag::Double(v) => v.encode(w),
+        Tag::Float(v) => E::write_float(w, *v),
+        Tag::Double(v) => E::write_double(w, *v),
         Tag::ByteArray(v) => {
             E::write_int(w, v.len() as i32);
 
@@ -176,20 +209,88 @@ pub fn encode<E: Encoding>(tag: &Tag, w: &mut Writer) {
     }
 }
 
+/// Decodes an [`OwnedTag`] of the specified [`TagId`] from the [`Reader`] with the specified
+/// [`Encoding`]. Unlike with [`decode`], strings are read with [`Encoding::read_string`], so that
+/// strings that cannot be borrowed from the Reader, such as those in modified UTF-8 holding NUL,
+/// are decoded as well. Tags nested deeper than 512 compounds and lists are not decoded.
+pub fn decode_owned<E: Encoding>(id: TagId, r: &mut Reader) -> Option<OwnedTag> {
+    decode_owned_at::<E>(id, r, 0)
+}
+
+fn decode_owned_at<E: Encoding>(id: TagId, r: &mut Reader, depth: usize) -> Option<OwnedTag> {
+    if depth > MAX_DEPTH {
+        return None;
+    }
+
+    match id {
+        TagId::String => Some(OwnedTag::String(E::read_string(r)?.into_owned())),
+        TagId::List => {
+            let list_type = decode_tag_id(r)?;
+            let mut len = E::read_int(r)?;
+
+            if list_type == TagId::End {
+                len = 0;
+            }
+
+            let mut list = OwnedList::with_element_type(list_type);
+            list.reserve((len.max(0) as usize).min(r.len()));
+
+            for _ in 0..len {
+                list.push(decode_owned_at::<E>(list_type, r, depth + 1)?);
+            }
+
+            Some(OwnedTag::List(list))
+        }
+        TagId::Compound => {
+            let mut compound = OwnedCompound::new();
+
+            loop {
+                let tag = decode_tag_id(r)?;
+
+                // We encountered the end of a compound tag. Break the loop.
+                if tag == TagId::End {
+                    break;
+                }
+
+                let name = E::read_string(r)?.into_owned();
+                let value = decode_owned_at::<E>(tag, r, depth + 1)?;
+                compound.insert(name, value);
+            }
+
+            Some(OwnedTag::Compound(compound))
+        }
+        _ => decode::<E>(id, r).map(OwnedTag::from)
+    }
+}
+
 /// Decodes a [`Tag`] of the specified [`TagId`] from the [`Reader`] and returns it if successfully
-/// decoded. Uses the specified [`Encoding`] to decode the tag.
+/// decoded. Uses the specified [`Encoding`] to decode the tag. Tags nested deeper than 512
+/// compounds and lists are not decoded.
 pub fn decode<'a, E: Encoding>(id: TagId, r: &mut Reader<'a>) -> Option<Tag<'a>> {
+    decode_at::<E>(id, r, 0)
+}
+
+fn decode_at<'a, E: Encoding>(id: TagId, r: &mut Reader<'a>, depth: usize) -> Option<Tag<'a>> {
+    if depth > MAX_DEPTH {
+        return None;
+    }
+
     match id {
         TagId::End => None,
         TagId::Byte => Some(Tag::Byte(i8::decode(r)?)),
-        TagId::Short => Some(Tag::Short(i16::decode(r)?)),
+        TagId::Short => Some(Tag::Short(E::read_short(r)?)),
         TagId::Int => Some(Tag::Int(E::read_int(r)?)),
         TagId::Long => Some(Tag::Long(E::read_long(r)?)),
-        TagId::Float => Some(Tag::Float(f32::decode(r)?)),
-        TagId::Double => Some(Tag::Double(f64::decode(r)?)),
+        TagId::Float => Some(Tag::Float(E::read_float(r)?)),
+        TagId::Double => Some(Tag::Double(E::read_double(r)?)),
         TagId::ByteArray => {
-            let len = E::read_int(r)? as usize;
-            let slice = &r[..len];
+            let len = usize::try_from(E::read_int(r)?).ok()?;
+            if r.len() < len {
+                return None;
+            }
+
+            let (slice, rest) = r.split_at(len);
+            *r = rest;
 
             unsafe {
                 let val: &[i8] = std::mem::transmute(slice);
@@ -208,10 +309,11 @@ pub fn decode<'a, E: Encoding>(id: TagId, r: &mut Reader<'a>) -> Option<Tag<'a>>
                 len = 0;
             }
 
-            let mut list = List::with_capacity(len as usize);
+            let mut list = List::with_element_type(list_type);
+            list.reserve((len as usize).min(r.len()));
 
             for _ in 0..len {
-                if let Some(element) = decode::<E>(list_type, r) {
+                if let Some(element) = decode_at::<E>(list_type, r, depth + 1) {
                     list.push(element);
                 } else {
                     return None;
@@ -233,7 +335,7 @@ pub fn decode<'a, E: Encoding>(id: TagId, r: &mut Reader<'a>) -> Option<Tag<'a>>
 
                 let name = E::read_str(r)?;
 
-                if let Some(value) = decode::<E>(tag, r) {
+                if let Some(value) = decode_at::<E>(tag, r, depth + 1) {
                     compound.insert(name, value);
                 } else {
                     return None;
@@ -244,7 +346,7 @@ pub fn decode<'a, E: Encoding>(id: TagId, r: &mut Reader<'a>) -> Option<Tag<'a>>
         }
         TagId::IntArray => {
             let len = E::read_int(r)?;
-            let mut array = Vec::with_capacity(len as usize);
+            let mut array = Vec::with_capacity((len as usize).min(r.len()));
 
             for _ in 0..len {
                 let data = E::read_int(r)?;
@@ -255,7 +357,7 @@ pub fn decode<'a, E: Encoding>(id: TagId, r: &mut Reader<'a>) -> Option<Tag<'a>>
         }
         TagId::LongArray => {
             let len = E::read_int(r)?;
-            let mut array = Vec::with_capacity(len as usize);
+            let mut array = Vec::with_capacity((len as usize).min(r.len()));
 
             for _ in 0..len {
                 let data = E::read_long(r)?;
@@ -0,0 +1,279 @@
+use crate::nbt::{OwnedList, OwnedTag, Tag, TagError, TagErrorKind, TagId};
+use crate::nbt::tag::{push_index, push_key};
+use crate::owned_compound;
+
+/// Change is a difference between two tags found by [`diff`], at the path of the tag that differs.
+/// Paths are formatted as accepted by [`Tag::path`], and the type of the tags involved is that of
+/// the tags held by the change.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Change {
+    /// The key or element at the path is only held by the new tag.
+    Added { path: String, value: OwnedTag },
+    /// The key or element at the path is only held by the old tag.
+    Removed { path: String, value: OwnedTag },
+    /// The tag at the path differs in value or in type.
+    Changed { path: String, from: OwnedTag, to: OwnedTag }
+}
+
+impl Change {
+    /// Returns the path of the tag that differs.
+    pub fn path(&self) -> &str {
+        match self {
+            Change::Added { path, .. } | Change::Removed { path, .. } | Change::Changed { path, .. } => path
+        }
+    }
+}
+
+/// Returns the changes that turn the tag `from` into the tag `to`. Compounds are compared key by
+/// key, regardless of the order of the keys, and lists of the same type element by element. Lists
+/// of different types and all other tags that differ are returned as a single change.
+///
+/// Elements removed from the end of a list are returned last to first, so that the changes can be
+/// applied in order, such as with [`Patch::apply`].
+pub fn diff(from: &Tag, to: &Tag) -> Vec<Change> {
+    let mut changes = Vec::new();
+    diff_at(&mut String::new(), from, to, &mut changes);
+    changes
+}
+
+fn diff_at(path: &mut String, from: &Tag, to: &Tag, changes: &mut Vec<Change>) {
+    match (from, to) {
+        (Tag::Compound(a), Tag::Compound(b)) => {
+            for (key, value) in a.iter() {
+                let len = push_key(path, key);
+                match b.get(key) {
+                    Some(other) => diff_at(path, value, other, changes),
+                    None => changes.push(Change::Removed { path: path.clone(), value: value.into() })
+                }
+                path.truncate(len);
+            }
+            for (key, value) in b.iter().filter(|(key, _)| !a.contains_key(*key)) {
+                let len = push_key(path, key);
+                changes.push(Change::Added { path: path.clone(), value: value.into() });
+                path.truncate(len);
+            }
+        }
+        (Tag::List(a), Tag::List(b)) if a.element_type() == b.element_type() => {
+            for (index, (value, other)) in a.iter().zip(b.iter()).enumerate() {
+                let len = push_index(path, index);
+                diff_at(path, value, other, changes);
+                path.truncate(len);
+            }
+            for index in (b.len()..a.len()).rev() {
+                let len = push_index(path, index);
+                changes.push(Change::Removed { path: path.clone(), value: (&a[index]).into() });
+                path.truncate(len);
+            }
+            for (index, value) in b.iter().enumerate().skip(a.len()) {
+                let len = push_index(path, index);
+                changes.push(Change::Added { path: path.clone(), value: value.into() });
+                path.truncate(len);
+            }
+        }
+        (a, b) if !same(a, b) => {
+            changes.push(Change::Changed { path: path.clone(), from: a.into(), to: b.into() });
+        }
+        _ => {}
+    }
+}
+
+/// Reports whether two tags that are not both compounds or lists of the same type are the same.
+/// Floats are compared by their bits, so that NaN is the same as itself.
+fn same(a: &Tag, b: &Tag) -> bool {
+    match (a, b) {
+        (Tag::Float(a), Tag::Float(b)) => a.to_bits() == b.to_bits(),
+        (Tag::Double(a), Tag::Double(b)) => a.to_bits() == b.to_bits(),
+        (a, b) => a == b
+    }
+}
+
+/// ListMerge is the strategy with which [`merge`] merges lists.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum ListMerge {
+    /// The list merged into is replaced with the other list.
+    #[default]
+    Replace,
+    /// The elements of the other list are appended to the list merged into.
+    Append,
+    /// The elements of the other list are merged into the elements at the same index, and the
+    /// elements past the end of the list merged into are appended.
+    MergeByIndex
+}
+
+impl ListMerge {
+    fn name(self) -> &'static str {
+        match self {
+            ListMerge::Replace => "replace",
+            ListMerge::Append => "append",
+            ListMerge::MergeByIndex => "merge_by_index"
+        }
+    }
+
+    fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "replace" => Some(ListMerge::Replace),
+            "append" => Some(ListMerge::Append),
+            "merge_by_index" => Some(ListMerge::MergeByIndex),
+            _ => None
+        }
+    }
+}
+
+/// Merges the tag `source` into the tag `target`. The keys of compounds are merged recursively,
+/// keeping the keys only held by `target`, and lists are merged with the [`ListMerge`] passed.
+/// Lists of different types and all other tags are replaced with those of `source`.
+pub fn merge(target: &mut OwnedTag, source: &OwnedTag, lists: ListMerge) {
+    match (target, source) {
+        (OwnedTag::Compound(a), OwnedTag::Compound(b)) => {
+            for (key, value) in b.iter() {
+                match a.get_mut(key) {
+                    Some(tag) => merge(tag, value, lists),
+                    None => {
+                        a.insert(key.clone(), value.clone());
+                    }
+                }
+            }
+        }
+        (OwnedTag::List(a), OwnedTag::List(b)) if mergeable(a, b) && lists != ListMerge::Replace => {
+            if lists == ListMerge::MergeByIndex {
+                for (tag, value) in a.iter_mut().zip(b.iter()) {
+                    merge(tag, value, lists);
+                }
+            }
+            let skip = if lists == ListMerge::Append { 0 } else { a.len() };
+            a.extend(b.iter().skip(skip).cloned());
+        }
+        (target, source) => *target = source.clone()
+    }
+}
+
+/// Reports whether the elements of the two lists may be put in the same list.
+fn mergeable(a: &OwnedList, b: &OwnedList) -> bool {
+    a.is_empty() || b.is_empty() || a.element_type() == b.element_type()
+}
+
+/// Op is a single operation of a [`Patch`], applied at a path formatted as accepted by
+/// [`OwnedTag::path`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum Op {
+    /// Sets the tag at the path, as [`OwnedTag::set_path`] does.
+    Set { path: String, value: OwnedTag },
+    /// Removes the tag at the path, as [`OwnedTag::remove_path`] does.
+    Remove { path: String },
+    /// Merges the value into the tag at the path with [`merge`].
+    Merge { path: String, value: OwnedTag, lists: ListMerge }
+}
+
+/// Patch is a list of operations applied to a tag in order, such as those used to migrate player
+/// data or update items. Patches may be created from the changes returned by [`diff`], and are
+/// stored as NBT with [`Patch::to_tag`] and [`Patch::from_tag`].
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Patch {
+    pub ops: Vec<Op>
+}
+
+impl Patch {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the patch that turns the tag `from` into the tag `to`.
+    pub fn diff(from: &Tag, to: &Tag) -> Self {
+        diff(from, to).into_iter().collect()
+    }
+
+    /// Applies the operations of the patch to the tag in order. If an operation fails, the
+    /// operations before it remain applied.
+    pub fn apply(&self, tag: &mut OwnedTag) -> Result<(), TagError> {
+        for op in &self.ops {
+            match op {
+                Op::Set { path, value } => {
+                    tag.set_path(path, value.clone())?;
+                }
+                Op::Remove { path } => {
+                    tag.remove_path(path)?;
+                }
+                Op::Merge { path, value, lists } => merge(tag.path_mut(path)?, value, *lists)
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns the patch as a list of compounds, one for each operation. Every compound holds the
+    /// name of the operation as `op`, which is `set`, `remove` or `merge`, its `path` and, if it
+    /// has them, its `value` and the strategy of merging `lists`.
+    pub fn to_tag(&self) -> OwnedTag {
+        let ops = self.ops.iter().map(|op| {
+            let compound = match op {
+                Op::Set { path, value } => owned_compound! {
+                    "op" => "set",
+                    "path" => path.as_str(),
+                    "value" => value.clone()
+                },
+                Op::Remove { path } => owned_compound! {
+                    "op" => "remove",
+                    "path" => path.as_str()
+                },
+                Op::Merge { path, value, lists } => owned_compound! {
+                    "op" => "merge",
+                    "path" => path.as_str(),
+                    "value" => value.clone(),
+                    "lists" => lists.name()
+                }
+            };
+            OwnedTag::Compound(compound)
+        });
+
+        let mut list = OwnedList::with_element_type(TagId::Compound);
+        list.extend(ops);
+        OwnedTag::List(list)
+    }
+
+    /// Returns the patch stored in the tag as returned by [`Patch::to_tag`].
+    pub fn from_tag(tag: &OwnedTag) -> Result<Self, TagError> {
+        let mut ops = Vec::new();
+        for (index, op) in tag.try_as_list()?.iter().enumerate() {
+            ops.push(Self::op_from_tag(op).map_err(|err| {
+                let path = if err.path.is_empty() { format!("[{index}]") } else { format!("[{index}].{}", err.path) };
+                err.at(path)
+            })?);
+        }
+        Ok(Self { ops })
+    }
+
+    fn op_from_tag(tag: &OwnedTag) -> Result<Op, TagError> {
+        let path = tag.get_string("path")?.clone();
+
+        match tag.get_string("op")?.as_str() {
+            "set" => Ok(Op::Set { path, value: tag.get("value")?.clone() }),
+            "remove" => Ok(Op::Remove { path }),
+            "merge" => {
+                let lists = match tag.get_string("lists") {
+                    Ok(name) => ListMerge::from_name(name).ok_or_else(|| {
+                        TagError::new(TagErrorKind::InvalidValue(name.clone()), "lists")
+                    })?,
+                    Err(TagError { kind: TagErrorKind::MissingKey(_), .. }) => ListMerge::default(),
+                    Err(err) => return Err(err)
+                };
+                Ok(Op::Merge { path, value: tag.get("value")?.clone(), lists })
+            }
+            op => Err(TagError::new(TagErrorKind::InvalidValue(op.to_owned()), "op"))
+        }
+    }
+}
+
+impl From<Change> for Op {
+    /// Returns the operation that applies the change.
+    fn from(change: Change) -> Self {
+        match change {
+            Change::Added { path, value } | Change::Changed { path, to: value, .. } => Op::Set { path, value },
+            Change::Removed { path, .. } => Op::Remove { path }
+        }
+    }
+}
+
+impl FromIterator<Change> for Patch {
+    fn from_iter<I: IntoIterator<Item = Change>>(iter: I) -> Self {
+        Self { ops: iter.into_iter().map(Op::from).collect() }
+    }
+}
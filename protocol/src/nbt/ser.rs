@@ -0,0 +1,413 @@
+use std::fmt::{self, Display, Formatter};
+use serde::ser::{self, Serialize};
+use binary::Writer;
+use crate::nbt::file::encode_root;
+use crate::nbt::{Encoding, OwnedCompound, OwnedList, OwnedTag, TagId};
+
+/// The names of the newtype structs that the field attributes of [`byte_array`], [`int_array`] and
+/// [`long_array`] wrap sequences in, so that the serializer writes them as arrays.
+pub(crate) const BYTE_ARRAY: &str = "__nbt_byte_array";
+pub(crate) const INT_ARRAY: &str = "__nbt_int_array";
+pub(crate) const LONG_ARRAY: &str = "__nbt_long_array";
+
+/// SerdeError is returned when a value cannot be serialized to or deserialized from NBT.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SerdeError {
+    message: String
+}
+
+impl SerdeError {
+    pub(crate) fn new(message: impl Display) -> Self {
+        Self { message: message.to_string() }
+    }
+}
+
+impl Display for SerdeError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl std::error::Error for SerdeError {}
+
+impl ser::Error for SerdeError {
+    fn custom<T: Display>(msg: T) -> Self {
+        Self::new(msg)
+    }
+}
+
+/// Serializes the value to a tag.
+///
+/// Structs and maps are serialized as compounds, of which fields that are None are left out.
+/// Sequences and tuples are serialized as lists, which must be homogeneous, unless the field is
+/// annotated with `#[serde(with = "nbt::byte_array")]` or the int and long variants of it. Booleans are
+/// serialized as bytes, and unsigned integers as the signed tag of the same size with the same
+/// bits. Enums are serialized as in JSON: unit variants as strings and the other variants as a
+/// compound with the name of the variant as only key.
+pub fn to_tag<T: Serialize + ?Sized>(value: &T) -> Result<OwnedTag, SerdeError> {
+    match value.serialize(Serializer)? {
+        OwnedTag::End => Err(SerdeError::new("cannot serialize None as NBT")),
+        tag => Ok(tag)
+    }
+}
+
+/// Serializes the value and encodes it to the Writer with the [`Encoding`] passed, in the same way
+/// as [`NBT`](crate::nbt::NBT), with an empty root name.
+pub fn to_writer<E: Encoding, T: Serialize + ?Sized>(value: &T, w: &mut Writer) -> Result<(), SerdeError> {
+    let tag = to_tag(value)?;
+    encode_root::<E>("", &tag.as_tag(), w);
+    Ok(())
+}
+
+/// Serializer serializes values to an [`OwnedTag`]. None is serialized as [`OwnedTag::End`],
+/// which is left out of compounds and rejected elsewhere.
+pub struct Serializer;
+
+/// Converts a list produced by serializing a sequence to the array passed.
+fn to_array(tag: OwnedTag, array: TagId) -> Result<OwnedTag, SerdeError> {
+    let mismatch = |found: TagId| SerdeError::new(format!("cannot serialize {found:?} in {array:?}"));
+
+    let list = match tag {
+        OwnedTag::ByteArray(_) if array == TagId::ByteArray => return Ok(tag),
+        OwnedTag::IntArray(_) if array == TagId::IntArray => return Ok(tag),
+        OwnedTag::LongArray(_) if array == TagId::LongArray => return Ok(tag),
+        OwnedTag::List(list) => list,
+        tag => return Err(mismatch(tag.id()))
+    };
+
+    match array {
+        TagId::ByteArray => list.into_iter().map(|tag| match tag {
+            OwnedTag::Byte(v) => Ok(v),
+            tag => Err(mismatch(tag.id()))
+        }).collect::<Result<_, _>>().map(OwnedTag::ByteArray),
+        TagId::IntArray => list.into_iter().map(|tag| match tag {
+            OwnedTag::Int(v) => Ok(v),
+            tag => Err(mismatch(tag.id()))
+        }).collect::<Result<_, _>>().map(OwnedTag::IntArray),
+        _ => list.into_iter().map(|tag| match tag {
+            OwnedTag::Long(v) => Ok(v),
+            tag => Err(mismatch(tag.id()))
+        }).collect::<Result<_, _>>().map(OwnedTag::LongArray)
+    }
+}
+
+/// Wraps the tag in a compound with the name of the variant as only key.
+fn variant(name: &str, tag: OwnedTag) -> OwnedTag {
+    let mut compound = OwnedCompound::new();
+    compound.insert(name.to_owned(), tag);
+    OwnedTag::Compound(compound)
+}
+
+impl ser::Serializer for Serializer {
+    type Ok = OwnedTag;
+    type Error = SerdeError;
+
+    type SerializeSeq = SerializeList;
+    type SerializeTuple = SerializeList;
+    type SerializeTupleStruct = SerializeList;
+    type SerializeTupleVariant = SerializeList;
+    type SerializeMap = SerializeCompound;
+    type SerializeStruct = SerializeCompound;
+    type SerializeStructVariant = SerializeCompound;
+
+    fn serialize_bool(self, v: bool) -> Result<OwnedTag, SerdeError> {
+        Ok(OwnedTag::Byte(v as i8))
+    }
+
+    fn serialize_i8(self, v: i8) -> Result<OwnedTag, SerdeError> {
+        Ok(OwnedTag::Byte(v))
+    }
+
+    fn serialize_i16(self, v: i16) -> Result<OwnedTag, SerdeError> {
+        Ok(OwnedTag::Short(v))
+    }
+
+    fn serialize_i32(self, v: i32) -> Result<OwnedTag, SerdeError> {
+        Ok(OwnedTag::Int(v))
+    }
+
+    fn serialize_i64(self, v: i64) -> Result<OwnedTag, SerdeError> {
+        Ok(OwnedTag::Long(v))
+    }
+
+    fn serialize_u8(self, v: u8) -> Result<OwnedTag, SerdeError> {
+        Ok(OwnedTag::Byte(v as i8))
+    }
+
+    fn serialize_u16(self, v: u16) -> Result<OwnedTag, SerdeError> {
+        Ok(OwnedTag::Short(v as i16))
+    }
+
+    fn serialize_u32(self, v: u32) -> Result<OwnedTag, SerdeError> {
+        Ok(OwnedTag::Int(v as i32))
+    }
+
+    fn serialize_u64(self, v: u64) -> Result<OwnedTag, SerdeError> {
+        Ok(OwnedTag::Long(v as i64))
+    }
+
+    fn serialize_f32(self, v: f32) -> Result<OwnedTag, SerdeError> {
+        Ok(OwnedTag::Float(v))
+    }
+
+    fn serialize_f64(self, v: f64) -> Result<OwnedTag, SerdeError> {
+        Ok(OwnedTag::Double(v))
+    }
+
+    fn serialize_char(self, v: char) -> Result<OwnedTag, SerdeError> {
+        Ok(OwnedTag::String(v.to_string()))
+    }
+
+    fn serialize_str(self, v: &str) -> Result<OwnedTag, SerdeError> {
+        Ok(OwnedTag::String(v.to_owned()))
+    }
+
+    fn serialize_bytes(self, v: &[u8]) -> Result<OwnedTag, SerdeError> {
+        Ok(OwnedTag::ByteArray(v.iter().map(|b| *b as i8).collect()))
+    }
+
+    fn serialize_none(self) -> Result<OwnedTag, SerdeError> {
+        Ok(OwnedTag::End)
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Result<OwnedTag, SerdeError> {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<OwnedTag, SerdeError> {
+        Err(SerdeError::new("cannot serialize () as NBT"))
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<OwnedTag, SerdeError> {
+        Ok(OwnedTag::Compound(OwnedCompound::new()))
+    }
+
+    fn serialize_unit_variant(self, _name: &'static str, _index: u32, variant: &'static str) -> Result<OwnedTag, SerdeError> {
+        Ok(OwnedTag::String(variant.to_owned()))
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(self, name: &'static str, value: &T) -> Result<OwnedTag, SerdeError> {
+        let tag = value.serialize(self)?;
+        match name {
+            BYTE_ARRAY => to_array(tag, TagId::ByteArray),
+            INT_ARRAY => to_array(tag, TagId::IntArray),
+            LONG_ARRAY => to_array(tag, TagId::LongArray),
+            _ => Ok(tag)
+        }
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(self, _name: &'static str, _index: u32, variant_name: &'static str, value: &T) -> Result<OwnedTag, SerdeError> {
+        Ok(variant(variant_name, to_tag(value)?))
+    }
+
+    fn serialize_seq(self, len: Option<usize>) -> Result<SerializeList, SerdeError> {
+        Ok(SerializeList { list: OwnedList::with_capacity(len.unwrap_or_default()), variant: None })
+    }
+
+    fn serialize_tuple(self, len: usize) -> Result<SerializeList, SerdeError> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_struct(self, _name: &'static str, len: usize) -> Result<SerializeList, SerdeError> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_variant(self, _name: &'static str, _index: u32, variant: &'static str, len: usize) -> Result<SerializeList, SerdeError> {
+        Ok(SerializeList { list: OwnedList::with_capacity(len), variant: Some(variant) })
+    }
+
+    fn serialize_map(self, len: Option<usize>) -> Result<SerializeCompound, SerdeError> {
+        Ok(SerializeCompound {
+            compound: OwnedCompound::with_capacity(len.unwrap_or_default()),
+            key: None,
+            variant: None
+        })
+    }
+
+    fn serialize_struct(self, _name: &'static str, len: usize) -> Result<SerializeCompound, SerdeError> {
+        self.serialize_map(Some(len))
+    }
+
+    fn serialize_struct_variant(self, _name: &'static str, _index: u32, variant: &'static str, len: usize) -> Result<SerializeCompound, SerdeError> {
+        Ok(SerializeCompound { compound: OwnedCompound::with_capacity(len), key: None, variant: Some(variant) })
+    }
+}
+
+/// SerializeList serializes sequences and tuples to a list, checking that all elements are of the
+/// same type.
+pub struct SerializeList {
+    list: OwnedList,
+    variant: Option<&'static str>
+}
+
+impl SerializeList {
+    fn push<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), SerdeError> {
+        let tag = to_tag(value)?;
+        if !self.list.is_empty() && tag.id() != self.list.element_type() {
+            return Err(SerdeError::new(format!("cannot serialize {:?} in list of {:?}", tag.id(), self.list.element_type())));
+        }
+        self.list.push(tag);
+        Ok(())
+    }
+
+    fn finish(self) -> OwnedTag {
+        let list = OwnedTag::List(self.list);
+        match self.variant {
+            Some(name) => variant(name, list),
+            None => list
+        }
+    }
+}
+
+impl ser::SerializeSeq for SerializeList {
+    type Ok = OwnedTag;
+    type Error = SerdeError;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), SerdeError> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<OwnedTag, SerdeError> {
+        Ok(self.finish())
+    }
+}
+
+impl ser::SerializeTuple for SerializeList {
+    type Ok = OwnedTag;
+    type Error = SerdeError;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), SerdeError> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<OwnedTag, SerdeError> {
+        Ok(self.finish())
+    }
+}
+
+impl ser::SerializeTupleStruct for SerializeList {
+    type Ok = OwnedTag;
+    type Error = SerdeError;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), SerdeError> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<OwnedTag, SerdeError> {
+        Ok(self.finish())
+    }
+}
+
+impl ser::SerializeTupleVariant for SerializeList {
+    type Ok = OwnedTag;
+    type Error = SerdeError;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), SerdeError> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<OwnedTag, SerdeError> {
+        Ok(self.finish())
+    }
+}
+
+/// SerializeCompound serializes maps and structs to a compound, leaving out values that are None.
+pub struct SerializeCompound {
+    compound: OwnedCompound,
+    key: Option<String>,
+    variant: Option<&'static str>
+}
+
+impl SerializeCompound {
+    fn insert<T: Serialize + ?Sized>(&mut self, key: String, value: &T) -> Result<(), SerdeError> {
+        match value.serialize(Serializer)? {
+            OwnedTag::End => {}
+            tag => {
+                self.compound.insert(key, tag);
+            }
+        }
+        Ok(())
+    }
+
+    fn finish(self) -> OwnedTag {
+        let compound = OwnedTag::Compound(self.compound);
+        match self.variant {
+            Some(name) => variant(name, compound),
+            None => compound
+        }
+    }
+}
+
+impl ser::SerializeMap for SerializeCompound {
+    type Ok = OwnedTag;
+    type Error = SerdeError;
+
+    fn serialize_key<T: Serialize + ?Sized>(&mut self, key: &T) -> Result<(), SerdeError> {
+        match key.serialize(Serializer)? {
+            OwnedTag::String(key) => {
+                self.key = Some(key);
+                Ok(())
+            }
+            tag => Err(SerdeError::new(format!("cannot serialize {:?} as key of a compound", tag.id())))
+        }
+    }
+
+    fn serialize_value<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), SerdeError> {
+        let key = self.key.take().ok_or_else(|| SerdeError::new("value serialized before its key"))?;
+        self.insert(key, value)
+    }
+
+    fn end(self) -> Result<OwnedTag, SerdeError> {
+        Ok(self.finish())
+    }
+}
+
+impl ser::SerializeStruct for SerializeCompound {
+    type Ok = OwnedTag;
+    type Error = SerdeError;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, key: &'static str, value: &T) -> Result<(), SerdeError> {
+        self.insert(key.to_owned(), value)
+    }
+
+    fn end(self) -> Result<OwnedTag, SerdeError> {
+        Ok(self.finish())
+    }
+}
+
+impl ser::SerializeStructVariant for SerializeCompound {
+    type Ok = OwnedTag;
+    type Error = SerdeError;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, key: &'static str, value: &T) -> Result<(), SerdeError> {
+        self.insert(key.to_owned(), value)
+    }
+
+    fn end(self) -> Result<OwnedTag, SerdeError> {
+        Ok(self.finish())
+    }
+}
+
+macro_rules! array_module {
+    ($module:ident, $name:ident, $doc:literal) => {
+        #[doc = $doc]
+        ///
+        /// Deserializing does not require the attribute, as arrays deserialize into any sequence,
+        /// but it is provided so that the attribute can be used with `with`.
+        pub mod $module {
+            use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+            pub fn serialize<T: Serialize + ?Sized, S: Serializer>(value: &T, serializer: S) -> Result<S::Ok, S::Error> {
+                serializer.serialize_newtype_struct(super::$name, value)
+            }
+
+            pub fn deserialize<'de, T: Deserialize<'de>, D: Deserializer<'de>>(deserializer: D) -> Result<T, D::Error> {
+                T::deserialize(deserializer)
+            }
+        }
+    };
+}
+
+array_module!(byte_array, BYTE_ARRAY, "Serializes a sequence of `i8` or `u8` as a byte array rather than a list, with `#[serde(with = \"nbt::byte_array\")]`.");
+array_module!(int_array, INT_ARRAY, "Serializes a sequence of `i32` or `u32` as an int array rather than a list, with `#[serde(with = \"nbt::int_array\")]`.");
+array_module!(long_array, LONG_ARRAY, "Serializes a sequence of `i64` or `u64` as a long array rather than a list, with `#[serde(with = \"nbt::long_array\")]`.");
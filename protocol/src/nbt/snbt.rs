@@ -0,0 +1,536 @@
+use std::fmt::{self, Display, Formatter, Write};
+use crate::nbt::{OwnedCompound, OwnedList, OwnedTag, Tag};
+
+/// The maximum depth of nested compounds and lists accepted by the parser.
+const MAX_DEPTH: usize = 512;
+
+/// The indentation of every level of nesting written in pretty mode.
+const INDENT: &str = "    ";
+
+/// SnbtErrorKind is the reason SNBT could not be parsed.
+#[derive(Debug, Clone, PartialEq)]
+pub enum SnbtErrorKind {
+    /// The input ended before the tag was complete.
+    UnexpectedEnd,
+    /// A character was found where it was not expected.
+    UnexpectedChar(char),
+    /// A backslash in a quoted string was followed by a character that cannot be escaped.
+    InvalidEscape(char),
+    /// A number with a type suffix is out of the range of its type.
+    NumberOutOfRange,
+    /// The elements of a list are not all of the same type.
+    MixedList,
+    /// An element of a typed array is not of the type of the array.
+    InvalidArrayElement,
+    /// Compounds and lists are nested deeper than the parser allows.
+    TooDeep,
+    /// The input continues after the tag.
+    TrailingData
+}
+
+/// SnbtError is returned when SNBT could not be parsed. The position is the byte offset in the
+/// input at which the error was found.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SnbtError {
+    pub kind: SnbtErrorKind,
+    pub position: usize
+}
+
+impl Display for SnbtError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match &self.kind {
+            SnbtErrorKind::UnexpectedEnd => write!(f, "unexpected end of input")?,
+            SnbtErrorKind::UnexpectedChar(c) => write!(f, "unexpected character {c:?}")?,
+            SnbtErrorKind::InvalidEscape(c) => write!(f, "invalid escape sequence \\{c}")?,
+            SnbtErrorKind::NumberOutOfRange => write!(f, "number out of range")?,
+            SnbtErrorKind::MixedList => write!(f, "list elements are not all of the same type")?,
+            SnbtErrorKind::InvalidArrayElement => write!(f, "array element is not of the type of the array")?,
+            SnbtErrorKind::TooDeep => write!(f, "tags are nested too deep")?,
+            SnbtErrorKind::TrailingData => write!(f, "unexpected data after the tag")?
+        }
+        write!(f, " at position {}", self.position)
+    }
+}
+
+impl std::error::Error for SnbtError {}
+
+/// Snbt is a tag parsed from SNBT by [`parse`]. It owns the strings and byte arrays of the tag,
+/// as strings with escape sequences cannot be borrowed from the input. The tag is borrowed from it
+/// with [`Snbt::tag`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct Snbt {
+    root: OwnedTag
+}
+
+impl Snbt {
+    /// Returns the tag parsed, borrowing its strings and byte arrays.
+    pub fn tag(&self) -> Tag<'_> {
+        self.root.as_tag()
+    }
+
+    /// Returns the tag parsed as an [`OwnedTag`].
+    pub fn into_owned(self) -> OwnedTag {
+        self.root
+    }
+}
+
+/// Parses a tag from SNBT, such as `{name: "Steve", pos: [I; 1, 64, -3], health: 20.0f}`.
+///
+/// Numbers are typed by their suffix: `b` for bytes, `s` for shorts, `L` for longs, `f` for floats
+/// and `d` for doubles. Numbers without suffix are ints, or doubles if they have a fraction or an
+/// exponent. `true` and `false` are bytes. Keys and strings may be unquoted if they only consist
+/// of letters, digits and `_-.+`, and are otherwise quoted with double or single quotes, in which
+/// `\\`, `\"`, `\'`, `\n`, `\r` and `\t` are escaped.
+pub fn parse(input: &str) -> Result<Snbt, SnbtError> {
+    let mut parser = Parser { input, pos: 0 };
+
+    let root = parser.value(0)?;
+    parser.skip_whitespace();
+    if parser.pos != input.len() {
+        return Err(parser.error(SnbtErrorKind::TrailingData));
+    }
+
+    Ok(Snbt { root })
+}
+
+/// Returns whether the character may be part of an unquoted key or string.
+fn is_unquoted(c: char) -> bool {
+    c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '.' | '+')
+}
+
+/// Parser parses SNBT from a string, keeping track of the position for errors.
+struct Parser<'a> {
+    input: &'a str,
+    pos: usize
+}
+
+impl Parser<'_> {
+    fn error(&self, kind: SnbtErrorKind) -> SnbtError {
+        SnbtError { kind, position: self.pos }
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.input[self.pos..].chars().next()
+    }
+
+    fn next(&mut self) -> Result<char, SnbtError> {
+        let c = self.peek().ok_or_else(|| self.error(SnbtErrorKind::UnexpectedEnd))?;
+        self.pos += c.len_utf8();
+        Ok(c)
+    }
+
+    fn skip_whitespace(&mut self) {
+        while let Some(c) = self.peek().filter(|c| c.is_whitespace()) {
+            self.pos += c.len_utf8();
+        }
+    }
+
+    /// Skips whitespace and consumes the character expected.
+    fn expect(&mut self, expected: char) -> Result<(), SnbtError> {
+        self.skip_whitespace();
+        match self.peek() {
+            Some(c) if c == expected => {
+                self.pos += 1;
+                Ok(())
+            }
+            Some(c) => Err(self.error(SnbtErrorKind::UnexpectedChar(c))),
+            None => Err(self.error(SnbtErrorKind::UnexpectedEnd))
+        }
+    }
+
+    /// Skips whitespace and consumes the character passed if it is next.
+    fn accept(&mut self, c: char) -> bool {
+        self.skip_whitespace();
+        if self.peek() == Some(c) {
+            self.pos += 1;
+            return true;
+        }
+        false
+    }
+
+    fn value(&mut self, depth: usize) -> Result<OwnedTag, SnbtError> {
+        if depth > MAX_DEPTH {
+            return Err(self.error(SnbtErrorKind::TooDeep));
+        }
+
+        self.skip_whitespace();
+        match self.peek() {
+            Some('{') => self.compound(depth),
+            Some('[') => self.list(depth),
+            Some('"' | '\'') => Ok(OwnedTag::String(self.quoted()?)),
+            Some(c) if is_unquoted(c) => {
+                let start = self.pos;
+                let literal = self.unquoted();
+                literal_to_tag(literal).map_err(|kind| SnbtError { kind, position: start })
+            }
+            Some(c) => Err(self.error(SnbtErrorKind::UnexpectedChar(c))),
+            None => Err(self.error(SnbtErrorKind::UnexpectedEnd))
+        }
+    }
+
+    fn compound(&mut self, depth: usize) -> Result<OwnedTag, SnbtError> {
+        self.expect('{')?;
+
+        let mut entries = OwnedCompound::new();
+        if self.accept('}') {
+            return Ok(OwnedTag::Compound(entries));
+        }
+
+        loop {
+            let key = self.key()?;
+            self.expect(':')?;
+            let value = self.value(depth + 1)?;
+
+            // Like in NBT, a key that occurs more than once takes the last value.
+            entries.insert(key, value);
+
+            if self.accept('}') {
+                return Ok(OwnedTag::Compound(entries));
+            }
+            self.expect(',')?;
+        }
+    }
+
+    fn key(&mut self) -> Result<String, SnbtError> {
+        self.skip_whitespace();
+        match self.peek() {
+            Some('"' | '\'') => self.quoted(),
+            Some(c) if is_unquoted(c) => Ok(self.unquoted().to_owned()),
+            Some(c) => Err(self.error(SnbtErrorKind::UnexpectedChar(c))),
+            None => Err(self.error(SnbtErrorKind::UnexpectedEnd))
+        }
+    }
+
+    fn list(&mut self, depth: usize) -> Result<OwnedTag, SnbtError> {
+        self.expect('[')?;
+
+        let rest = &self.input[self.pos..];
+        let array = match rest.as_bytes() {
+            [prefix @ (b'B' | b'I' | b'L'), b';', ..] => Some(*prefix),
+            _ => None
+        };
+        if let Some(prefix) = array {
+            self.pos += 2;
+            return self.array(prefix);
+        }
+
+        let mut elements = OwnedList::new();
+        if self.accept(']') {
+            return Ok(OwnedTag::List(elements));
+        }
+
+        loop {
+            self.skip_whitespace();
+            let start = self.pos;
+            let element = self.value(depth + 1)?;
+            if elements.first().is_some_and(|first| first.id() != element.id()) {
+                return Err(SnbtError { kind: SnbtErrorKind::MixedList, position: start });
+            }
+            elements.push(element);
+
+            if self.accept(']') {
+                return Ok(OwnedTag::List(elements));
+            }
+            self.expect(',')?;
+        }
+    }
+
+    /// Parses the elements of a typed array after its `[B;`, `[I;` or `[L;` prefix. Elements of
+    /// byte and long arrays may leave out their suffix.
+    fn array(&mut self, prefix: u8) -> Result<OwnedTag, SnbtError> {
+        let mut bytes = Vec::new();
+        let mut ints = Vec::new();
+        let mut longs = Vec::new();
+
+        if !self.accept(']') {
+            loop {
+                self.skip_whitespace();
+                let start = self.pos;
+                if !self.peek().is_some_and(is_unquoted) {
+                    return Err(match self.peek() {
+                        Some(c) => self.error(SnbtErrorKind::UnexpectedChar(c)),
+                        None => self.error(SnbtErrorKind::UnexpectedEnd)
+                    });
+                }
+
+                let invalid = SnbtError { kind: SnbtErrorKind::InvalidArrayElement, position: start };
+                let element = literal_to_tag(self.unquoted()).map_err(|kind| SnbtError { kind, position: start })?;
+                match (prefix, element) {
+                    (b'B', OwnedTag::Byte(v)) => bytes.push(v),
+                    (b'B', OwnedTag::Int(v)) => bytes.push(i8::try_from(v).map_err(|_| invalid)?),
+                    (b'I', OwnedTag::Int(v)) => ints.push(v),
+                    (b'L', OwnedTag::Long(v)) => longs.push(v),
+                    (b'L', OwnedTag::Int(v)) => longs.push(v as i64),
+                    _ => return Err(invalid)
+                }
+
+                if self.accept(']') {
+                    break;
+                }
+                self.expect(',')?;
+            }
+        }
+
+        Ok(match prefix {
+            b'B' => OwnedTag::ByteArray(bytes),
+            b'I' => OwnedTag::IntArray(ints),
+            _ => OwnedTag::LongArray(longs)
+        })
+    }
+
+    fn unquoted(&mut self) -> &str {
+        let start = self.pos;
+        while self.peek().is_some_and(is_unquoted) {
+            self.pos += 1;
+        }
+        &self.input[start..self.pos]
+    }
+
+    fn quoted(&mut self) -> Result<String, SnbtError> {
+        let quote = self.next()?;
+        let mut s = String::new();
+
+        loop {
+            match self.next()? {
+                c if c == quote => return Ok(s),
+                '\\' => {
+                    let escaped = self.next()?;
+                    s.push(match escaped {
+                        '\\' | '"' | '\'' => escaped,
+                        'n' => '\n',
+                        'r' => '\r',
+                        't' => '\t',
+                        c => {
+                            self.pos -= c.len_utf8();
+                            return Err(self.error(SnbtErrorKind::InvalidEscape(c)));
+                        }
+                    });
+                }
+                c => s.push(c)
+            }
+        }
+    }
+}
+
+/// Returns whether the string is an integer: digits with an optional sign.
+fn is_integer(s: &str) -> bool {
+    let digits = s.strip_prefix(['-', '+']).unwrap_or(s);
+    !digits.is_empty() && digits.bytes().all(|b| b.is_ascii_digit())
+}
+
+/// Returns whether the string is a decimal number: digits with an optional sign, fraction and
+/// exponent, or one of the special values written for infinite floats and NaN.
+fn is_decimal(s: &str) -> bool {
+    let unsigned = s.strip_prefix(['-', '+']).unwrap_or(s);
+    if matches!(unsigned, "Infinity" | "NaN") {
+        return true;
+    }
+
+    let (mantissa, exponent) = match unsigned.find(['e', 'E']) {
+        Some(i) => (&unsigned[..i], Some(&unsigned[i + 1..])),
+        None => (unsigned, None)
+    };
+
+    let (int, fraction) = mantissa.split_once('.').unwrap_or((mantissa, ""));
+    let digits = |s: &str| s.bytes().all(|b| b.is_ascii_digit());
+
+    (!int.is_empty() || !fraction.is_empty()) && digits(int) && digits(fraction) && exponent.is_none_or(is_integer)
+}
+
+/// Parses a decimal number checked with [`is_decimal`].
+fn parse_decimal(s: &str) -> f64 {
+    match s.trim_start_matches('+') {
+        "Infinity" => f64::INFINITY,
+        "-Infinity" => f64::NEG_INFINITY,
+        "NaN" | "-NaN" => f64::NAN,
+        s => s.parse().unwrap_or_default()
+    }
+}
+
+/// Converts an unquoted literal to a number if it has the syntax of one, and to a string
+/// otherwise.
+fn literal_to_tag(literal: &str) -> Result<OwnedTag, SnbtErrorKind> {
+    match literal {
+        "true" => return Ok(OwnedTag::Byte(1)),
+        "false" => return Ok(OwnedTag::Byte(0)),
+        _ => {}
+    }
+
+    let out_of_range = |_| SnbtErrorKind::NumberOutOfRange;
+
+    if let Some((number, suffix)) = literal.len().checked_sub(1).map(|i| literal.split_at(i)) {
+        match suffix {
+            "b" | "B" if is_integer(number) => return number.parse().map(OwnedTag::Byte).map_err(out_of_range),
+            "s" | "S" if is_integer(number) => return number.parse().map(OwnedTag::Short).map_err(out_of_range),
+            "l" | "L" if is_integer(number) => return number.parse().map(OwnedTag::Long).map_err(out_of_range),
+            "f" | "F" if is_decimal(number) => return Ok(OwnedTag::Float(parse_decimal(number) as f32)),
+            "d" | "D" if is_decimal(number) => return Ok(OwnedTag::Double(parse_decimal(number))),
+            _ => {}
+        }
+    }
+
+    if is_integer(literal) {
+        return literal.parse().map(OwnedTag::Int).map_err(out_of_range);
+    }
+    if is_decimal(literal) && literal.contains(['.', 'e', 'E']) {
+        return Ok(OwnedTag::Double(parse_decimal(literal)));
+    }
+
+    Ok(OwnedTag::String(literal.to_owned()))
+}
+
+/// Writes the tag as compact SNBT, without any whitespace. End tags cannot be represented in
+/// SNBT and are written as nothing.
+pub fn to_string(tag: &Tag) -> String {
+    let mut s = String::new();
+    let _ = write_tag(&mut s, tag, None);
+    s
+}
+
+/// Writes the tag as pretty SNBT, with every entry of a compound and every compound or list in a
+/// list on its own indented line.
+pub fn to_string_pretty(tag: &Tag) -> String {
+    let mut s = String::new();
+    let _ = write_tag(&mut s, tag, Some(0));
+    s
+}
+
+/// Writes the tag as SNBT. The indentation level is None in compact mode.
+fn write_tag(w: &mut impl Write, tag: &Tag, indent: Option<usize>) -> fmt::Result {
+    let separator = if indent.is_some() { ", " } else { "," };
+
+    match tag {
+        Tag::End => Ok(()),
+        Tag::Byte(v) => write!(w, "{v}b"),
+        Tag::Short(v) => write!(w, "{v}s"),
+        Tag::Int(v) => write!(w, "{v}"),
+        Tag::Long(v) => write!(w, "{v}L"),
+        Tag::Float(v) => {
+            write_decimal(w, *v)?;
+            w.write_char('f')
+        }
+        Tag::Double(v) => {
+            write_decimal(w, *v)?;
+            w.write_char('d')
+        }
+        Tag::ByteArray(v) => write_array(w, 'B', v.iter().map(|v| format!("{v}b")), separator),
+        Tag::String(v) => write_quoted(w, v),
+        Tag::IntArray(v) => write_array(w, 'I', v.iter().map(|v| v.to_string()), separator),
+        Tag::LongArray(v) => write_array(w, 'L', v.iter().map(|v| format!("{v}L")), separator),
+        Tag::List(v) => {
+            let nested = v.iter().any(|tag| matches!(tag, Tag::List(_) | Tag::Compound(_)));
+
+            w.write_char('[')?;
+            for (i, item) in v.iter().enumerate() {
+                if i > 0 {
+                    w.write_char(',')?;
+                }
+                match indent {
+                    Some(level) if nested => {
+                        newline(w, level + 1)?;
+                        write_tag(w, item, Some(level + 1))?;
+                    }
+                    Some(level) => {
+                        if i > 0 {
+                            w.write_char(' ')?;
+                        }
+                        write_tag(w, item, Some(level))?;
+                    }
+                    None => write_tag(w, item, None)?
+                }
+            }
+            if let Some(level) = indent.filter(|_| nested) {
+                newline(w, level)?;
+            }
+            w.write_char(']')
+        }
+        Tag::Compound(v) => {
+            #[cfg_attr(feature = "preserve_order", allow(unused_mut))]
+            let mut entries: Vec<_> = v.iter().collect();
+            // Without the preserve_order feature, compounds are unordered, so their keys are
+            // sorted to write the same tag the same way.
+            #[cfg(not(feature = "preserve_order"))]
+            entries.sort_unstable_by_key(|(k, _)| **k);
+
+            w.write_char('{')?;
+            for (i, (key, item)) in entries.into_iter().enumerate() {
+                if i > 0 {
+                    w.write_char(',')?;
+                }
+                if let Some(level) = indent {
+                    newline(w, level + 1)?;
+                }
+                write_key(w, key)?;
+                w.write_char(':')?;
+                if indent.is_some() {
+                    w.write_char(' ')?;
+                }
+                write_tag(w, item, indent.map(|level| level + 1))?;
+            }
+            if let Some(level) = indent.filter(|_| !v.is_empty()) {
+                newline(w, level)?;
+            }
+            w.write_char('}')
+        }
+    }
+}
+
+/// Writes a float or double formatted with `Debug`, which is the shortest representation that
+/// parses back to the same value. Infinities are written as `Infinity`.
+fn write_decimal<T: fmt::Debug + Into<f64> + Copy>(w: &mut impl Write, v: T) -> fmt::Result {
+    match v.into() {
+        f64::INFINITY => w.write_str("Infinity"),
+        f64::NEG_INFINITY => w.write_str("-Infinity"),
+        _ => write!(w, "{v:?}")
+    }
+}
+
+fn write_array(w: &mut impl Write, prefix: char, items: impl Iterator<Item = String>, separator: &str) -> fmt::Result {
+    write!(w, "[{prefix};")?;
+    for (i, item) in items.enumerate() {
+        if i > 0 {
+            w.write_str(separator)?;
+        } else if separator.len() > 1 {
+            w.write_char(' ')?;
+        }
+        w.write_str(&item)?;
+    }
+    w.write_char(']')
+}
+
+fn write_key(w: &mut impl Write, key: &str) -> fmt::Result {
+    if !key.is_empty() && key.chars().all(is_unquoted) {
+        return w.write_str(key);
+    }
+    write_quoted(w, key)
+}
+
+fn write_quoted(w: &mut impl Write, s: &str) -> fmt::Result {
+    w.write_char('"')?;
+    for c in s.chars() {
+        match c {
+            '\\' => w.write_str("\\\\")?,
+            '"' => w.write_str("\\\"")?,
+            '\n' => w.write_str("\\n")?,
+            '\r' => w.write_str("\\r")?,
+            '\t' => w.write_str("\\t")?,
+            c => w.write_char(c)?
+        }
+    }
+    w.write_char('"')
+}
+
+fn newline(w: &mut impl Write, level: usize) -> fmt::Result {
+    w.write_char('\n')?;
+    for _ in 0..level {
+        w.write_str(INDENT)?;
+    }
+    Ok(())
+}
+
+/// Formats the tag as SNBT: compact by default and pretty with the alternate flag (`{:#}`).
+impl Display for Tag<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write_tag(f, self, f.alternate().then_some(0))
+    }
+}
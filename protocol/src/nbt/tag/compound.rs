@@ -1,8 +1,33 @@
-use std::collections::HashMap;
+use std::borrow::Borrow;
+use std::hash::Hash;
+#[cfg(not(feature = "preserve_order"))]
+use std::collections::HashMap as MapImpl;
+#[cfg(feature = "preserve_order")]
+use indexmap::IndexMap as MapImpl;
 use super::Tag;
 
-/// Compound represents a heterogeneous collection of objects indexed by string keys.
-pub type Compound<'a> = HashMap<&'a str, Tag<'a>>;
+/// Map is the map compounds are built on. With the `preserve_order` feature, it is an `IndexMap`
+/// that keeps its keys in the order they were inserted or decoded in, so that decoding and encoding
+/// NBT reproduces the input bytes exactly. Otherwise it is a [`HashMap`](std::collections::HashMap),
+/// which does not keep any order.
+pub type Map<K, V> = MapImpl<K, V>;
+
+/// Compound represents a heterogeneous collection of objects indexed by string keys. See [`Map`]
+/// for the order its keys are kept in.
+///
+/// Keys are unique, so if a compound is decoded that holds the same key more than once, only the
+/// last value is kept. With the `preserve_order` feature, the value takes the place of the first
+/// occurrence of the key. Such compounds are therefore not encoded into the same bytes again.
+pub type Compound<'a> = Map<&'a str, Tag<'a>>;
+
+/// Removes the key from the map and returns its value. With the `preserve_order` feature, the
+/// other keys keep their order.
+pub fn remove_key<K: Borrow<Q> + Hash + Eq, V, Q: Hash + Eq + ?Sized>(map: &mut Map<K, V>, key: &Q) -> Option<V> {
+    #[cfg(feature = "preserve_order")]
+    return map.shift_remove(key);
+    #[cfg(not(feature = "preserve_order"))]
+    return map.remove(key);
+}
 
 /*
     Creates and returns a Compound Tag. Provided below is an example use case.
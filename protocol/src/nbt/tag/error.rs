@@ -0,0 +1,75 @@
+use std::fmt::{self, Display, Formatter};
+use crate::nbt::TagId;
+
+/// TagErrorKind is the reason a tag could not be accessed.
+#[derive(Debug, Clone, PartialEq)]
+pub enum TagErrorKind {
+    /// The tag was of another type than the one expected.
+    TypeMismatch { expected: TagId, found: TagId },
+    /// The compound did not hold the key.
+    MissingKey(String),
+    /// The index was past the end of the list.
+    IndexOutOfBounds { index: usize, len: usize },
+    /// The path could not be parsed because of the character at the position passed.
+    InvalidPath(usize),
+    /// The value could not be set in the list, as it holds elements of another type.
+    MixedList { expected: TagId, found: TagId },
+    /// The tag held a value that is not valid where it was found, such as an unknown operation of a
+    /// patch.
+    InvalidValue(String),
+}
+
+impl Display for TagErrorKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            TagErrorKind::TypeMismatch { expected, found } => write!(f, "expected {expected:?}, found {found:?}"),
+            TagErrorKind::MissingKey(key) => write!(f, "missing key {key:?}"),
+            TagErrorKind::IndexOutOfBounds { index, len } => write!(f, "index {index} out of bounds for list of length {len}"),
+            TagErrorKind::InvalidPath(position) => write!(f, "invalid path at position {position}"),
+            TagErrorKind::MixedList { expected, found } => write!(f, "cannot put {found:?} in list of {expected:?}"),
+            TagErrorKind::InvalidValue(value) => write!(f, "invalid value {value:?}"),
+        }
+    }
+}
+
+/// TagError is returned by the fallible accessors of tags, such as [`Tag::try_as_int`] and
+/// [`Tag::path`], instead of panicking.
+///
+/// [`Tag::try_as_int`]: crate::nbt::Tag::try_as_int
+/// [`Tag::path`]: crate::nbt::Tag::path
+#[derive(Debug, Clone, PartialEq)]
+pub struct TagError {
+    pub kind: TagErrorKind,
+    /// The path of the tag at which the error occurred, such as `Items[0].tag`. It is empty if
+    /// the error occurred at the tag the accessor was called on.
+    pub path: String,
+}
+
+impl TagError {
+    pub fn new(kind: TagErrorKind, path: impl Into<String>) -> Self {
+        Self { kind, path: path.into() }
+    }
+
+    /// Returns an error for a tag of the type found where another type was expected.
+    pub fn mismatch(expected: TagId, found: TagId) -> Self {
+        Self::new(TagErrorKind::TypeMismatch { expected, found }, "")
+    }
+
+    /// Returns the error with the path passed.
+    pub fn at(mut self, path: impl Into<String>) -> Self {
+        self.path = path.into();
+        self
+    }
+}
+
+impl Display for TagError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        if self.path.is_empty() {
+            write!(f, "{}", self.kind)
+        } else {
+            write!(f, "{}: {}", self.path, self.kind)
+        }
+    }
+}
+
+impl std::error::Error for TagError {}
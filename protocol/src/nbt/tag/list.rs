@@ -1,16 +1,157 @@
-use crate::nbt::{Tag, TagId};
+use std::ops::{Deref, DerefMut};
+use crate::nbt::{OwnedTag, Tag, TagId};
 
 /// List represents a collection of Tag objects. It is a homogenous collection of Tag objects, in other
 /// words, objects of same type only.
-pub type List<'a> = Vec<Tag<'a>>;
+pub type List<'a> = TypedList<Tag<'a>>;
 
-/// Returns the TagId of elements contained inside the List.
-pub fn get_list_type(list: &List) -> TagId{
-    if list.len() == 0 {
-        return TagId::End;
+/// OwnedList is a [`List`] that owns its elements.
+pub type OwnedList = TypedList<OwnedTag>;
+
+/// Element is implemented for the tags that may be held by a [`TypedList`].
+pub trait Element {
+    fn id(&self) -> TagId;
+}
+
+impl Element for Tag<'_> {
+    fn id(&self) -> TagId {
+        Tag::id(self)
+    }
+}
+
+impl Element for OwnedTag {
+    fn id(&self) -> TagId {
+        OwnedTag::id(self)
+    }
+}
+
+/// TypedList is a list of tags along with the type of its elements. The type of a list is that of
+/// its first element, but lists also remember the type they were created or decoded with, so that
+/// an empty list is encoded with the same type it was decoded with.
+///
+/// TypedList dereferences to the [`Vec`] of its elements.
+#[derive(Clone)]
+pub struct TypedList<T> {
+    tags: Vec<T>,
+    element_type: TagId
+}
+
+impl<T: Element> TypedList<T> {
+    pub fn new() -> Self {
+        Self::with_element_type(TagId::End)
+    }
+
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self { tags: Vec::with_capacity(capacity), element_type: TagId::End }
+    }
+
+    /// Returns an empty list of which the elements are of the type passed.
+    pub fn with_element_type(element_type: TagId) -> Self {
+        Self { tags: Vec::new(), element_type }
+    }
+
+    /// Returns the type of the elements of the list. For empty lists, this is the type the list
+    /// was created or decoded with, which is [`TagId::End`] unless set otherwise.
+    pub fn element_type(&self) -> TagId {
+        match self.tags.first() {
+            Some(tag) => tag.id(),
+            None => self.element_type
+        }
+    }
+
+    /// Returns a list with the elements of this list converted with the function passed, keeping
+    /// the type of this list if it is empty.
+    pub fn map<'b, U: Element>(&'b self, f: impl FnMut(&'b T) -> U) -> TypedList<U> {
+        TypedList { tags: self.tags.iter().map(f).collect(), element_type: self.element_type() }
+    }
+
+    /// Returns the list with its elements converted with the function passed, keeping the type of
+    /// this list if it is empty.
+    pub fn into_map<U: Element>(self, f: impl FnMut(T) -> U) -> TypedList<U> {
+        let element_type = self.element_type();
+        TypedList { tags: self.tags.into_iter().map(f).collect(), element_type }
+    }
+
+    /// Returns the elements of the list.
+    pub fn into_vec(self) -> Vec<T> {
+        self.tags
+    }
+}
+
+impl<T: Element> Default for TypedList<T> {
+    fn default() -> Self {
+        Self::new()
     }
+}
 
-    list[0].id()
+impl<T: Element + PartialEq> PartialEq for TypedList<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.element_type() == other.element_type() && self.tags == other.tags
+    }
+}
+
+impl<T: std::fmt::Debug> std::fmt::Debug for TypedList<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?}", self.tags)
+    }
+}
+
+impl<T> Deref for TypedList<T> {
+    type Target = Vec<T>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.tags
+    }
+}
+
+impl<T> DerefMut for TypedList<T> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.tags
+    }
+}
+
+impl<T: Element> From<Vec<T>> for TypedList<T> {
+    fn from(tags: Vec<T>) -> Self {
+        Self { tags, element_type: TagId::End }
+    }
+}
+
+impl<T: Element> FromIterator<T> for TypedList<T> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        Vec::from_iter(iter).into()
+    }
+}
+
+impl<T> IntoIterator for TypedList<T> {
+    type Item = T;
+    type IntoIter = std::vec::IntoIter<T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.tags.into_iter()
+    }
+}
+
+impl<'b, T> IntoIterator for &'b TypedList<T> {
+    type Item = &'b T;
+    type IntoIter = std::slice::Iter<'b, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.tags.iter()
+    }
+}
+
+impl<'b, T> IntoIterator for &'b mut TypedList<T> {
+    type Item = &'b mut T;
+    type IntoIter = std::slice::IterMut<'b, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.tags.iter_mut()
+    }
+}
+
+/// Returns the TagId of elements contained inside the List.
+pub fn get_list_type(list: &List) -> TagId{
+    list.element_type()
 }
 
 /*
@@ -32,4 +173,4 @@ macro_rules! list {
         )*
         list
     }};
-}
\ No newline at end of file
+}
@@ -1,12 +1,61 @@
 pub mod list;
 pub mod compound;
+pub mod error;
+pub mod owned;
+mod path;
 
 pub use list::*;
 pub use compound::*;
+pub use error::*;
+pub use owned::*;
+pub(crate) use path::{push_index, push_key};
+
+/// Variant is implemented for the inner types of the variants of a tag type, [`Tag`] and
+/// [`OwnedTag`], so that they can be extracted generically, such as with [`Tag::try_as`] and
+/// [`Tag::path_as`].
+pub trait Variant<T>: Sized {
+    /// The TagId of the variant.
+    const ID: TagId;
+
+    fn of(tag: &T) -> Option<&Self>;
+    fn of_mut(tag: &mut T) -> Option<&mut Self>;
+}
 
 macro_rules! impl_tag {
-    ($variant:ident, $type:ty, $as:ident, $as_mut:ident) => {
+    ($variant:ident, $type:ty, $as:ident, $as_mut:ident, $try_as:ident, $try_as_mut:ident, $get:ident) => {
+        impl<'a> Variant<Tag<'a>> for $type {
+            const ID: TagId = TagId::$variant;
+
+            fn of<'t>(tag: &'t Tag<'a>) -> Option<&'t Self> {
+                match tag {
+                    Tag::$variant(val) => Some(val),
+                    _ => None,
+                }
+            }
+
+            fn of_mut<'t>(tag: &'t mut Tag<'a>) -> Option<&'t mut Self> {
+                match tag {
+                    Tag::$variant(val) => Some(val),
+                    _ => None,
+                }
+            }
+        }
+
         impl<'a> Tag<'a> {
+            pub fn $try_as(&self) -> Result<&$type, TagError> {
+                self.try_as::<$type>()
+            }
+
+            pub fn $try_as_mut(&mut self) -> Result<&mut $type, TagError> {
+                self.try_as_mut::<$type>()
+            }
+
+            /// Returns the value of the key in the compound, or an error if the tag is not a
+            /// compound, does not hold the key or its value is of another type.
+            pub fn $get(&self, key: &str) -> Result<&$type, TagError> {
+                self.get_as::<$type>(key)
+            }
+
             pub fn $as(&self) -> &$type {
                 match self {
                     Tag::$variant(val) => val,
@@ -40,7 +89,7 @@ macro_rules! impl_tag {
 }
 
 macro_rules! generate_tags {
-    ($($variant:ident => $type:ty, $as:ident, $as_mut:ident),*) => {
+    ($($variant:ident => $type:ty, $as:ident, $as_mut:ident, $try_as:ident, $try_as_mut:ident, $get:ident),*) => {
         /// TagId is an enumeration of Tag Ids for different types of Tags.
         #[derive(Default, Debug, PartialEq, Clone, Copy)]
         #[repr(u8)]
@@ -88,22 +137,22 @@ macro_rules! generate_tags {
         }
 
         $(
-            impl_tag!($variant, $type, $as, $as_mut);
+            impl_tag!($variant, $type, $as, $as_mut, $try_as, $try_as_mut, $get);
         )*
     };
 }
 
 generate_tags!(
-    Byte => i8, as_byte, as_mut_byte,
-    Short => i16, as_short, as_mut_short,
-    Int => i32, as_int, as_mut_int,
-    Long => i64, as_long, as_mut_long,
-    Float => f32, as_float, as_mut_float,
-    Double => f64, as_double, as_mut_double,
-    ByteArray => &'a [i8], as_byte_array, as_mut_byte_array,
-    String => &'a str, as_string, as_mut_string,
-    List => List<'a>, as_list, as_mut_list,
-    Compound => Compound<'a>, as_compound, as_mut_compound,
-    IntArray => Vec<i32>, as_int_array, as_mut_int_array,
-    LongArray => Vec<i64>, as_long_array, as_mut_long_array
+    Byte => i8, as_byte, as_mut_byte, try_as_byte, try_as_mut_byte, get_byte,
+    Short => i16, as_short, as_mut_short, try_as_short, try_as_mut_short, get_short,
+    Int => i32, as_int, as_mut_int, try_as_int, try_as_mut_int, get_int,
+    Long => i64, as_long, as_mut_long, try_as_long, try_as_mut_long, get_long,
+    Float => f32, as_float, as_mut_float, try_as_float, try_as_mut_float, get_float,
+    Double => f64, as_double, as_mut_double, try_as_double, try_as_mut_double, get_double,
+    ByteArray => &'a [i8], as_byte_array, as_mut_byte_array, try_as_byte_array, try_as_mut_byte_array, get_byte_array,
+    String => &'a str, as_string, as_mut_string, try_as_string, try_as_mut_string, get_string,
+    List => List<'a>, as_list, as_mut_list, try_as_list, try_as_mut_list, get_list,
+    Compound => Compound<'a>, as_compound, as_mut_compound, try_as_compound, try_as_mut_compound, get_compound,
+    IntArray => Vec<i32>, as_int_array, as_mut_int_array, try_as_int_array, try_as_mut_int_array, get_int_array,
+    LongArray => Vec<i64>, as_long_array, as_mut_long_array, try_as_long_array, try_as_mut_long_array, get_long_array
 );
\ No newline at end of file
@@ -0,0 +1,231 @@
+use super::{Compound, Map, OwnedList, Tag, TagError, TagId, Variant};
+
+/// OwnedCompound is a [`Compound`] that owns its keys and values.
+pub type OwnedCompound = Map<String, OwnedTag>;
+
+macro_rules! impl_owned_tag {
+    ($variant:ident, $type:ty, $as:ident, $as_mut:ident, $try_as:ident, $try_as_mut:ident, $get:ident) => {
+        impl Variant<OwnedTag> for $type {
+            const ID: TagId = TagId::$variant;
+
+            fn of(tag: &OwnedTag) -> Option<&Self> {
+                match tag {
+                    OwnedTag::$variant(val) => Some(val),
+                    _ => None,
+                }
+            }
+
+            fn of_mut(tag: &mut OwnedTag) -> Option<&mut Self> {
+                match tag {
+                    OwnedTag::$variant(val) => Some(val),
+                    _ => None,
+                }
+            }
+        }
+
+        impl OwnedTag {
+            pub fn $try_as(&self) -> Result<&$type, TagError> {
+                self.try_as::<$type>()
+            }
+
+            pub fn $try_as_mut(&mut self) -> Result<&mut $type, TagError> {
+                self.try_as_mut::<$type>()
+            }
+
+            /// Returns the value of the key in the compound, or an error if the tag is not a
+            /// compound, does not hold the key or its value is of another type.
+            pub fn $get(&self, key: &str) -> Result<&$type, TagError> {
+                self.get_as::<$type>(key)
+            }
+
+            pub fn $as(&self) -> &$type {
+                match self {
+                    OwnedTag::$variant(val) => val,
+                    _ => panic!("Cannot convert OwnedTag object to inner type"),
+                }
+            }
+
+            pub fn $as_mut(&mut self) -> &mut $type {
+                match self {
+                    OwnedTag::$variant(val) => val,
+                    _ => panic!("Cannot convert OwnedTag object to inner type"),
+                }
+            }
+        }
+
+        impl From<$type> for OwnedTag {
+            fn from(value: $type) -> Self {
+                OwnedTag::$variant(value)
+            }
+        }
+    };
+}
+
+/// OwnedTag is an NBT tag that owns all of its contents, unlike [`Tag`], which borrows its strings
+/// and byte arrays from the buffer it was decoded from. It can be built from strings created at
+/// runtime and kept after the buffer is dropped.
+///
+/// OwnedTag is encoded through [`Tag`]: [`OwnedTag::as_tag`] borrows the tree as a [`Tag`] to
+/// encode it. It is decoded with [`decode_owned`](crate::nbt::decode_owned), or converted from a
+/// decoded [`Tag`] with [`OwnedTag::from`].
+#[derive(Default, Debug, PartialEq, Clone)]
+pub enum OwnedTag {
+    #[default]
+    End,
+    Byte(i8),
+    Short(i16),
+    Int(i32),
+    Long(i64),
+    Float(f32),
+    Double(f64),
+    ByteArray(Vec<i8>),
+    String(String),
+    List(OwnedList),
+    Compound(OwnedCompound),
+    IntArray(Vec<i32>),
+    LongArray(Vec<i64>),
+}
+
+impl OwnedTag {
+    pub fn id(&self) -> TagId {
+        match self {
+            OwnedTag::End => TagId::End,
+            OwnedTag::Byte(_) => TagId::Byte,
+            OwnedTag::Short(_) => TagId::Short,
+            OwnedTag::Int(_) => TagId::Int,
+            OwnedTag::Long(_) => TagId::Long,
+            OwnedTag::Float(_) => TagId::Float,
+            OwnedTag::Double(_) => TagId::Double,
+            OwnedTag::ByteArray(_) => TagId::ByteArray,
+            OwnedTag::String(_) => TagId::String,
+            OwnedTag::List(_) => TagId::List,
+            OwnedTag::Compound(_) => TagId::Compound,
+            OwnedTag::IntArray(_) => TagId::IntArray,
+            OwnedTag::LongArray(_) => TagId::LongArray,
+        }
+    }
+
+    /// Returns the tag as a [`Tag`] borrowing the strings and byte arrays of this tag. Only the
+    /// containers and the int and long arrays are allocated.
+    pub fn as_tag(&self) -> Tag<'_> {
+        match self {
+            OwnedTag::End => Tag::End,
+            OwnedTag::Byte(v) => Tag::Byte(*v),
+            OwnedTag::Short(v) => Tag::Short(*v),
+            OwnedTag::Int(v) => Tag::Int(*v),
+            OwnedTag::Long(v) => Tag::Long(*v),
+            OwnedTag::Float(v) => Tag::Float(*v),
+            OwnedTag::Double(v) => Tag::Double(*v),
+            OwnedTag::ByteArray(v) => Tag::ByteArray(v),
+            OwnedTag::String(v) => Tag::String(v),
+            OwnedTag::List(v) => Tag::List(v.map(OwnedTag::as_tag)),
+            OwnedTag::Compound(v) => Tag::Compound(v.iter().map(|(k, v)| (k.as_str(), v.as_tag())).collect::<Compound>()),
+            OwnedTag::IntArray(v) => Tag::IntArray(v.clone()),
+            OwnedTag::LongArray(v) => Tag::LongArray(v.clone()),
+        }
+    }
+}
+
+impl Tag<'_> {
+    /// Returns a copy of the tag that owns all of its contents.
+    pub fn to_owned_tag(&self) -> OwnedTag {
+        match self {
+            Tag::End => OwnedTag::End,
+            Tag::Byte(v) => OwnedTag::Byte(*v),
+            Tag::Short(v) => OwnedTag::Short(*v),
+            Tag::Int(v) => OwnedTag::Int(*v),
+            Tag::Long(v) => OwnedTag::Long(*v),
+            Tag::Float(v) => OwnedTag::Float(*v),
+            Tag::Double(v) => OwnedTag::Double(*v),
+            Tag::ByteArray(v) => OwnedTag::ByteArray(v.to_vec()),
+            Tag::String(v) => OwnedTag::String(String::from(*v)),
+            Tag::List(v) => OwnedTag::List(v.map(Tag::to_owned_tag)),
+            Tag::Compound(v) => OwnedTag::Compound(v.iter().map(|(k, v)| (String::from(*k), v.to_owned_tag())).collect()),
+            Tag::IntArray(v) => OwnedTag::IntArray(v.clone()),
+            Tag::LongArray(v) => OwnedTag::LongArray(v.clone()),
+        }
+    }
+}
+
+impl From<Tag<'_>> for OwnedTag {
+    /// Converts the tag, moving its containers and int and long arrays rather than copying them.
+    fn from(value: Tag<'_>) -> Self {
+        match value {
+            Tag::End => OwnedTag::End,
+            Tag::Byte(v) => OwnedTag::Byte(v),
+            Tag::Short(v) => OwnedTag::Short(v),
+            Tag::Int(v) => OwnedTag::Int(v),
+            Tag::Long(v) => OwnedTag::Long(v),
+            Tag::Float(v) => OwnedTag::Float(v),
+            Tag::Double(v) => OwnedTag::Double(v),
+            Tag::ByteArray(v) => OwnedTag::ByteArray(v.to_vec()),
+            Tag::String(v) => OwnedTag::String(String::from(v)),
+            Tag::List(v) => OwnedTag::List(v.into_map(OwnedTag::from)),
+            Tag::Compound(v) => OwnedTag::Compound(v.into_iter().map(|(k, v)| (String::from(k), OwnedTag::from(v))).collect()),
+            Tag::IntArray(v) => OwnedTag::IntArray(v),
+            Tag::LongArray(v) => OwnedTag::LongArray(v),
+        }
+    }
+}
+
+impl From<&Tag<'_>> for OwnedTag {
+    fn from(value: &Tag<'_>) -> Self {
+        value.to_owned_tag()
+    }
+}
+
+impl<'a> From<&'a OwnedTag> for Tag<'a> {
+    fn from(value: &'a OwnedTag) -> Self {
+        value.as_tag()
+    }
+}
+
+impl From<&str> for OwnedTag {
+    fn from(value: &str) -> Self {
+        OwnedTag::String(String::from(value))
+    }
+}
+
+impl From<&[i8]> for OwnedTag {
+    fn from(value: &[i8]) -> Self {
+        OwnedTag::ByteArray(value.to_vec())
+    }
+}
+
+impl_owned_tag!(Byte, i8, as_byte, as_mut_byte, try_as_byte, try_as_mut_byte, get_byte);
+impl_owned_tag!(Short, i16, as_short, as_mut_short, try_as_short, try_as_mut_short, get_short);
+impl_owned_tag!(Int, i32, as_int, as_mut_int, try_as_int, try_as_mut_int, get_int);
+impl_owned_tag!(Long, i64, as_long, as_mut_long, try_as_long, try_as_mut_long, get_long);
+impl_owned_tag!(Float, f32, as_float, as_mut_float, try_as_float, try_as_mut_float, get_float);
+impl_owned_tag!(Double, f64, as_double, as_mut_double, try_as_double, try_as_mut_double, get_double);
+impl_owned_tag!(ByteArray, Vec<i8>, as_byte_array, as_mut_byte_array, try_as_byte_array, try_as_mut_byte_array, get_byte_array);
+impl_owned_tag!(String, String, as_string, as_mut_string, try_as_string, try_as_mut_string, get_string);
+impl_owned_tag!(List, OwnedList, as_list, as_mut_list, try_as_list, try_as_mut_list, get_list);
+impl_owned_tag!(Compound, OwnedCompound, as_compound, as_mut_compound, try_as_compound, try_as_mut_compound, get_compound);
+impl_owned_tag!(IntArray, Vec<i32>, as_int_array, as_mut_int_array, try_as_int_array, try_as_mut_int_array, get_int_array);
+impl_owned_tag!(LongArray, Vec<i64>, as_long_array, as_mut_long_array, try_as_long_array, try_as_mut_long_array, get_long_array);
+
+/*
+    Creates and returns an OwnedCompound. Keys and values may be of any type that converts into
+    String and OwnedTag respectively, so that keys can be created at runtime.
+
+    # Example
+
+    ```
+    let compound = owned_compound! {
+        "display" => owned_compound! {
+            "Name" => format!("Sword of {}", player)
+        }
+    };
+    ```
+*/
+#[macro_export]
+macro_rules! owned_compound {
+    ($($key:expr => $value:expr),*) => {{
+        let mut compound = $crate::nbt::OwnedCompound::new();
+        $(
+            compound.insert($key.into(), $value.into());
+        )*
+        compound
+    }};
+}
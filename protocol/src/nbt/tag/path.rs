@@ -0,0 +1,433 @@
+use std::borrow::{Borrow, Cow};
+use std::hash::Hash;
+use crate::nbt::{remove_key, Compound, Element, Map, OwnedCompound, OwnedTag, Tag, TagError, TagErrorKind, TagId, TypedList, Variant};
+
+/// Step is a single step of a path: a key of a compound or an index of a list. Keys are borrowed
+/// from the path unless they hold escaped characters.
+#[derive(Debug, Clone, PartialEq)]
+enum Step<'p> {
+    Key(Cow<'p, str>),
+    Index(usize),
+}
+
+/// Parses a path into its steps, along with the position in the path at which every step ends,
+/// which is used to report the path of the tag an error occurred at.
+fn parse(path: &str) -> Result<Vec<(Step<'_>, usize)>, TagError> {
+    let invalid = |pos| TagError::new(TagErrorKind::InvalidPath(pos), path);
+    let bytes = path.as_bytes();
+
+    let mut steps = Vec::new();
+    if path.is_empty() {
+        return Ok(steps);
+    }
+
+    let mut pos = 0;
+    let mut key = bytes[0] != b'[';
+    loop {
+        if key {
+            let (key, end) = if bytes.get(pos) == Some(&b'"') {
+                parse_quoted(path, pos + 1).map_err(invalid)?
+            } else {
+                let end = path[pos..].find(['.', '[', ']', '"']).map_or(path.len(), |i| pos + i);
+                if end == pos {
+                    return Err(invalid(pos));
+                }
+                (Cow::Borrowed(&path[pos..end]), end)
+            };
+            steps.push((Step::Key(key), end));
+            pos = end;
+        }
+
+        match bytes.get(pos) {
+            None => return Ok(steps),
+            Some(b'.') => {
+                pos += 1;
+                key = true;
+            }
+            Some(b'[') => {
+                let start = pos + 1;
+                let end = path[start..].find(']').ok_or_else(|| invalid(path.len()))? + start;
+                let index = path[start..end].parse().map_err(|_| invalid(start))?;
+                pos = end + 1;
+                steps.push((Step::Index(index), pos));
+                key = false;
+            }
+            Some(_) => return Err(invalid(pos)),
+        }
+    }
+}
+
+/// Parses the quoted key that starts at the position passed, right after the opening quote. Quotes
+/// and backslashes in the key are escaped with a backslash. Returns the key along with the position
+/// after the closing quote, or the position at which the key is invalid.
+fn parse_quoted(path: &str, start: usize) -> Result<(Cow<'_, str>, usize), usize> {
+    let mut unescaped: Option<String> = None;
+    let mut from = start;
+    let mut chars = path[start..].char_indices().map(|(i, c)| (start + i, c));
+
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '"' => {
+                let key = match unescaped {
+                    Some(mut key) => {
+                        key.push_str(&path[from..i]);
+                        Cow::Owned(key)
+                    }
+                    None => Cow::Borrowed(&path[from..i]),
+                };
+                return Ok((key, i + 1));
+            }
+            '\\' => {
+                let key = unescaped.get_or_insert_with(String::new);
+                key.push_str(&path[from..i]);
+                match chars.next() {
+                    Some((_, c @ ('"' | '\\'))) => key.push(c),
+                    _ => return Err(i),
+                }
+                from = i + 2;
+            }
+            _ => {}
+        }
+    }
+    Err(path.len())
+}
+
+/// Appends the key to the path, quoting it if it holds characters used by paths, and returns the
+/// length of the path before it. Quotes and backslashes in quoted keys are escaped.
+pub(crate) fn push_key(path: &mut String, key: &str) -> usize {
+    let len = path.len();
+    if len > 0 {
+        path.push('.');
+    }
+    if key.is_empty() || key.contains(['.', '[', ']', '"']) {
+        path.push('"');
+        for c in key.chars() {
+            if matches!(c, '"' | '\\') {
+                path.push('\\');
+            }
+            path.push(c);
+        }
+        path.push('"');
+    } else {
+        path.push_str(key);
+    }
+    len
+}
+
+pub(crate) fn push_index(path: &mut String, index: usize) -> usize {
+    let len = path.len();
+    path.push_str(&format!("[{index}]"));
+    len
+}
+
+/// Returns the error for a step taken into a tag that is not a container of the right type.
+fn mismatch(step: Step, found: TagId) -> TagErrorKind {
+    let expected = match step {
+        Step::Key(_) => TagId::Compound,
+        Step::Index(_) => TagId::List,
+    };
+    TagErrorKind::TypeMismatch { expected, found }
+}
+
+fn entry<'c, K: Borrow<str> + Hash + Eq, V>(compound: &'c Map<K, V>, key: &str) -> Result<&'c V, TagErrorKind> {
+    compound.get(key).ok_or_else(|| TagErrorKind::MissingKey(key.to_owned()))
+}
+
+fn entry_mut<'c, K: Borrow<str> + Hash + Eq, V>(compound: &'c mut Map<K, V>, key: &str) -> Result<&'c mut V, TagErrorKind> {
+    compound.get_mut(key).ok_or_else(|| TagErrorKind::MissingKey(key.to_owned()))
+}
+
+fn element<T>(list: &TypedList<T>, index: usize) -> Result<&T, TagErrorKind> {
+    list.get(index).ok_or(TagErrorKind::IndexOutOfBounds { index, len: list.len() })
+}
+
+fn element_mut<T>(list: &mut TypedList<T>, index: usize) -> Result<&mut T, TagErrorKind> {
+    let len = list.len();
+    list.get_mut(index).ok_or(TagErrorKind::IndexOutOfBounds { index, len })
+}
+
+/// Sets the element at the index of the list, or appends it if the index is the length of the
+/// list. The element must be of the type of the other elements of the list.
+fn set_element<T: Element>(list: &mut TypedList<T>, index: usize, value: T) -> Result<Option<T>, TagErrorKind> {
+    let len = list.len();
+    if index > len {
+        return Err(TagErrorKind::IndexOutOfBounds { index, len });
+    }
+    let others = len - usize::from(index < len);
+    if others > 0 && value.id() != list.element_type() {
+        return Err(TagErrorKind::MixedList { expected: list.element_type(), found: value.id() });
+    }
+
+    if index == len {
+        list.push(value);
+        return Ok(None);
+    }
+    Ok(Some(std::mem::replace(&mut list[index], value)))
+}
+
+/// Removes the element at the index of the list. A list emptied keeps the type of its elements, so
+/// that it is encoded with the same type as before.
+fn remove_element<T: Element>(list: &mut TypedList<T>, index: usize) -> Result<T, TagErrorKind> {
+    if index >= list.len() {
+        return Err(TagErrorKind::IndexOutOfBounds { index, len: list.len() });
+    }
+    let element_type = list.element_type();
+    let element = list.remove(index);
+    if list.is_empty() {
+        *list = TypedList::with_element_type(element_type);
+    }
+    Ok(element)
+}
+
+/// Returns the key with the lifetime of the keys of a borrowed compound. Keys that were unescaped
+/// are not borrowed from the path, so they can only be used if the compound already holds them.
+fn borrowed_key<'a>(compound: &Compound<'a>, key: Cow<'a, str>) -> Result<&'a str, TagErrorKind> {
+    match key {
+        Cow::Borrowed(key) => Ok(key),
+        Cow::Owned(key) => match compound.get_key_value(key.as_str()) {
+            Some((key, _)) => Ok(*key),
+            None => Err(TagErrorKind::MissingKey(key)),
+        },
+    }
+}
+
+/// Node is implemented for the tag types that can be walked with paths.
+trait Node: Element + Sized {
+    fn child(&self, step: Step) -> Result<&Self, TagErrorKind>;
+    fn child_mut(&mut self, step: Step) -> Result<&mut Self, TagErrorKind>;
+    fn remove(&mut self, step: Step) -> Result<Self, TagErrorKind>;
+}
+
+/// Insert is implemented for the tag types of which compounds can take keys borrowed from paths
+/// with the lifetime `'p`.
+trait Insert<'p>: Node {
+    /// Returns the value of the key, inserting an empty compound if the key is missing.
+    fn child_or_insert(&mut self, key: Cow<'p, str>) -> Result<&mut Self, TagErrorKind>;
+    fn set(&mut self, step: Step<'p>, value: Self) -> Result<Option<Self>, TagErrorKind>;
+}
+
+impl Node for Tag<'_> {
+    fn child(&self, step: Step) -> Result<&Self, TagErrorKind> {
+        match (self, step) {
+            (Tag::Compound(c), Step::Key(key)) => entry(c, &key),
+            (Tag::List(l), Step::Index(index)) => element(l, index),
+            (tag, step) => Err(mismatch(step, tag.id())),
+        }
+    }
+
+    fn child_mut(&mut self, step: Step) -> Result<&mut Self, TagErrorKind> {
+        match (self, step) {
+            (Tag::Compound(c), Step::Key(key)) => entry_mut(c, &key),
+            (Tag::List(l), Step::Index(index)) => element_mut(l, index),
+            (tag, step) => Err(mismatch(step, tag.id())),
+        }
+    }
+
+    fn remove(&mut self, step: Step) -> Result<Self, TagErrorKind> {
+        match (self, step) {
+            (Tag::Compound(c), Step::Key(key)) => remove_key(c, &*key).ok_or_else(|| TagErrorKind::MissingKey(key.into_owned())),
+            (Tag::List(l), Step::Index(index)) => remove_element(l, index),
+            (tag, step) => Err(mismatch(step, tag.id())),
+        }
+    }
+}
+
+impl<'a> Insert<'a> for Tag<'a> {
+    fn child_or_insert(&mut self, key: Cow<'a, str>) -> Result<&mut Self, TagErrorKind> {
+        match self {
+            Tag::Compound(c) => {
+                let key = borrowed_key(c, key)?;
+                Ok(c.entry(key).or_insert_with(|| Tag::Compound(Compound::new())))
+            }
+            tag => Err(mismatch(Step::Key(key), tag.id())),
+        }
+    }
+
+    fn set(&mut self, step: Step<'a>, value: Self) -> Result<Option<Self>, TagErrorKind> {
+        match (self, step) {
+            (Tag::Compound(c), Step::Key(key)) => Ok(c.insert(borrowed_key(c, key)?, value)),
+            (Tag::List(l), Step::Index(index)) => set_element(l, index, value),
+            (tag, step) => Err(mismatch(step, tag.id())),
+        }
+    }
+}
+
+impl Node for OwnedTag {
+    fn child(&self, step: Step) -> Result<&Self, TagErrorKind> {
+        match (self, step) {
+            (OwnedTag::Compound(c), Step::Key(key)) => entry(c, &key),
+            (OwnedTag::List(l), Step::Index(index)) => element(l, index),
+            (tag, step) => Err(mismatch(step, tag.id())),
+        }
+    }
+
+    fn child_mut(&mut self, step: Step) -> Result<&mut Self, TagErrorKind> {
+        match (self, step) {
+            (OwnedTag::Compound(c), Step::Key(key)) => entry_mut(c, &key),
+            (OwnedTag::List(l), Step::Index(index)) => element_mut(l, index),
+            (tag, step) => Err(mismatch(step, tag.id())),
+        }
+    }
+
+    fn remove(&mut self, step: Step) -> Result<Self, TagErrorKind> {
+        match (self, step) {
+            (OwnedTag::Compound(c), Step::Key(key)) => remove_key(c, &*key).ok_or_else(|| TagErrorKind::MissingKey(key.into_owned())),
+            (OwnedTag::List(l), Step::Index(index)) => remove_element(l, index),
+            (tag, step) => Err(mismatch(step, tag.id())),
+        }
+    }
+}
+
+impl Insert<'_> for OwnedTag {
+    fn child_or_insert(&mut self, key: Cow<str>) -> Result<&mut Self, TagErrorKind> {
+        match self {
+            OwnedTag::Compound(c) => Ok(c.entry(key.into_owned()).or_insert_with(|| OwnedTag::Compound(OwnedCompound::new()))),
+            tag => Err(mismatch(Step::Key(key), tag.id())),
+        }
+    }
+
+    fn set(&mut self, step: Step, value: Self) -> Result<Option<Self>, TagErrorKind> {
+        match (self, step) {
+            (OwnedTag::Compound(c), Step::Key(key)) => Ok(c.insert(key.into_owned(), value)),
+            (OwnedTag::List(l), Step::Index(index)) => set_element(l, index, value),
+            (tag, step) => Err(mismatch(step, tag.id())),
+        }
+    }
+}
+
+fn walk<'t, N: Node>(root: &'t N, path: &str) -> Result<&'t N, TagError> {
+    let mut tag = root;
+    let mut at = 0;
+    for (step, end) in parse(path)? {
+        tag = tag.child(step).map_err(|kind| TagError::new(kind, &path[..at]))?;
+        at = end;
+    }
+    Ok(tag)
+}
+
+fn walk_mut<'t, N: Node>(root: &'t mut N, path: &str) -> Result<&'t mut N, TagError> {
+    let mut tag = root;
+    let mut at = 0;
+    for (step, end) in parse(path)? {
+        tag = tag.child_mut(step).map_err(|kind| TagError::new(kind, &path[..at]))?;
+        at = end;
+    }
+    Ok(tag)
+}
+
+fn set<'p, N: Insert<'p>>(root: &mut N, path: &'p str, value: N) -> Result<Option<N>, TagError> {
+    let mut steps = parse(path)?;
+    let Some((last, _)) = steps.pop() else {
+        return Ok(Some(std::mem::replace(root, value)));
+    };
+
+    let mut tag = root;
+    let mut at = 0;
+    for (step, end) in steps {
+        let child = match step {
+            Step::Key(key) => tag.child_or_insert(key),
+            step @ Step::Index(_) => tag.child_mut(step),
+        };
+        tag = child.map_err(|kind| TagError::new(kind, &path[..at]))?;
+        at = end;
+    }
+    tag.set(last, value).map_err(|kind| TagError::new(kind, &path[..at]))
+}
+
+fn remove<N: Node>(root: &mut N, path: &str) -> Result<N, TagError> {
+    let mut steps = parse(path)?;
+    let Some((last, _)) = steps.pop() else {
+        return Err(TagError::new(TagErrorKind::InvalidPath(0), path));
+    };
+
+    let at = steps.last().map_or(0, |(_, end)| *end);
+    let mut tag = root;
+    let mut prev = 0;
+    for (step, end) in steps {
+        tag = tag.child_mut(step).map_err(|kind| TagError::new(kind, &path[..prev]))?;
+        prev = end;
+    }
+    tag.remove(last).map_err(|kind| TagError::new(kind, &path[..at]))
+}
+
+macro_rules! impl_access {
+    ($tag:ty $(, $lt:lifetime)?) => {
+        impl<$($lt)?> $tag {
+            /// Returns the inner value of the tag, or an error if the tag is of another type.
+            pub fn try_as<V: Variant<Self>>(&self) -> Result<&V, TagError> {
+                V::of(self).ok_or_else(|| TagError::mismatch(V::ID, self.id()))
+            }
+
+            pub fn try_as_mut<V: Variant<Self>>(&mut self) -> Result<&mut V, TagError> {
+                let found = self.id();
+                V::of_mut(self).ok_or_else(|| TagError::mismatch(V::ID, found))
+            }
+
+            /// Returns the value of the key in the compound, or an error if the tag is not a
+            /// compound or does not hold the key.
+            pub fn get(&self, key: &str) -> Result<&Self, TagError> {
+                self.child(Step::Key(Cow::Borrowed(key))).map_err(|kind| TagError::new(kind, ""))
+            }
+
+            pub fn get_mut(&mut self, key: &str) -> Result<&mut Self, TagError> {
+                self.child_mut(Step::Key(Cow::Borrowed(key))).map_err(|kind| TagError::new(kind, ""))
+            }
+
+            /// Returns the inner value of the value of the key in the compound.
+            pub fn get_as<V: Variant<Self>>(&self, key: &str) -> Result<&V, TagError> {
+                self.get(key)?.try_as().map_err(|err| err.at(key))
+            }
+
+            /// Returns the tag at the path passed. Paths consist of keys of compounds separated by
+            /// dots and indices of lists in brackets, such as `Items[0].tag.display.Name`. Keys
+            /// holding dots, brackets or quotes are quoted, as in `"minecraft:a.b".value`, and
+            /// quotes and backslashes within quoted keys are escaped with a backslash, as in
+            /// `"say \"hi\""`. An empty path refers to the tag itself.
+            ///
+            /// The error holds the path of the tag at which the path could not be followed.
+            pub fn path(&self, path: &str) -> Result<&Self, TagError> {
+                walk(self, path)
+            }
+
+            pub fn path_mut(&mut self, path: &str) -> Result<&mut Self, TagError> {
+                walk_mut(self, path)
+            }
+
+            /// Returns the inner value of the tag at the path passed, or an error if the path
+            /// cannot be followed or the tag is of another type.
+            pub fn path_as<V: Variant<Self>>(&self, path: &str) -> Result<&V, TagError> {
+                self.path(path)?.try_as().map_err(|err| err.at(path))
+            }
+
+            pub fn path_as_mut<V: Variant<Self>>(&mut self, path: &str) -> Result<&mut V, TagError> {
+                self.path_mut(path)?.try_as_mut().map_err(|err| err.at(path))
+            }
+
+            /// Removes the tag at the path passed from its compound or list and returns it.
+            pub fn remove_path(&mut self, path: &str) -> Result<Self, TagError> {
+                remove(self, path)
+            }
+        }
+    };
+}
+
+impl_access!(Tag<'a>, 'a);
+impl_access!(OwnedTag);
+
+impl<'a> Tag<'a> {
+    /// Sets the tag at the path passed, returning the tag it replaced. Compounds missing on the
+    /// path are created, and lists may be appended to by setting the index of their length. The
+    /// keys inserted are borrowed from the path, so keys with escaped characters can only be set
+    /// if the compound already holds them.
+    pub fn set_path(&mut self, path: &'a str, value: impl Into<Tag<'a>>) -> Result<Option<Tag<'a>>, TagError> {
+        set(self, path, value.into())
+    }
+}
+
+impl OwnedTag {
+    /// Sets the tag at the path passed, returning the tag it replaced. Compounds missing on the
+    /// path are created, and lists may be appended to by setting the index of their length.
+    pub fn set_path(&mut self, path: &str, value: impl Into<OwnedTag>) -> Result<Option<OwnedTag>, TagError> {
+        set(self, path, value.into())
+    }
+}
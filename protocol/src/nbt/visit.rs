@@ -0,0 +1,392 @@
+use binary::{Decode, Reader};
+use crate::nbt::{decode, decode_tag_id, Encoding, Tag, TagId, MAX_DEPTH};
+
+/// Visit is returned by the methods of a [`Visitor`] to control how the tags that follow are
+/// visited.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Visit {
+    /// Visits the tags that follow as usual.
+    #[default]
+    Continue,
+    /// Skips the contents of the compound or list that was begun, or the value of the key that was
+    /// visited, without calling the visitor for them. The same as [`Visit::Continue`] elsewhere.
+    Skip,
+    /// Stops visiting, leaving the Reader at the position it stopped at.
+    Stop
+}
+
+/// Visitor is called for each tag decoded by [`visit`], in the order the tags are encoded, without
+/// the tags being collected into a [`Tag`]. Strings and byte arrays are borrowed from the Reader.
+///
+/// All methods do nothing and return [`Visit::Continue`] unless implemented.
+#[allow(unused_variables)]
+pub trait Visitor<'a> {
+    /// Called when a compound is begun, before its first key.
+    fn begin_compound(&mut self) -> Visit {
+        Visit::Continue
+    }
+
+    /// Called for each key of a compound, along with the type of its value, before the value is
+    /// visited.
+    fn key(&mut self, id: TagId, key: &'a str) -> Visit {
+        Visit::Continue
+    }
+
+    /// Called when a compound that was not skipped is ended.
+    fn end_compound(&mut self) -> Visit {
+        Visit::Continue
+    }
+
+    /// Called when a list is begun, with the type and number of its elements.
+    fn begin_list(&mut self, element_type: TagId, len: usize) -> Visit {
+        Visit::Continue
+    }
+
+    /// Called when a list that was not skipped is ended.
+    fn end_list(&mut self) -> Visit {
+        Visit::Continue
+    }
+
+    fn byte(&mut self, v: i8) -> Visit {
+        Visit::Continue
+    }
+
+    fn short(&mut self, v: i16) -> Visit {
+        Visit::Continue
+    }
+
+    fn int(&mut self, v: i32) -> Visit {
+        Visit::Continue
+    }
+
+    fn long(&mut self, v: i64) -> Visit {
+        Visit::Continue
+    }
+
+    fn float(&mut self, v: f32) -> Visit {
+        Visit::Continue
+    }
+
+    fn double(&mut self, v: f64) -> Visit {
+        Visit::Continue
+    }
+
+    fn string(&mut self, v: &'a str) -> Visit {
+        Visit::Continue
+    }
+
+    fn byte_array(&mut self, v: &'a [i8]) -> Visit {
+        Visit::Continue
+    }
+
+    fn int_array(&mut self, v: ArrayIter<'a, i32>) -> Visit {
+        Visit::Continue
+    }
+
+    fn long_array(&mut self, v: ArrayIter<'a, i64>) -> Visit {
+        Visit::Continue
+    }
+}
+
+/// ArrayIter iterates over the elements of an int or long array, decoding them as they are
+/// iterated rather than collecting them into a [`Vec`].
+#[derive(Clone)]
+pub struct ArrayIter<'a, T> {
+    r: Reader<'a>,
+    len: usize,
+    read: fn(&mut Reader<'a>) -> Option<T>
+}
+
+impl<'a, T> ArrayIter<'a, T> {
+    /// Returns an iterator over the array at the front of the Reader, of which the elements are
+    /// read with the function passed, and advances the Reader past it.
+    fn new(r: &mut Reader<'a>, len: usize, read: fn(&mut Reader<'a>) -> Option<T>) -> Option<Self> {
+        let start = *r;
+        for _ in 0..len {
+            read(r)?;
+        }
+
+        let r = &start[..start.len() - r.len()];
+        Some(Self { r, len, read })
+    }
+}
+
+impl<T> Iterator for ArrayIter<'_, T> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<T> {
+        if self.len == 0 {
+            return None;
+        }
+
+        self.len -= 1;
+        (self.read)(&mut self.r)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.len, Some(self.len))
+    }
+}
+
+impl<T> ExactSizeIterator for ArrayIter<'_, T> {}
+
+/// Decodes a tag of the specified [`TagId`] from the [`Reader`] with the specified [`Encoding`],
+/// calling the [`Visitor`] for it and every tag it holds. Returns None if the tag could not be
+/// decoded, and the value returned by the visitor otherwise, which is [`Visit::Stop`] if the
+/// visitor stopped visiting. Tags nested deeper than 512 compounds and lists are not decoded.
+pub fn visit<'a, E: Encoding, V: Visitor<'a> + ?Sized>(id: TagId, r: &mut Reader<'a>, v: &mut V) -> Option<Visit> {
+    visit_at::<E, V>(id, r, v, 0)
+}
+
+fn visit_at<'a, E: Encoding, V: Visitor<'a> + ?Sized>(id: TagId, r: &mut Reader<'a>, v: &mut V, depth: usize) -> Option<Visit> {
+    if depth > MAX_DEPTH {
+        return None;
+    }
+
+    let visit = match id {
+        TagId::End => return None,
+        TagId::Byte => v.byte(i8::decode(r)?),
+        TagId::Short => v.short(E::read_short(r)?),
+        TagId::Int => v.int(E::read_int(r)?),
+        TagId::Long => v.long(E::read_long(r)?),
+        TagId::Float => v.float(E::read_float(r)?),
+        TagId::Double => v.double(E::read_double(r)?),
+        TagId::ByteArray => v.byte_array(read_byte_array::<E>(r)?),
+        TagId::String => v.string(E::read_str(r)?),
+        TagId::List => {
+            let (list_type, len) = read_list_header::<E>(r)?;
+
+            match v.begin_list(list_type, len) {
+                Visit::Continue => {}
+                Visit::Skip => {
+                    skip_elements::<E>(list_type, len, r, depth)?;
+                    return Some(Visit::Continue);
+                }
+                Visit::Stop => return Some(Visit::Stop)
+            }
+
+            for _ in 0..len {
+                if visit_at::<E, V>(list_type, r, v, depth + 1)? == Visit::Stop {
+                    return Some(Visit::Stop);
+                }
+            }
+
+            v.end_list()
+        }
+        TagId::Compound => {
+            match v.begin_compound() {
+                Visit::Continue => {}
+                Visit::Skip => {
+                    skip_at::<E>(TagId::Compound, r, depth)?;
+                    return Some(Visit::Continue);
+                }
+                Visit::Stop => return Some(Visit::Stop)
+            }
+
+            loop {
+                let tag = decode_tag_id(r)?;
+
+                // We encountered the end of a compound tag. Break the loop.
+                if tag == TagId::End {
+                    break;
+                }
+
+                let name = E::read_str(r)?;
+
+                let visit = match v.key(tag, name) {
+                    Visit::Continue => visit_at::<E, V>(tag, r, v, depth + 1)?,
+                    Visit::Skip => {
+                        skip_at::<E>(tag, r, depth + 1)?;
+                        Visit::Continue
+                    }
+                    Visit::Stop => Visit::Stop
+                };
+                if visit == Visit::Stop {
+                    return Some(Visit::Stop);
+                }
+            }
+
+            v.end_compound()
+        }
+        TagId::IntArray => {
+            let len = read_len::<E>(r)?;
+            v.int_array(ArrayIter::new(r, len, E::read_int)?)
+        }
+        TagId::LongArray => {
+            let len = read_len::<E>(r)?;
+            v.long_array(ArrayIter::new(r, len, E::read_long)?)
+        }
+    };
+
+    match visit {
+        Visit::Stop => Some(Visit::Stop),
+        _ => Some(Visit::Continue)
+    }
+}
+
+/// Advances the [`Reader`] past a tag of the specified [`TagId`], encoded with the specified
+/// [`Encoding`], without decoding it into a [`Tag`]. Returns None if the tag could not be decoded,
+/// or if it nests more than 512 compounds and lists.
+pub fn skip_tag<E: Encoding>(id: TagId, r: &mut Reader) -> Option<()> {
+    skip_at::<E>(id, r, 0)
+}
+
+fn skip_at<E: Encoding>(id: TagId, r: &mut Reader, depth: usize) -> Option<()> {
+    if depth > MAX_DEPTH {
+        return None;
+    }
+
+    match id {
+        TagId::End => return None,
+        TagId::Byte => advance(r, 1)?,
+        TagId::Short => advance(r, 2)?,
+        TagId::Int => {
+            E::read_int(r)?;
+        }
+        TagId::Long => {
+            E::read_long(r)?;
+        }
+        TagId::Float => advance(r, 4)?,
+        TagId::Double => advance(r, 8)?,
+        TagId::ByteArray => {
+            let len = read_len::<E>(r)?;
+            advance(r, len)?;
+        }
+        TagId::String => {
+            E::read_string(r)?;
+        }
+        TagId::List => {
+            let (list_type, len) = read_list_header::<E>(r)?;
+            skip_elements::<E>(list_type, len, r, depth)?;
+        }
+        TagId::Compound => loop {
+            let tag = decode_tag_id(r)?;
+
+            // We encountered the end of a compound tag. Break the loop.
+            if tag == TagId::End {
+                break;
+            }
+
+            E::read_string(r)?;
+            skip_at::<E>(tag, r, depth + 1)?;
+        },
+        TagId::IntArray => {
+            let len = read_len::<E>(r)?;
+            for _ in 0..len {
+                E::read_int(r)?;
+            }
+        }
+        TagId::LongArray => {
+            let len = read_len::<E>(r)?;
+            for _ in 0..len {
+                E::read_long(r)?;
+            }
+        }
+    }
+
+    Some(())
+}
+
+/// Decodes only the tag found by following the keys passed through nested compounds, starting at a
+/// tag of the specified [`TagId`], and skips all other tags on the way without decoding them.
+/// Returns None if the tag could not be decoded or was not found. The Reader is left after the tag
+/// found, or wherever decoding stopped. At most 512 keys are followed.
+///
+/// # Example
+///
+/// ```
+/// use bytes::BytesMut;
+/// use protocol::compound;
+/// use protocol::nbt::{decode_tag_id, Compound, Encoding, LittleEndian, Tag, NBT};
+/// use protocol::nbt::visit::find;
+/// use binary::Encode;
+///
+/// let tag = Tag::Compound(compound! {
+///     "Data" => Tag::Compound(compound! { "LevelName" => "world" })
+/// });
+/// let mut w = BytesMut::new();
+/// NBT::<LittleEndian>::new(tag).encode(&mut w);
+///
+/// let mut r = &w[..];
+/// let id = decode_tag_id(&mut r).unwrap();
+/// LittleEndian::read_str(&mut r).unwrap();
+/// let name = find::<LittleEndian>(id, &mut r, &["Data", "LevelName"]);
+/// assert_eq!(name, Some(Tag::String("world")));
+/// ```
+pub fn find<'a, E: Encoding>(id: TagId, r: &mut Reader<'a>, keys: &[&str]) -> Option<Tag<'a>> {
+    let Some((key, keys)) = keys.split_first() else {
+        return decode::<E>(id, r);
+    };
+    if id != TagId::Compound || keys.len() >= MAX_DEPTH {
+        return None;
+    }
+
+    loop {
+        let tag = decode_tag_id(r)?;
+        if tag == TagId::End {
+            return None;
+        }
+
+        if E::read_string(r)? == *key {
+            return find::<E>(tag, r, keys);
+        }
+        skip_tag::<E>(tag, r)?;
+    }
+}
+
+fn read_len<E: Encoding>(r: &mut Reader) -> Option<usize> {
+    usize::try_from(E::read_int(r)?).ok()
+}
+
+fn read_list_header<E: Encoding>(r: &mut Reader) -> Option<(TagId, usize)> {
+    let list_type = decode_tag_id(r)?;
+    let len = E::read_int(r)?;
+
+    // Lists of End tags are empty, whatever length they were encoded with.
+    if list_type == TagId::End || len <= 0 {
+        return Some((list_type, 0));
+    }
+    Some((list_type, len as usize))
+}
+
+fn read_byte_array<'a, E: Encoding>(r: &mut Reader<'a>) -> Option<&'a [i8]> {
+    let len = read_len::<E>(r)?;
+    if r.len() < len {
+        return None;
+    }
+
+    let (slice, rest) = r.split_at(len);
+    *r = rest;
+
+    unsafe {
+        let val: &[i8] = std::mem::transmute(slice);
+        Some(val)
+    }
+}
+
+fn skip_elements<E: Encoding>(list_type: TagId, len: usize, r: &mut Reader, depth: usize) -> Option<()> {
+    // Elements of a fixed size are skipped at once.
+    let size = match list_type {
+        TagId::Byte => 1,
+        TagId::Short => 2,
+        TagId::Float => 4,
+        TagId::Double => 8,
+        _ => {
+            for _ in 0..len {
+                skip_at::<E>(list_type, r, depth + 1)?;
+            }
+            return Some(());
+        }
+    };
+
+    advance(r, len.checked_mul(size)?)
+}
+
+fn advance(r: &mut Reader, len: usize) -> Option<()> {
+    if r.len() < len {
+        return None;
+    }
+
+    *r = &r[len..];
+    Some(())
+}
@@ -402,8 +402,9 @@ pub use update_trade::*;
 
 use derive::{Decode, Encode};
 use binary::{Decode, Encode, Reader, Writer};
+use num_derive::FromPrimitive;
 
-#[derive(Debug, Clone, Copy, Encode, Decode, PartialEq)]
+#[derive(Debug, Clone, Copy, Encode, Decode, FromPrimitive, PartialEq, Eq, Hash)]
 #[encoding(type = w32)]
 pub enum PacketId {
     Login = 1,
@@ -48,8 +48,8 @@ impl<'a> Decode<'a> for PlayerListAdd<'a> {
     fn decode(r: &mut Reader<'a>) -> Option<Self> {
         let mut entries: Vec<PlayerListEntry> = Vec::decode(r)?;
 
-        for i in 0..entries.len() {
-            entries[i].skin.trusted = bool::decode(r)?;
+        for entry in entries.iter_mut() {
+            entry.skin.trusted = bool::decode(r)?;
         }
 
         Some(PlayerListAdd { entries })
@@ -16,7 +16,7 @@ pub struct SetScoreboardIdentity {
     pub entries: Vec<ScoreboardIdentityEntry>,
 }
 
-impl<'a> Encode for SetScoreboardIdentity {
+impl Encode for SetScoreboardIdentity {
     fn encode(&self, w: &mut Writer) {
         self.action_type.encode(w);
 
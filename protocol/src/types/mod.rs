@@ -103,7 +103,7 @@ impl Decode<'_> for BlockPos {
             x: v32::decode(r)?.value(),
             y: v32::decode(r)?.value(),
             z: v32::decode(r)?.value()
-        }.into())
+        })
     }
 }
 
@@ -121,7 +121,7 @@ impl Decode<'_> for UBlockPos {
             x: v32::decode(r)?.value(),
             y: w32::decode(r)?.value() as i32,
             z: v32::decode(r)?.value()
-        }.into())
+        })
     }
 }
 
@@ -0,0 +1,92 @@
+use std::fs;
+use bytes::BytesMut;
+use protocol::nbt::file::{self, Compression};
+use protocol::owned_compound;
+use protocol::nbt::{decode_modified_utf8, encode_modified_utf8, snbt, BigEndian, Encoding, OwnedTag};
+
+const LEVEL: &str = r#"{Data: {LevelName: "world", SpawnY: 300s, Time: 70000L, Rain: 1.5f, Border: -2.25d, Flags: [B; 1b, 2b], Pos: [I; 1, 2, 3]}}"#;
+
+#[test]
+fn files_round_trip_with_every_compression() {
+    let parsed = snbt::parse(LEVEL).unwrap();
+    let tag = parsed.tag();
+    let dir = std::env::temp_dir().join(format!("nbt-big-endian-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+
+    for compression in [Compression::None, Compression::Gzip, Compression::Zlib] {
+        let path = dir.join(format!("{compression:?}.nbt"));
+        file::write::<BigEndian>(&path, "root", &tag, compression).unwrap();
+
+        let (buf, detected) = file::read(&path).unwrap();
+        assert_eq!(detected, compression);
+        assert_eq!(file::decode_root::<BigEndian>(&mut &buf[..]), Some(("root", tag.clone())));
+        assert!(file::load::<BigEndian, _>(&path, |name, loaded| name == "root" && loaded == tag).unwrap());
+    }
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn root_is_written_in_java_layout() {
+    let parsed = snbt::parse("{a: 1s}").unwrap();
+    let mut w = BytesMut::new();
+    file::encode_root::<BigEndian>("Schematic", &parsed.tag(), &mut w);
+
+    assert_eq!(&w[..], b"\x0a\x00\x09Schematic\x02\x00\x01a\x00\x01\x00");
+}
+
+#[test]
+fn modified_utf8_strings_are_decoded_owned() {
+    let owned = OwnedTag::Compound(owned_compound! { "nul\0" => "a😀é" });
+    let mut w = BytesMut::new();
+    file::encode_root::<BigEndian>("", &owned.as_tag(), &mut w);
+
+    // NUL and supplementary characters are encoded differently from UTF-8, so they cannot be
+    // borrowed from the buffer.
+    assert!(file::decode_root::<BigEndian>(&mut &w[..]).is_none());
+    let (_, tag) = file::decode_root_owned::<BigEndian>(&mut &w[..]).unwrap();
+    assert_eq!(tag, owned);
+    assert_eq!(decode_modified_utf8(&encode_modified_utf8("a\0😀é")).unwrap(), "a\0😀é");
+}
+
+#[test]
+fn long_strings_are_truncated_on_character_boundaries() {
+    let val = "é".repeat(40000);
+    let mut w = BytesMut::new();
+    BigEndian::write_str(&mut w, &val);
+
+    let len = u16::from_be_bytes([w[0], w[1]]) as usize;
+    assert_eq!(len, w.len() - 2);
+    assert_eq!(len, 65534);
+    let read = BigEndian::read_str(&mut &w[..]).unwrap();
+    assert_eq!(read, &val[..65534]);
+    assert_eq!(OwnedTag::from(read), OwnedTag::String("é".repeat(32767)));
+}
+
+#[test]
+fn truncated_arrays_are_rejected() {
+    let buf = [7u8, 0, 0, 0, 0, 0, 10, 1];
+    assert!(file::decode_root::<BigEndian>(&mut &buf[..]).is_none());
+}
+
+/// Returns a file with a root of lists nested to the depth passed, the innermost of which is an
+/// empty list of ints.
+fn nested_lists(depth: usize) -> Vec<u8> {
+    let mut buf = vec![9, 0, 0];
+    for _ in 1..depth {
+        buf.extend_from_slice(&[9, 0, 0, 0, 1]);
+    }
+    buf.extend_from_slice(&[3, 0, 0, 0, 0]);
+    buf
+}
+
+#[test]
+fn deeply_nested_tags_are_rejected() {
+    let shallow = nested_lists(100);
+    assert!(file::decode_root::<BigEndian>(&mut &shallow[..]).is_some());
+    assert!(file::decode_root_owned::<BigEndian>(&mut &shallow[..]).is_some());
+
+    // Decoding these recursively would overflow the stack without a depth limit.
+    let deep = nested_lists(100_000);
+    assert!(file::decode_root::<BigEndian>(&mut &deep[..]).is_none());
+    assert!(file::decode_root_owned::<BigEndian>(&mut &deep[..]).is_none());
+}
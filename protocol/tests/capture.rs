@@ -0,0 +1,37 @@
+use std::net::SocketAddr;
+use std::sync::Arc;
+use bytes::BytesMut;
+use protocol::conn;
+use protocol::packet::{Disconnect, Packet};
+use raknet::capture::{self, Direction, Format, Kind, Recorder};
+use raknet::simulator::{Conditions, Network};
+use raknet::{Config, RakClient, RakListener};
+
+#[tokio::test(start_paused = true)]
+async fn game_packets_are_recorded() {
+    let net = Network::new(Conditions::default(), 1);
+    let server: SocketAddr = "10.0.0.1:19132".parse().unwrap();
+    let listener = RakListener::with_transports(vec![net.bind(server).unwrap()], Config::default()).unwrap();
+    let client = RakClient::connect_with_transport(net.bind("10.0.0.2:50000".parse().unwrap()).unwrap(), server, Config::default()).await.unwrap();
+    let server_conn = listener.accept().await.unwrap();
+
+    let path = std::env::temp_dir().join(format!("protocol-capture-{}.rakcap", std::process::id()));
+    let recorder = Arc::new(Recorder::create(&path, Format::Native).unwrap());
+
+    let pk = Disconnect { message: "bye", ..Default::default() };
+    conn::send(&server_conn, &pk).unwrap();
+    let msg = client.recv().await.unwrap();
+    conn::record(&recorder, &client, Direction::Inbound, &msg);
+    // Messages that are not batches are not recorded.
+    conn::record(&recorder, &client, Direction::Inbound, &[0x86]);
+    recorder.flush().unwrap();
+
+    let records = capture::read(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    let mut encoded = BytesMut::new();
+    pk.write(&mut encoded);
+    assert_eq!(records.len(), 1);
+    assert_eq!((records[0].kind, records[0].direction, records[0].remote_addr), (Kind::Packet, Direction::Inbound, server));
+    assert_eq!(records[0].data, encoded);
+}
@@ -0,0 +1,42 @@
+use protocol::nbt::{file, LittleEndian, Tag};
+
+#[test]
+fn duplicate_keys_keep_the_last_value() {
+    // {a: 1b, a: 2b} in little endian NBT.
+    let buf = [10, 0, 0, 1, 1, 0, b'a', 1, 1, 1, 0, b'a', 2, 0];
+    let (_, tag) = file::decode_root::<LittleEndian>(&mut &buf[..]).unwrap();
+
+    assert!(matches!(tag, Tag::Compound(c) if c.len() == 1 && c["a"] == Tag::Byte(2)));
+}
+
+#[cfg(feature = "preserve_order")]
+#[test]
+fn keys_keep_their_order() {
+    use protocol::nbt::{remove_key, snbt};
+
+    let src = r#"{zeta:1,alpha:{y:2b,b:"x",a:[]},mid:[L;1L],list:[{q:1,c:2}]}"#;
+    let parsed = snbt::parse(src).unwrap();
+    let mut tag = parsed.tag();
+    assert_eq!(snbt::to_string(&tag), src);
+
+    let Tag::Compound(compound) = &mut tag else { unreachable!() };
+    remove_key(compound, "alpha");
+    assert_eq!(snbt::to_string(&tag), r#"{zeta:1,mid:[L;1L],list:[{q:1,c:2}]}"#);
+}
+
+#[cfg(feature = "preserve_order")]
+#[test]
+fn block_states_re_encode_byte_for_byte() {
+    use bytes::BytesMut;
+    use protocol::nbt::NetworkLittleEndian;
+
+    let buf = include_bytes!("../src/registry/block_states.nbt");
+    let mut r = &buf[..];
+    let mut w = BytesMut::new();
+    while !r.is_empty() {
+        let (name, tag) = file::decode_root::<NetworkLittleEndian>(&mut r).unwrap();
+        file::encode_root::<NetworkLittleEndian>(name, &tag.to_owned_tag().as_tag(), &mut w);
+    }
+
+    assert_eq!(&w[..], &buf[..]);
+}
@@ -0,0 +1,88 @@
+use binary::Decode;
+use bytes::BytesMut;
+use protocol::nbt::json::{decode_json, encode_json, JsonFormat, LossyJson, TypedJson};
+use protocol::nbt::{diff, NetworkLittleEndian, OwnedList, OwnedNBT, OwnedTag, TagErrorKind, TagId};
+use protocol::owned_compound;
+use serde_json::{json, Value};
+
+fn values() -> OwnedTag {
+    OwnedTag::Compound(owned_compound! {
+        "f" => 0.1f32,
+        "nan" => f32::NAN,
+        "inf" => f64::NEG_INFINITY,
+        "empty" => OwnedTag::List(OwnedList::with_element_type(TagId::Int)),
+        "big" => i64::MAX,
+        "ints" => vec![1i32, -1],
+        "bytes" => OwnedTag::ByteArray(vec![-1, 2])
+    })
+}
+
+#[test]
+fn typed_json_keeps_types() {
+    let tag = values();
+    let text = TypedJson::to_json(&tag.as_tag()).to_string();
+    let back = TypedJson::from_json(&serde_json::from_str(&text).unwrap()).unwrap();
+
+    // NaN does not equal itself, so the tags are compared with diff, which treats it as equal.
+    assert!(diff(&tag.as_tag(), &back.as_tag()).is_empty());
+    assert!(matches!(back.get("empty").unwrap(), OwnedTag::List(l) if l.element_type() == TagId::Int));
+}
+
+#[test]
+fn block_states_round_trip_through_typed_json() {
+    let buf = include_bytes!("../src/registry/block_states.nbt");
+    let mut r = &buf[..];
+    while !r.is_empty() {
+        let start = r;
+        let text = decode_json::<NetworkLittleEndian, TypedJson>(&mut r).unwrap().to_string();
+        let encoded = &start[..start.len() - r.len()];
+
+        let mut w = BytesMut::new();
+        encode_json::<NetworkLittleEndian, TypedJson>(&serde_json::from_str(&text).unwrap(), &mut w).unwrap();
+        let tag = OwnedNBT::<NetworkLittleEndian>::decode(&mut &w[..]).unwrap().value();
+        let expected = OwnedNBT::<NetworkLittleEndian>::decode(&mut &encoded[..]).unwrap().value();
+        assert_eq!(tag, expected);
+        #[cfg(feature = "preserve_order")]
+        assert_eq!(&w[..], encoded);
+    }
+}
+
+#[test]
+fn lossy_json_uses_the_smallest_types() {
+    let json = LossyJson::to_json(&values().as_tag());
+    assert_eq!(json["nan"], Value::Null);
+    assert_eq!(json["ints"], json!([1, -1]));
+
+    let tag = LossyJson::from_json(&json!({"a": [1, 5000000000i64, 2.5], "b": true, "c": null, "d": [1, 2]})).unwrap();
+    let expected = OwnedTag::Compound(owned_compound! {
+        "a" => OwnedTag::List(vec![OwnedTag::Double(1.0), OwnedTag::Double(5e9), OwnedTag::Double(2.5)].into()),
+        "b" => 1i8,
+        "d" => OwnedTag::List(vec![OwnedTag::Int(1), OwnedTag::Int(2)].into())
+    });
+    assert_eq!(tag, expected);
+
+    let err = LossyJson::from_json(&json!({"b": {"c": [1, "x"]}})).unwrap_err();
+    assert_eq!(err.path, "b.c");
+    assert_eq!(err.kind, TagErrorKind::MixedList { expected: TagId::Int, found: TagId::String });
+}
+
+#[test]
+fn invalid_typed_json_is_rejected() {
+    let mixed = json!({"type": "list", "element_type": "int", "value": [{"type": "int", "value": 1}, {"type": "short", "value": 2}]});
+    let err = TypedJson::from_json(&mixed).unwrap_err();
+    assert_eq!(err.path, "[1]");
+
+    let invalid = [
+        json!({"type": "byte", "value": 300}),
+        json!({"type": "end", "value": null}),
+        json!({"type": "compound", "value": {"a": {"type": "end", "value": null}}}),
+        json!({"type": "list", "element_type": "end", "value": [{"type": "end", "value": null}]}),
+        json!({"type": "list", "element_type": "end", "value": [{"type": "int", "value": 1}]}),
+        json!({"type": "int"}),
+    ];
+    for json in invalid {
+        assert!(TypedJson::from_json(&json).is_err(), "{json} was accepted");
+    }
+    let empty = TypedJson::from_json(&json!({"type": "list", "element_type": "end", "value": []})).unwrap();
+    assert_eq!(empty, OwnedTag::List(OwnedList::new()));
+}
@@ -0,0 +1,67 @@
+use binary::{Decode, Encode};
+use bytes::{BufMut, BytesMut};
+use protocol::level_dat::LevelDat;
+use protocol::nbt::{file, snbt, LittleEndian, OwnedTag, Tag};
+use protocol::types::{ExperimentData, GameRule, GameRuleValue, GameType};
+
+/// Encodes a level.dat file holding the SNBT compound passed.
+fn level_dat(src: &str) -> BytesMut {
+    let parsed = snbt::parse(src).unwrap();
+    let mut payload = BytesMut::new();
+    file::encode_root::<LittleEndian>("", &parsed.tag(), &mut payload);
+
+    let mut w = BytesMut::new();
+    w.put_i32_le(10);
+    w.put_u32_le(payload.len() as u32);
+    w.put_slice(&payload);
+    w
+}
+
+#[test]
+fn header_holds_version_and_length() {
+    let level = LevelDat { level_name: String::from("world"), ..Default::default() };
+    let mut w = BytesMut::new();
+    level.encode(&mut w);
+
+    assert_eq!(i32::from_le_bytes(w[0..4].try_into().unwrap()), 10);
+    assert_eq!(u32::from_le_bytes(w[4..8].try_into().unwrap()) as usize, w.len() - 8);
+}
+
+#[test]
+fn fields_and_extra_keys_round_trip() {
+    let mut level = LevelDat { level_name: String::from("world"), seed: -5, ..Default::default() };
+    level.game_rules.push(GameRule { name: String::from("pvp"), can_be_modified_by_player: true, value: GameRuleValue::Bool(false) });
+    level.experiments.push(ExperimentData { name: String::from("gametest"), enabled: true });
+    level.extra.insert(String::from("FlatWorldLayers"), OwnedTag::from("{}"));
+
+    let mut w = BytesMut::new();
+    level.encode(&mut w);
+    let decoded = LevelDat::decode(&mut &w[..]).unwrap();
+
+    assert_eq!(decoded.level_name, "world");
+    assert_eq!(decoded.seed, -5);
+    assert_eq!(decoded.spawn.y, 32767);
+    assert!(matches!(decoded.game_rules[..], [GameRule { value: GameRuleValue::Bool(false), .. }]));
+    assert!(matches!(&decoded.experiments[..], [ExperimentData { name, enabled: true }] if name == "gametest"));
+    assert_eq!(decoded.extra.get("FlatWorldLayers").map(OwnedTag::as_tag), Some(Tag::String("{}")));
+}
+
+#[test]
+fn unknown_enum_values_are_kept() {
+    let buf = level_dat("{LevelName: world, GameType: 99, Difficulty: 2}");
+    let level = LevelDat::decode(&mut &buf[..]).unwrap();
+
+    assert!(matches!(level.game_type, GameType::Survival));
+    assert_eq!(level.extra.get("GameType"), Some(&OwnedTag::Int(99)));
+
+    let mut w = BytesMut::new();
+    level.encode(&mut w);
+    let saved = LevelDat::decode(&mut &w[..]).unwrap();
+    assert_eq!(saved.extra.get("GameType"), Some(&OwnedTag::Int(99)));
+}
+
+#[test]
+fn mistyped_fields_are_rejected() {
+    let buf = level_dat("{LevelName: 1}");
+    assert!(LevelDat::decode(&mut &buf[..]).is_none());
+}
@@ -0,0 +1,53 @@
+use binary::{w32, Encode};
+use bytes::BytesMut;
+use protocol::conn;
+use protocol::metrics::GameMetrics;
+use protocol::packet::{Disconnect, PacketId};
+use raknet::capture::Direction;
+use raknet::metrics::Exposition;
+
+/// Encodes a packet with the header passed followed by the body passed.
+fn packet(header: u32, body: &[u8]) -> Vec<u8> {
+    let mut buf = BytesMut::new();
+    w32::new(header).encode(&mut buf);
+    buf.extend_from_slice(body);
+    buf.to_vec()
+}
+
+#[test]
+fn sub_client_ids_are_masked() {
+    let metrics = GameMetrics::new();
+    let login = packet(PacketId::Login as u32 | 1 << 10 | 2 << 12, &[0; 4]);
+    let disconnect = packet(PacketId::Disconnect as u32 | 3 << 12, &[]);
+    let unknown = packet(0x3ff | 1 << 10, &[]);
+    metrics.record_batch(Direction::Inbound, 12, &[&login, &disconnect, &unknown]);
+
+    let stats = metrics.stats();
+    assert_eq!(stats.received.batches, 1);
+    assert_eq!(stats.received.batch_bytes, 12);
+    assert_eq!(stats.received.packet_bytes, (login.len() + disconnect.len() + unknown.len()) as u64);
+    assert_eq!(stats.received.packets[&PacketId::Login], 1);
+    assert_eq!(stats.received.packets[&PacketId::Disconnect], 1);
+    assert_eq!(stats.received.unknown_packets, 1);
+    assert_eq!(stats.sent.batches, 0);
+}
+
+#[test]
+fn batches_are_exposed() {
+    let metrics = GameMetrics::new();
+    let mut msg = BytesMut::new();
+    msg.extend_from_slice(&[0xfe]);
+    conn::write_batched(&Disconnect { message: "bye", ..Default::default() }, &mut msg);
+    metrics.record(Direction::Outbound, &msg);
+    // Messages that are not batches are ignored.
+    metrics.record(Direction::Outbound, &[0x86]);
+
+    let mut e = Exposition::new();
+    metrics.write_metrics(&mut e);
+    let doc = e.finish();
+
+    assert!(doc.contains("# TYPE game_batches_total counter\ngame_batches_total{direction=\"sent\"} 1\ngame_batches_total{direction=\"received\"} 0\n"));
+    assert!(doc.contains(&format!("game_batch_bytes_total{{direction=\"sent\"}} {}\n", msg.len())));
+    assert!(doc.contains("game_packets_total{direction=\"sent\",packet=\"Disconnect\"} 1\n"));
+    assert!(doc.contains("game_packets_total{direction=\"sent\",packet=\"Unknown\"} 0\n"));
+}
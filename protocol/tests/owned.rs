@@ -0,0 +1,34 @@
+use binary::{Decode, Encode};
+use bytes::BytesMut;
+use protocol::nbt::{snbt, LittleEndian, NetworkLittleEndian, OwnedList, OwnedNBT, OwnedTag, TagId, NBT};
+use protocol::owned_compound;
+
+#[test]
+fn owned_and_borrowed_tags_convert_losslessly() {
+    let parsed = snbt::parse(r#"{a: 1b, b: [I; 1, 2], c: [{d: "e"}], f: [], g: {}}"#).unwrap();
+    let owned = OwnedTag::from(parsed.tag());
+
+    assert_eq!(owned.as_tag(), parsed.tag());
+    assert_eq!(parsed.into_owned(), owned);
+}
+
+#[test]
+fn owned_tags_round_trip() {
+    let key = String::from("dynamic");
+    let tag = OwnedTag::Compound(owned_compound! {
+        key.clone() => 3i32,
+        "s" => "str",
+        "l" => OwnedTag::List(vec![OwnedTag::Byte(1), OwnedTag::Byte(2)].into()),
+        "e" => OwnedTag::List(OwnedList::with_element_type(TagId::Long))
+    });
+
+    let mut w = BytesMut::new();
+    OwnedNBT::<NetworkLittleEndian>::new(tag.clone()).encode(&mut w);
+    assert_eq!(OwnedNBT::<NetworkLittleEndian>::decode(&mut &w[..]).unwrap().value(), tag);
+
+    let mut w = BytesMut::new();
+    OwnedNBT::<LittleEndian>::new(tag.clone()).encode(&mut w);
+    let borrowed = NBT::<LittleEndian>::decode(&mut &w[..]).unwrap();
+    assert_eq!(borrowed.to_owned_tag(), tag);
+    assert_eq!(tag.as_tag(), *borrowed);
+}
@@ -0,0 +1,92 @@
+use bytes::BytesMut;
+use protocol::nbt::{decode, diff, encode, merge, BigEndian, Change, ListMerge, Op, OwnedList, OwnedTag, Patch, TagErrorKind, TagId};
+use protocol::owned_compound;
+
+fn stack(id: &str, count: i8) -> OwnedTag {
+    OwnedTag::Compound(owned_compound! { "id" => id, "Count" => count })
+}
+
+fn before() -> OwnedTag {
+    OwnedTag::Compound(owned_compound! {
+        "Name" => "Steve",
+        "Health" => 20.0f32,
+        "a.b" => 1i32,
+        "Inventory" => OwnedTag::List(vec![stack("stone", 1), stack("dirt", 2), stack("sand", 3)].into()),
+        "Pos" => OwnedTag::List(vec![OwnedTag::Double(1.0), OwnedTag::Double(f64::NAN)].into())
+    })
+}
+
+fn after() -> OwnedTag {
+    OwnedTag::Compound(owned_compound! {
+        "Name" => "Alex",
+        "a.b" => 1i64,
+        "Inventory" => OwnedTag::List(vec![stack("stone", 2)].into()),
+        "Pos" => OwnedTag::List(vec![OwnedTag::Double(1.0), OwnedTag::Double(f64::NAN), OwnedTag::Double(3.0)].into()),
+        "Abilities" => owned_compound! { "flying" => 1i8 }
+    })
+}
+
+#[test]
+fn diffs_turn_one_tag_into_the_other() {
+    let (from, to) = (before(), after());
+    assert!(diff(&from.as_tag(), &from.as_tag()).is_empty());
+
+    let changes = diff(&from.as_tag(), &to.as_tag());
+    assert_eq!(changes.len(), 8);
+    assert!(changes.contains(&Change::Changed { path: String::from(r#""a.b""#), from: OwnedTag::Int(1), to: OwnedTag::Long(1) }));
+    assert!(changes.contains(&Change::Removed { path: String::from("Health"), value: OwnedTag::Float(20.0) }));
+
+    let mut tag = from.clone();
+    Patch::diff(&from.as_tag(), &to.as_tag()).apply(&mut tag).unwrap();
+    assert!(diff(&tag.as_tag(), &to.as_tag()).is_empty());
+}
+
+#[test]
+fn keys_with_quotes_are_escaped() {
+    let from = OwnedTag::Compound(owned_compound! { r#"say "hi".\now"# => 1i32 });
+    let to = OwnedTag::Compound(owned_compound! { r#"say "hi".\now"# => 2i32 });
+
+    let patch = Patch::diff(&from.as_tag(), &to.as_tag());
+    assert!(matches!(&patch.ops[..], [Op::Set { path, .. }] if path == r#""say \"hi\".\\now""#));
+
+    let mut tag = from.clone();
+    patch.apply(&mut tag).unwrap();
+    assert_eq!(tag, to);
+}
+
+#[test]
+fn emptied_lists_keep_their_element_type() {
+    let from = OwnedTag::Compound(owned_compound! { "l" => OwnedTag::List(vec![OwnedTag::Int(1)].into()) });
+    let to = OwnedTag::Compound(owned_compound! { "l" => OwnedTag::List(OwnedList::with_element_type(TagId::Int)) });
+
+    let mut tag = from.clone();
+    Patch::diff(&from.as_tag(), &to.as_tag()).apply(&mut tag).unwrap();
+    assert_eq!(tag, to);
+    assert!(matches!(tag.get("l").unwrap(), OwnedTag::List(l) if l.element_type() == TagId::Int));
+}
+
+#[test]
+fn patches_are_stored_as_nbt() {
+    let mut patch = Patch::diff(&before().as_tag(), &after().as_tag());
+    patch.ops.push(Op::Merge { path: String::from("Abilities"), value: owned_compound! { "mayfly" => 1i8 }.into(), lists: ListMerge::Append });
+
+    let mut w = BytesMut::new();
+    encode::<BigEndian>(&patch.to_tag().as_tag(), &mut w);
+    let stored = OwnedTag::from(decode::<BigEndian>(TagId::List, &mut &w[..]).unwrap());
+    assert_eq!(Patch::from_tag(&stored).unwrap(), patch);
+
+    let unknown = OwnedTag::List(vec![OwnedTag::Compound(owned_compound! { "op" => "nope", "path" => "x" })].into());
+    assert!(matches!(Patch::from_tag(&unknown).unwrap_err().kind, TagErrorKind::InvalidValue(_)));
+}
+
+#[test]
+fn lists_are_merged_with_the_strategy_passed() {
+    let ints = |v: &[i32]| OwnedTag::List(v.iter().copied().map(OwnedTag::Int).collect());
+    for (lists, expected) in [(ListMerge::Replace, ints(&[7])), (ListMerge::Append, ints(&[1, 2, 7])), (ListMerge::MergeByIndex, ints(&[7, 2]))] {
+        let mut tag = OwnedTag::Compound(owned_compound! { "l" => ints(&[1, 2]), "k" => 1i8 });
+        merge(&mut tag, &OwnedTag::Compound(owned_compound! { "l" => ints(&[7]), "m" => 2i8 }), lists);
+
+        let expected = OwnedTag::Compound(owned_compound! { "l" => expected, "k" => 1i8, "m" => 2i8 });
+        assert_eq!(tag, expected);
+    }
+}
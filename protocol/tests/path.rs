@@ -0,0 +1,65 @@
+use protocol::nbt::{snbt, Compound, OwnedTag, Tag, TagErrorKind, TagId};
+
+const PLAYER: &str = r#"{Items: [{Count: 3b, tag: {display: {Name: "Sword", Lore: ["a"]}}}], "a.b": {c: 1}}"#;
+
+fn player() -> OwnedTag {
+    snbt::parse(PLAYER).unwrap().into_owned()
+}
+
+#[test]
+fn paths_resolve_keys_and_indices() {
+    let tag = player();
+
+    assert_eq!(tag.path_as::<String>("Items[0].tag.display.Name").unwrap(), "Sword");
+    assert_eq!(*tag.path_as::<i8>("Items[0].Count").unwrap(), 3);
+    assert_eq!(*tag.path_as::<i32>(r#""a.b".c"#).unwrap(), 1);
+    assert_eq!(tag.path("Items[0].tag.display.Lore").unwrap().id(), TagId::List);
+}
+
+#[test]
+fn errors_hold_the_path_they_occurred_at() {
+    let tag = player();
+
+    let err = tag.path("Items[0].tag.display.Name.x").unwrap_err();
+    assert_eq!(err.path, "Items[0].tag.display.Name");
+    assert_eq!(err.kind, TagErrorKind::TypeMismatch { expected: TagId::Compound, found: TagId::String });
+
+    let err = tag.path("Items[3]").unwrap_err();
+    assert_eq!(err.kind, TagErrorKind::IndexOutOfBounds { index: 3, len: 1 });
+
+    let err = tag.path("Items[0].nope").unwrap_err();
+    assert_eq!((err.kind, err.path.as_str()), (TagErrorKind::MissingKey(String::from("nope")), "Items[0]"));
+
+    let err = tag.path_as::<i32>("Items[0].Count").unwrap_err();
+    assert_eq!(err.kind, TagErrorKind::TypeMismatch { expected: TagId::Int, found: TagId::Byte });
+
+    for bad in ["Items[x]", "a..b", "Items[0", "\"a"] {
+        assert!(matches!(tag.path(bad).unwrap_err().kind, TagErrorKind::InvalidPath(_)), "{bad} was accepted");
+    }
+}
+
+#[test]
+fn values_are_set_and_removed() {
+    let mut tag = player();
+
+    tag.set_path("Items[0].tag.display.Lore[1]", "b").unwrap();
+    tag.set_path("Items[0].tag.ench.lvl", 5i16).unwrap();
+    *tag.path_as_mut::<String>("Items[0].tag.display.Name").unwrap() = String::from("Axe");
+    let err = tag.set_path("Items[0].tag.display.Lore[0]", 1i32).unwrap_err();
+    assert_eq!(err.kind, TagErrorKind::MixedList { expected: TagId::String, found: TagId::Int });
+
+    assert_eq!(tag.remove_path("Items[0].Count").unwrap(), OwnedTag::Byte(3));
+    let expected = snbt::parse(r#"{Items: [{tag: {display: {Name: "Axe", Lore: ["a", "b"]}, ench: {lvl: 5s}}}], "a.b": {c: 1}}"#).unwrap();
+    assert_eq!(tag, expected.into_owned());
+}
+
+#[test]
+fn borrowed_tags_are_set_with_borrowed_paths() {
+    let path = String::from("k.v");
+    let mut tag = Tag::Compound(Compound::new());
+    tag.set_path(&path, 3i32).unwrap();
+
+    assert_eq!(*tag.path("k.v").unwrap().try_as_int().unwrap(), 3);
+    assert_eq!(*tag.get("k").unwrap().get_as::<i32>("v").unwrap(), 3);
+    assert!(tag.try_as_int().is_err());
+}
@@ -0,0 +1,90 @@
+use std::collections::BTreeMap;
+use bytes::BytesMut;
+use protocol::nbt::{byte_array, from_reader, from_tag, int_array, long_array, snbt, to_tag, to_writer, BigEndian, Encoding, LittleEndian, NetworkLittleEndian, TagId};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+enum Kind {
+    Chest,
+    Sign { text: String },
+    Wrapped(i32),
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct BlockEntity<'a> {
+    id: &'a str,
+    x: i32,
+    custom_name: Option<String>,
+    lock: Option<String>,
+    #[serde(with = "int_array")]
+    pos: [i32; 3],
+    #[serde(with = "byte_array")]
+    data: Vec<u8>,
+    #[serde(with = "long_array")]
+    longs: Vec<u64>,
+    items: Vec<u8>,
+    open: bool,
+    kinds: Vec<Kind>,
+    speed: f32,
+    small: u16,
+    scores: BTreeMap<String, i64>,
+}
+
+fn block_entity() -> BlockEntity<'static> {
+    BlockEntity {
+        id: "Chest",
+        x: 5,
+        custom_name: Some(String::from("Loot")),
+        lock: None,
+        pos: [1, 2, 3],
+        data: vec![255, 1],
+        longs: vec![u64::MAX],
+        items: vec![1, 2],
+        open: true,
+        kinds: vec![Kind::Sign { text: String::from("hi") }, Kind::Sign { text: String::from("x") }],
+        speed: 1.5,
+        small: u16::MAX,
+        scores: BTreeMap::from([(String::from("a"), 1)]),
+    }
+}
+
+fn round_trip<E: Encoding>(be: &BlockEntity) {
+    let mut w = BytesMut::new();
+    to_writer::<E, _>(be, &mut w).unwrap();
+    assert_eq!(&from_reader::<E, BlockEntity>(&mut &w[..]).unwrap(), be);
+}
+
+#[test]
+fn structs_round_trip_in_every_encoding() {
+    let be = block_entity();
+    round_trip::<LittleEndian>(&be);
+    round_trip::<NetworkLittleEndian>(&be);
+    round_trip::<BigEndian>(&be);
+}
+
+#[test]
+fn fields_map_to_the_expected_tags() {
+    let tag = to_tag(&block_entity()).unwrap();
+
+    assert_eq!(tag.path("Pos").unwrap().id(), TagId::IntArray);
+    assert_eq!(tag.path("Data").unwrap().id(), TagId::ByteArray);
+    assert_eq!(tag.path("Longs").unwrap().id(), TagId::LongArray);
+    assert_eq!(tag.path("Items").unwrap().id(), TagId::List);
+    assert_eq!(tag.path("Open").unwrap().id(), TagId::Byte);
+    assert!(tag.path("Lock").is_err());
+}
+
+#[test]
+fn enums_are_read_from_tags() {
+    let kind: Kind = from_tag(to_tag(&Kind::Wrapped(3)).unwrap().as_tag()).unwrap();
+    assert_eq!(kind, Kind::Wrapped(3));
+
+    let kinds: Vec<Kind> = from_tag(snbt::parse("[Chest, Chest]").unwrap().tag()).unwrap();
+    assert_eq!(kinds, vec![Kind::Chest, Kind::Chest]);
+}
+
+#[test]
+fn mixed_lists_are_rejected() {
+    assert!(to_tag(&vec![Kind::Chest, Kind::Wrapped(1)]).is_err());
+}
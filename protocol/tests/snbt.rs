@@ -0,0 +1,34 @@
+use protocol::nbt::{snbt, Tag};
+
+const PLAYER: &str = r#"{name: "Ste\"ve\n", 'odd key': 1b, s: 2s, i: -3, l: 4L, f: 1.5f, d: 2.0, inf: -Infinityf, b: [B; 1b, 2, -3b], ia: [I; 1, 2], la: [L; 1L, 5], list: [{a: 1}, {a: 2}], empty: [], ec: {}, t: true, word: hello.world, big: 3000000000L}"#;
+
+#[test]
+fn compact_and_pretty_output_round_trip() {
+    let parsed = snbt::parse(PLAYER).unwrap();
+    let tag = parsed.tag();
+
+    assert_eq!(snbt::parse(&snbt::to_string(&tag)).unwrap().tag(), tag);
+    assert_eq!(snbt::parse(&format!("{tag:#}")).unwrap().tag(), tag);
+}
+
+#[test]
+fn scalars_keep_their_types() {
+    assert_eq!(snbt::parse("3.5").unwrap().tag(), Tag::Double(3.5));
+    assert_eq!(snbt::parse("-0.0f").unwrap().tag(), Tag::Float(-0.0));
+    assert_eq!(snbt::parse("true").unwrap().tag(), Tag::Byte(1));
+    assert_eq!(snbt::parse("3000000000L").unwrap().tag(), Tag::Long(3000000000));
+}
+
+#[test]
+fn floats_round_trip_exactly() {
+    for tag in [Tag::Float(0.1), Tag::Float(f32::MIN_POSITIVE), Tag::Double(1e300), Tag::Double(-0.1)] {
+        assert_eq!(snbt::parse(&snbt::to_string(&tag)).unwrap().tag(), tag);
+    }
+}
+
+#[test]
+fn invalid_input_is_rejected() {
+    for bad in ["{a:1,}", "[1, 2s]", "[B; 1s]", "300b", "{a:1} x", "\"\\q\"", "{", "[I; x]"] {
+        assert!(snbt::parse(bad).is_err(), "{bad} was accepted");
+    }
+}
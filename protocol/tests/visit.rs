@@ -0,0 +1,141 @@
+use bytes::BytesMut;
+use protocol::nbt::visit::find;
+use protocol::nbt::{decode_tag_id, encode, file, skip_tag, visit, ArrayIter, BigEndian, Encoding, LittleEndian, NetworkLittleEndian, OwnedList, OwnedTag, Tag, TagId, Visit, Visitor};
+use protocol::owned_compound;
+
+/// Counts the keys visited and sums the values of int and long arrays.
+#[derive(Default)]
+struct Count {
+    keys: usize,
+    sum: i64,
+}
+
+impl<'a> Visitor<'a> for Count {
+    fn key(&mut self, _: TagId, _: &'a str) -> Visit {
+        self.keys += 1;
+        Visit::Continue
+    }
+
+    fn int_array(&mut self, v: ArrayIter<'a, i32>) -> Visit {
+        self.sum += v.map(i64::from).sum::<i64>();
+        Visit::Continue
+    }
+
+    fn long_array(&mut self, v: ArrayIter<'a, i64>) -> Visit {
+        self.sum += v.sum::<i64>();
+        Visit::Continue
+    }
+}
+
+/// Stops visiting at the first key.
+struct StopAtKey;
+
+impl<'a> Visitor<'a> for StopAtKey {
+    fn key(&mut self, _: TagId, _: &'a str) -> Visit {
+        Visit::Stop
+    }
+}
+
+fn count_keys(tag: &Tag) -> usize {
+    match tag {
+        Tag::Compound(c) => c.len() + c.values().map(count_keys).sum::<usize>(),
+        Tag::List(l) => l.iter().map(count_keys).sum(),
+        _ => 0,
+    }
+}
+
+/// Reads the header of a root tag and returns its ID.
+fn read_header<E: Encoding>(r: &mut &[u8]) -> TagId {
+    let id = decode_tag_id(r).unwrap();
+    E::read_str(r).unwrap();
+    id
+}
+
+#[test]
+fn block_states_are_visited_skipped_and_searched() {
+    let buf = include_bytes!("../src/registry/block_states.nbt");
+    let mut r = &buf[..];
+    while !r.is_empty() {
+        let (mut skipped, mut visited, mut searched) = (r, r, r);
+        let (_, tag) = file::decode_root::<NetworkLittleEndian>(&mut r).unwrap();
+
+        let id = read_header::<NetworkLittleEndian>(&mut skipped);
+        skip_tag::<NetworkLittleEndian>(id, &mut skipped).unwrap();
+        assert_eq!(skipped.len(), r.len());
+
+        read_header::<NetworkLittleEndian>(&mut visited);
+        let mut count = Count::default();
+        assert_eq!(visit::<NetworkLittleEndian, _>(id, &mut visited, &mut count), Some(Visit::Continue));
+        assert_eq!(visited.len(), r.len());
+        assert_eq!(count.keys, count_keys(&tag));
+
+        read_header::<NetworkLittleEndian>(&mut searched);
+        let name = find::<NetworkLittleEndian>(id, &mut searched, &["name"]).unwrap();
+        assert_eq!(tag.get("name"), Ok(&name));
+    }
+}
+
+fn arrays<E: Encoding>() {
+    let tag = OwnedTag::Compound(owned_compound! {
+        "a" => OwnedTag::IntArray(vec![1, -2, 300000]),
+        "b" => OwnedTag::LongArray(vec![1 << 40, -5]),
+        "c" => OwnedTag::List(vec![OwnedTag::Short(1), OwnedTag::Short(2)].into())
+    });
+    let mut w = BytesMut::new();
+    encode::<E>(&tag.as_tag(), &mut w);
+
+    let mut r = &w[..];
+    let mut count = Count::default();
+    visit::<E, _>(TagId::Compound, &mut r, &mut count).unwrap();
+    assert!(r.is_empty());
+    assert_eq!(count.sum, 1 - 2 + 300000 + (1 << 40) - 5);
+
+    let mut r = &w[..];
+    skip_tag::<E>(TagId::Compound, &mut r).unwrap();
+    assert!(r.is_empty());
+
+    let list = find::<E>(TagId::Compound, &mut &w[..], &["c"]).unwrap();
+    assert_eq!(list, tag.get("c").unwrap().as_tag());
+    assert_eq!(find::<E>(TagId::Compound, &mut &w[..], &["d"]), None);
+}
+
+#[test]
+fn arrays_are_visited_in_every_encoding() {
+    arrays::<LittleEndian>();
+    arrays::<NetworkLittleEndian>();
+    arrays::<BigEndian>();
+}
+
+#[test]
+fn visiting_stops_when_asked() {
+    let tag = OwnedTag::Compound(owned_compound! { "a" => 1i32 });
+    let mut w = BytesMut::new();
+    encode::<LittleEndian>(&tag.as_tag(), &mut w);
+
+    let mut r = &w[..];
+    assert_eq!(visit::<LittleEndian, _>(TagId::Compound, &mut r, &mut StopAtKey), Some(Visit::Stop));
+    assert!(!r.is_empty());
+}
+
+/// Encodes lists nested to the depth passed.
+fn nested_lists(depth: usize) -> BytesMut {
+    let mut tag = OwnedTag::List(OwnedList::with_element_type(TagId::Int));
+    for _ in 1..depth {
+        tag = OwnedTag::List(vec![tag].into());
+    }
+    let mut w = BytesMut::new();
+    encode::<LittleEndian>(&tag.as_tag(), &mut w);
+    w
+}
+
+#[test]
+fn deeply_nested_tags_are_rejected() {
+    let shallow = nested_lists(100);
+    assert!(skip_tag::<LittleEndian>(TagId::List, &mut &shallow[..]).is_some());
+    assert!(visit::<LittleEndian, _>(TagId::List, &mut &shallow[..], &mut Count::default()).is_some());
+
+    let deep = nested_lists(1000);
+    assert!(skip_tag::<LittleEndian>(TagId::List, &mut &deep[..]).is_none());
+    assert!(visit::<LittleEndian, _>(TagId::List, &mut &deep[..], &mut Count::default()).is_none());
+    assert!(find::<LittleEndian>(TagId::List, &mut &deep[..], &[]).is_none());
+}
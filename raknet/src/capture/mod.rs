@@ -0,0 +1,155 @@
+pub mod native;
+pub mod pcapng;
+pub mod replay;
+
+pub use replay::*;
+
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufWriter, Read, Write};
+use std::net::SocketAddr;
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime};
+use bytes::Bytes;
+use tokio::time::Instant;
+
+/// Direction is the direction in which a captured datagram or message flowed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    /// Received from the peer.
+    Inbound,
+    /// Sent to the peer.
+    Outbound
+}
+
+/// Kind is the layer a record was captured at.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Kind {
+    /// A raw datagram as sent over the network.
+    Datagram,
+    /// A message after the reliability layer, such as a batch of game packets.
+    Message,
+    /// A single game packet decoded from a batch, starting with its header. Packets are recorded
+    /// by the game layer, for example with `protocol::conn::record`, as batches may be compressed.
+    Packet
+}
+
+/// Record is a single datagram, message or game packet captured on a connection.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Record {
+    /// The time the record was captured at, relative to the start of the capture.
+    pub time: Duration,
+    pub direction: Direction,
+    pub kind: Kind,
+    pub local_addr: SocketAddr,
+    pub remote_addr: SocketAddr,
+    pub data: Bytes
+}
+
+/// Format is the file format captures are written in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    /// pcapng, readable by Wireshark. Every record is written with an IP and UDP header
+    /// synthesized from its addresses. Datagrams, messages and game packets are written on separate
+    /// interfaces, and the direction is stored in the flags of every packet.
+    Pcapng,
+    /// A compact log specific to this crate.
+    Native
+}
+
+/// Recorder writes the datagrams and messages of connections to a capture. It is attached to
+/// connections through [`Config::capture`](crate::Config::capture), which records every
+/// connection of a listener or client once its offline handshake completed. Game packets are
+/// recorded by the game layer with [`Recorder::record`] and [`Kind::Packet`].
+pub struct Recorder {
+    format: Format,
+    start: Instant,
+    start_time: SystemTime,
+    writer: Mutex<Box<dyn Write + Send>>
+}
+
+impl Recorder {
+    /// Creates a recorder writing to the file at the path passed, replacing it if it exists.
+    pub fn create(path: impl AsRef<Path>, format: Format) -> io::Result<Self> {
+        Self::new(BufWriter::new(File::create(path)?), format)
+    }
+
+    /// Creates a recorder writing to the writer passed. Writers should be buffered, as every
+    /// record results in a write.
+    pub fn new(writer: impl Write + Send + 'static, format: Format) -> io::Result<Self> {
+        let mut writer: Box<dyn Write + Send> = Box::new(writer);
+
+        match format {
+            Format::Pcapng => pcapng::write_header(&mut writer)?,
+            Format::Native => native::write_header(&mut writer)?
+        }
+
+        Ok(Self {
+            format,
+            start: Instant::now(),
+            start_time: SystemTime::now(),
+            writer: Mutex::new(writer)
+        })
+    }
+
+    /// Records a datagram, message or game packet captured now. Errors writing the record are ignored, so that
+    /// a failing capture never affects the connection.
+    pub fn record(&self, direction: Direction, kind: Kind, local_addr: SocketAddr, remote_addr: SocketAddr, data: &[u8]) {
+        let record = Record {
+            time: self.start.elapsed(),
+            direction,
+            kind,
+            local_addr,
+            remote_addr,
+            data: Bytes::copy_from_slice(data)
+        };
+
+        let mut writer = self.writer.lock().unwrap();
+        let _ = match self.format {
+            Format::Pcapng => pcapng::write_record(&mut *writer, &record, self.start_time),
+            Format::Native => native::write_record(&mut *writer, &record)
+        };
+    }
+
+    /// Flushes the records written so far to the underlying writer.
+    pub fn flush(&self) -> io::Result<()> {
+        self.writer.lock().unwrap().flush()
+    }
+}
+
+impl Drop for Recorder {
+    fn drop(&mut self) {
+        let _ = self.flush();
+    }
+}
+
+impl fmt::Debug for Recorder {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Recorder").field("format", &self.format).finish_non_exhaustive()
+    }
+}
+
+/// Reads all records of a capture from the file at the path passed. The format is detected from
+/// the contents of the file.
+pub fn read(path: impl AsRef<Path>) -> io::Result<Vec<Record>> {
+    let mut buf = Vec::new();
+    File::open(path)?.read_to_end(&mut buf)?;
+
+    read_from(&buf)
+}
+
+/// Reads all records of a capture in either format from the buffer passed.
+pub fn read_from(buf: &[u8]) -> io::Result<Vec<Record>> {
+    if buf.starts_with(&native::MAGIC) {
+        native::read(buf)
+    } else if buf.starts_with(&pcapng::SECTION_HEADER_BLOCK.to_le_bytes()) {
+        pcapng::read(buf)
+    } else {
+        Err(invalid_capture())
+    }
+}
+
+fn invalid_capture() -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, "invalid capture")
+}
@@ -0,0 +1,84 @@
+use std::io::{self, Write};
+use std::net::SocketAddr;
+use std::time::Duration;
+use bytes::{Buf, Bytes, BytesMut};
+use binary::{n32, n64, Decode, Encode};
+use crate::capture::{invalid_capture, Direction, Kind, Record};
+
+/// The bytes a native capture starts with, including the version of the format.
+pub const MAGIC: [u8; 8] = *b"RAKCAP01";
+
+const FLAG_OUTBOUND: u8 = 0x01;
+const FLAG_MESSAGE: u8 = 0x02;
+const FLAG_PACKET: u8 = 0x04;
+
+/// Writes the header of a native capture.
+pub fn write_header(w: &mut impl Write) -> io::Result<()> {
+    w.write_all(&MAGIC)
+}
+
+/// Writes a record to a native capture. Every record consists of its time in microseconds, its
+/// flags, its local and remote address and its length-prefixed data.
+pub fn write_record(w: &mut impl Write, record: &Record) -> io::Result<()> {
+    let mut flags = 0;
+    if record.direction == Direction::Outbound {
+        flags |= FLAG_OUTBOUND;
+    }
+    match record.kind {
+        Kind::Datagram => {}
+        Kind::Message => flags |= FLAG_MESSAGE,
+        Kind::Packet => flags |= FLAG_PACKET
+    }
+
+    let mut buf = BytesMut::with_capacity(record.data.len() + 64);
+    n64::new(record.time.as_micros() as u64).encode(&mut buf);
+    flags.encode(&mut buf);
+    record.local_addr.encode(&mut buf);
+    record.remote_addr.encode(&mut buf);
+    n32::new(record.data.len() as u32).encode(&mut buf);
+    buf.extend_from_slice(&record.data);
+
+    w.write_all(&buf)
+}
+
+/// Reads all records of a native capture.
+pub fn read(buf: &[u8]) -> io::Result<Vec<Record>> {
+    let mut r = buf.strip_prefix(&MAGIC).ok_or_else(invalid_capture)?;
+    let mut records = Vec::new();
+
+    while r.has_remaining() {
+        records.push(read_record(&mut r).ok_or_else(invalid_capture)?);
+    }
+
+    Ok(records)
+}
+
+fn read_record(r: &mut &[u8]) -> Option<Record> {
+    let time = Duration::from_micros(n64::decode(r)?.value());
+    let flags = u8::decode(r)?;
+    let local_addr = SocketAddr::decode(r)?;
+    let remote_addr = SocketAddr::decode(r)?;
+
+    let len = n32::decode(r)?.value() as usize;
+    if r.remaining() < len {
+        return None;
+    }
+
+    let data = Bytes::copy_from_slice(&r[..len]);
+    r.advance(len);
+
+    Some(Record {
+        time,
+        direction: if flags & FLAG_OUTBOUND != 0 { Direction::Outbound } else { Direction::Inbound },
+        kind: if flags & FLAG_PACKET != 0 {
+            Kind::Packet
+        } else if flags & FLAG_MESSAGE != 0 {
+            Kind::Message
+        } else {
+            Kind::Datagram
+        },
+        local_addr,
+        remote_addr,
+        data
+    })
+}
@@ -0,0 +1,337 @@
+use std::io::{self, Write};
+use std::net::{IpAddr, Ipv6Addr, SocketAddr};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use bytes::{BufMut, Bytes, BytesMut};
+use crate::capture::{invalid_capture, Direction, Kind, Record};
+
+/// The block type of the Section Header Block every pcapng file starts with.
+pub const SECTION_HEADER_BLOCK: u32 = 0x0a0d0d0a;
+const INTERFACE_DESCRIPTION_BLOCK: u32 = 0x00000001;
+const ENHANCED_PACKET_BLOCK: u32 = 0x00000006;
+
+const BYTE_ORDER_MAGIC: u32 = 0x1a2b3c4d;
+
+/// The link type of packets starting with an IPv4 or IPv6 header.
+const LINKTYPE_RAW: u16 = 101;
+
+/// The interfaces written to the capture: datagrams are written on the first, messages on the
+/// second and game packets on the third.
+const INTERFACE_DATAGRAMS: u32 = 0;
+const INTERFACE_MESSAGES: u32 = 1;
+const INTERFACE_PACKETS: u32 = 2;
+
+const OPT_END_OF_OPT: u16 = 0;
+const OPT_IF_NAME: u16 = 2;
+const OPT_EPB_FLAGS: u16 = 2;
+
+/// The values of the direction bits of the flags of an Enhanced Packet Block.
+const EPB_INBOUND: u32 = 0x01;
+const EPB_OUTBOUND: u32 = 0x02;
+
+const IPV4_HEADER_SIZE: usize = 20;
+const IPV6_HEADER_SIZE: usize = 40;
+const UDP_HEADER_SIZE: usize = 8;
+const IP_PROTOCOL_UDP: u8 = 17;
+
+/// Writes the Section Header Block and the Interface Description Blocks of the datagram, message
+/// and packet interfaces.
+pub fn write_header(w: &mut impl Write) -> io::Result<()> {
+    let mut body = BytesMut::new();
+    body.put_u32_le(BYTE_ORDER_MAGIC);
+    body.put_u16_le(1);
+    body.put_u16_le(0);
+    // The length of the section is not specified.
+    body.put_i64_le(-1);
+    write_block(w, SECTION_HEADER_BLOCK, &body)?;
+
+    for name in ["datagrams", "messages", "packets"] {
+        let mut body = BytesMut::new();
+        body.put_u16_le(LINKTYPE_RAW);
+        body.put_u16_le(0);
+        body.put_u32_le(0);
+        put_option(&mut body, OPT_IF_NAME, name.as_bytes());
+        put_option(&mut body, OPT_END_OF_OPT, &[]);
+        write_block(w, INTERFACE_DESCRIPTION_BLOCK, &body)?;
+    }
+
+    Ok(())
+}
+
+/// Writes a record as an Enhanced Packet Block. The timestamp is the time the capture started
+/// at plus the time of the record, in microseconds.
+pub fn write_record(w: &mut impl Write, record: &Record, start_time: SystemTime) -> io::Result<()> {
+    let packet = encapsulate(record);
+    let timestamp = (start_time + record.time).duration_since(UNIX_EPOCH).unwrap_or_default().as_micros() as u64;
+
+    let mut body = BytesMut::with_capacity(packet.len() + 32);
+    body.put_u32_le(match record.kind {
+        Kind::Datagram => INTERFACE_DATAGRAMS,
+        Kind::Message => INTERFACE_MESSAGES,
+        Kind::Packet => INTERFACE_PACKETS
+    });
+    body.put_u32_le((timestamp >> 32) as u32);
+    body.put_u32_le(timestamp as u32);
+    body.put_u32_le(packet.len() as u32);
+    body.put_u32_le(packet.len() as u32);
+    body.put_slice(&packet);
+    pad(&mut body);
+
+    let flags = match record.direction {
+        Direction::Inbound => EPB_INBOUND,
+        Direction::Outbound => EPB_OUTBOUND
+    };
+    put_option(&mut body, OPT_EPB_FLAGS, &flags.to_le_bytes());
+    put_option(&mut body, OPT_END_OF_OPT, &[]);
+
+    write_block(w, ENHANCED_PACKET_BLOCK, &body)
+}
+
+fn write_block(w: &mut impl Write, block_type: u32, body: &[u8]) -> io::Result<()> {
+    let len = (body.len() + 12) as u32;
+
+    w.write_all(&block_type.to_le_bytes())?;
+    w.write_all(&len.to_le_bytes())?;
+    w.write_all(body)?;
+    w.write_all(&len.to_le_bytes())
+}
+
+fn put_option(buf: &mut BytesMut, code: u16, value: &[u8]) {
+    buf.put_u16_le(code);
+    buf.put_u16_le(value.len() as u16);
+    buf.put_slice(value);
+    pad(buf);
+}
+
+/// Pads the buffer with zeroes to a multiple of 32 bits.
+fn pad(buf: &mut BytesMut) {
+    buf.put_bytes(0, (4 - buf.len() % 4) % 4);
+}
+
+/// Prefixes the data of a record with an IP and UDP header synthesized from its addresses. An
+/// IPv6 header is used if either address is an IPv6 address. The UDP checksum is left out.
+///
+/// Messages reassembled from split packets may be larger than the length fields of the headers
+/// can hold. Such records are written whole, with the length fields clamped to their maximum, so
+/// that Wireshark reports them as oversized while [`read`] still returns all of their data.
+fn encapsulate(record: &Record) -> BytesMut {
+    let (src, dst) = match record.direction {
+        Direction::Inbound => (record.remote_addr, record.local_addr),
+        Direction::Outbound => (record.local_addr, record.remote_addr)
+    };
+
+    let udp_len = UDP_HEADER_SIZE + record.data.len();
+    let mut buf = BytesMut::with_capacity(IPV6_HEADER_SIZE + udp_len);
+
+    match (src.ip(), dst.ip()) {
+        (IpAddr::V4(src), IpAddr::V4(dst)) => {
+            let mut header = [0; IPV4_HEADER_SIZE];
+            header[0] = 0x45;
+            header[2..4].copy_from_slice(&clamp_len(IPV4_HEADER_SIZE + udp_len).to_be_bytes());
+            header[8] = 64;
+            header[9] = IP_PROTOCOL_UDP;
+            header[12..16].copy_from_slice(&src.octets());
+            header[16..20].copy_from_slice(&dst.octets());
+
+            let checksum = ipv4_checksum(&header);
+            header[10..12].copy_from_slice(&checksum.to_be_bytes());
+            buf.put_slice(&header);
+        }
+        (src, dst) => {
+            buf.put_u32(0x60000000);
+            buf.put_u16(clamp_len(udp_len));
+            buf.put_u8(IP_PROTOCOL_UDP);
+            buf.put_u8(64);
+            buf.put_slice(&to_ipv6(src).octets());
+            buf.put_slice(&to_ipv6(dst).octets());
+        }
+    }
+
+    buf.put_u16(src.port());
+    buf.put_u16(dst.port());
+    buf.put_u16(clamp_len(udp_len));
+    buf.put_u16(0);
+    buf.put_slice(&record.data);
+    buf
+}
+
+/// Returns the length passed as the value of a 16-bit length field, clamped to its maximum.
+fn clamp_len(len: usize) -> u16 {
+    len.try_into().unwrap_or(u16::MAX)
+}
+
+fn to_ipv6(ip: IpAddr) -> Ipv6Addr {
+    match ip {
+        IpAddr::V4(ip) => ip.to_ipv6_mapped(),
+        IpAddr::V6(ip) => ip
+    }
+}
+
+fn ipv4_checksum(header: &[u8]) -> u16 {
+    let mut sum = header
+        .chunks(2)
+        .map(|word| u16::from_be_bytes([word[0], word[1]]) as u32)
+        .sum::<u32>();
+
+    while sum > 0xffff {
+        sum = (sum & 0xffff) + (sum >> 16);
+    }
+
+    !(sum as u16)
+}
+
+/// Reads all records of a pcapng capture written by [`write_record`]. Times are relative to the
+/// first record, and the direction is derived from the packet flags. Records that are not UDP
+/// packets on a raw IP interface are skipped.
+pub fn read(buf: &[u8]) -> io::Result<Vec<Record>> {
+    let mut records = Vec::new();
+    let mut link_types = Vec::new();
+    let mut first_timestamp = None;
+    let mut r = buf;
+
+    while !r.is_empty() {
+        if r.len() < 12 {
+            return Err(invalid_capture());
+        }
+
+        let block_type = u32::from_le_bytes(r[0..4].try_into().unwrap());
+        let len = u32::from_le_bytes(r[4..8].try_into().unwrap()) as usize;
+        if len < 12 || !len.is_multiple_of(4) || len > r.len() {
+            return Err(invalid_capture());
+        }
+
+        let body = &r[8..len - 4];
+        r = &r[len..];
+
+        match block_type {
+            SECTION_HEADER_BLOCK => {
+                if body.len() < 4 || u32::from_le_bytes(body[0..4].try_into().unwrap()) != BYTE_ORDER_MAGIC {
+                    // Only little endian sections are supported.
+                    return Err(invalid_capture());
+                }
+                link_types.clear();
+            }
+            INTERFACE_DESCRIPTION_BLOCK => {
+                if body.len() < 2 {
+                    return Err(invalid_capture());
+                }
+                link_types.push(u16::from_le_bytes([body[0], body[1]]));
+            }
+            ENHANCED_PACKET_BLOCK => {
+                if body.len() < 20 {
+                    return Err(invalid_capture());
+                }
+
+                let word = |i: usize| u32::from_le_bytes(body[i * 4..i * 4 + 4].try_into().unwrap());
+                let interface = word(0);
+                let timestamp = ((word(1) as u64) << 32) | word(2) as u64;
+                let captured_len = word(3) as usize;
+                if body.len() < 20 + captured_len {
+                    return Err(invalid_capture());
+                }
+
+                if link_types.get(interface as usize) != Some(&LINKTYPE_RAW) {
+                    continue;
+                }
+
+                let packet = &body[20..20 + captured_len];
+                let options = &body[20 + captured_len.div_ceil(4) * 4..];
+
+                let Some((src, dst, data)) = decapsulate(packet) else {
+                    continue;
+                };
+
+                let direction = if epb_flags(options) & 0x03 == EPB_OUTBOUND {
+                    Direction::Outbound
+                } else {
+                    Direction::Inbound
+                };
+
+                let (local_addr, remote_addr) = match direction {
+                    Direction::Inbound => (dst, src),
+                    Direction::Outbound => (src, dst)
+                };
+
+                let first = *first_timestamp.get_or_insert(timestamp);
+
+                records.push(Record {
+                    time: Duration::from_micros(timestamp.saturating_sub(first)),
+                    direction,
+                    kind: match interface {
+                        INTERFACE_MESSAGES => Kind::Message,
+                        INTERFACE_PACKETS => Kind::Packet,
+                        _ => Kind::Datagram
+                    },
+                    local_addr,
+                    remote_addr,
+                    data: Bytes::copy_from_slice(data)
+                });
+            }
+            _ => {}
+        }
+    }
+
+    Ok(records)
+}
+
+/// Returns the value of the flags option of an Enhanced Packet Block, or zero if it is absent.
+fn epb_flags(mut options: &[u8]) -> u32 {
+    while options.len() >= 4 {
+        let code = u16::from_le_bytes([options[0], options[1]]);
+        let len = u16::from_le_bytes([options[2], options[3]]) as usize;
+        let padded = 4 + len.div_ceil(4) * 4;
+
+        if code == OPT_END_OF_OPT || options.len() < padded {
+            break;
+        }
+        if code == OPT_EPB_FLAGS && len == 4 {
+            return u32::from_le_bytes(options[4..8].try_into().unwrap());
+        }
+
+        options = &options[padded..];
+    }
+
+    0
+}
+
+/// Strips the IP and UDP header from a packet, returning its source and destination address and
+/// its payload.
+fn decapsulate(packet: &[u8]) -> Option<(SocketAddr, SocketAddr, &[u8])> {
+    let version = packet.first()? >> 4;
+
+    let (src, dst, udp) = match version {
+        4 => {
+            let header_len = ((packet[0] & 0x0f) as usize) * 4;
+            if packet.len() < header_len + UDP_HEADER_SIZE || header_len < IPV4_HEADER_SIZE || packet[9] != IP_PROTOCOL_UDP {
+                return None;
+            }
+
+            let src: [u8; 4] = packet[12..16].try_into().unwrap();
+            let dst: [u8; 4] = packet[16..20].try_into().unwrap();
+            (IpAddr::from(src), IpAddr::from(dst), &packet[header_len..])
+        }
+        6 => {
+            if packet.len() < IPV6_HEADER_SIZE + UDP_HEADER_SIZE || packet[6] != IP_PROTOCOL_UDP {
+                return None;
+            }
+
+            let src: [u8; 16] = packet[8..24].try_into().unwrap();
+            let dst: [u8; 16] = packet[24..40].try_into().unwrap();
+            (from_ipv6(src.into()), from_ipv6(dst.into()), &packet[IPV6_HEADER_SIZE..])
+        }
+        _ => return None
+    };
+
+    let src_port = u16::from_be_bytes([udp[0], udp[1]]);
+    let dst_port = u16::from_be_bytes([udp[2], udp[3]]);
+
+    Some((SocketAddr::new(src, src_port), SocketAddr::new(dst, dst_port), &udp[UDP_HEADER_SIZE..]))
+}
+
+/// Converts IPv4-mapped IPv6 addresses back to IPv4 addresses, as [`encapsulate`] maps them if
+/// only one of the addresses of a record is an IPv6 address.
+fn from_ipv6(ip: Ipv6Addr) -> IpAddr {
+    match ip.to_ipv4_mapped() {
+        Some(ip) => IpAddr::V4(ip),
+        None => IpAddr::V6(ip)
+    }
+}
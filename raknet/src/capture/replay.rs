@@ -0,0 +1,48 @@
+use std::io;
+use std::net::SocketAddr;
+use tokio::time::{sleep_until, Instant};
+use crate::capture::{Direction, Kind, Record};
+use crate::client::RakClient;
+use crate::config::Config;
+use crate::conn::RakConn;
+use crate::types::Reliability;
+
+/// Replays records by calling the function passed for each of them, keeping the time between
+/// them. The speed is a multiplier of the original speed: 1 replays at the original speed, 10
+/// replays ten times faster and [`f64::INFINITY`] replays without waiting at all.
+pub async fn replay(records: &[Record], speed: f64, mut f: impl FnMut(&Record)) {
+    let start = Instant::now();
+    let first = records.first().map(|record| record.time).unwrap_or_default();
+
+    for record in records {
+        if speed.is_finite() && speed > 0.0 {
+            sleep_until(start + record.time.saturating_sub(first).div_f64(speed)).await;
+        }
+
+        f(record);
+    }
+}
+
+/// Replays the messages of a recorded session at a server. A connection is made to the server at
+/// the address passed, over which the messages of the direction passed are sent with the timing
+/// of [`replay`]. Messages sent by the client were captured as outbound on the client and as
+/// inbound on the server. The connection is returned once all messages were sent, so that the
+/// replies of the server can be read from it.
+pub async fn replay_to(records: &[Record], addr: SocketAddr, direction: Direction, speed: f64, config: Config) -> io::Result<RakConn> {
+    let conn = RakClient::connect_with_config(addr, config).await?;
+
+    let messages: Vec<Record> = records
+        .iter()
+        .filter(|record| record.kind == Kind::Message && record.direction == direction)
+        .cloned()
+        .collect();
+
+    let mut result = Ok(());
+    replay(&messages, speed, |record| {
+        if result.is_ok() {
+            result = conn.send(record.data.clone(), Reliability::ReliableOrdered);
+        }
+    }).await;
+
+    result.map(|_| conn)
+}
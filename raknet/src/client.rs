@@ -0,0 +1,228 @@
+use std::io;
+use std::net::{Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::sync::Arc;
+use std::time::Duration;
+use bytes::{Bytes, BytesMut};
+use tokio::net::{lookup_host, ToSocketAddrs, UdpSocket};
+use tokio::sync::{mpsc, watch};
+use tokio::time::{timeout, Instant};
+use binary::{n32, Decode};
+use rand::random;
+use crate::config::{Config, MIN_MTU};
+use crate::conn::{self, RakConn};
+use crate::packet::{IncompatibleProtocol, OpenConnectionReply1, OpenConnectionReply2, OpenConnectionRequest1, OpenConnectionRequest2, Packet, PacketId, UnconnectedPing, UnconnectedPong};
+use crate::session::Session;
+use crate::status::ServerStatus;
+use crate::transport::Transport;
+use crate::types::Magic;
+
+/// The MTU sizes tried in order during MTU discovery.
+const MTU_SIZES: [u16; 3] = [1492, 1200, MIN_MTU];
+
+/// The amount of times a request of the offline handshake is sent before giving up on it.
+const ATTEMPTS: usize = 4;
+
+/// The duration to wait for a reply to a request of the offline handshake.
+const ATTEMPT_TIMEOUT: Duration = Duration::from_millis(500);
+
+/// RakClient establishes RakNet connections with a server.
+pub struct RakClient;
+
+impl RakClient {
+    /// Connects to the RakNet server at the specified address using the default [`Config`].
+    pub async fn connect(addr: impl ToSocketAddrs) -> io::Result<RakConn> {
+        Self::connect_with_config(addr, Config::default()).await
+    }
+
+    /// Connects to the RakNet server at the specified address. The MTU is discovered by sending
+    /// OpenConnectionRequest1 with decreasing sizes until the server replies, after which the
+    /// offline and connection handshakes are completed. The connection returned is the same as
+    /// the one accepted by a [`RakListener`](crate::RakListener).
+    pub async fn connect_with_config(addr: impl ToSocketAddrs, config: Config) -> io::Result<RakConn> {
+        let addr = lookup_host(addr).await?.next().ok_or_else(|| {
+            io::Error::new(io::ErrorKind::InvalidInput, "could not resolve to any address")
+        })?;
+
+        let socket = UdpSocket::bind(unspecified_addr(addr)).await?;
+        Self::connect_with_transport(socket, addr, config).await
+    }
+
+    /// Connects to the RakNet server at the specified address over the transport passed instead
+    /// of a UDP socket, for example to run over a simulated [`Network`](crate::simulator::Network).
+    /// The transport must not be shared with other connections.
+    pub async fn connect_with_transport<T: Transport>(transport: T, addr: SocketAddr, config: Config) -> io::Result<RakConn> {
+        let socket = Arc::new(transport);
+        let local_addr = socket.local_addr()?;
+        let guid: i64 = random();
+
+        let (mtu, cookie) = discover_mtu(&*socket, addr, &config).await?;
+
+        let mut outg = BytesMut::new();
+        OpenConnectionRequest2 {
+            magic: Magic,
+            cookie,
+            addr,
+            mtu: (mtu as i16).into(),
+            guid: guid.into()
+        }.write(&mut outg);
+
+        let reply = request(&*socket, addr, &outg, &[PacketId::OpenConnectionReply2]).await?;
+        let reply = OpenConnectionReply2::read(&mut &reply[1..]).ok_or_else(invalid_reply)?;
+        let mtu = (reply.mtu.value() as u16).clamp(MIN_MTU, mtu);
+
+        let mut session = Session::new(addr, mtu, &config, Instant::now());
+        session.connect(guid, Instant::now());
+
+        let (datagrams_tx, datagrams) = mpsc::channel(conn::DATAGRAM_BACKLOG);
+        let (established, mut incoming) = mpsc::channel(1);
+        let (stats, _) = watch::channel(session.stats());
+        let (_, shutdown) = watch::channel(false);
+
+        tokio::spawn(read(socket.clone(), addr, datagrams_tx));
+        tokio::spawn(conn::run(socket, local_addr, addr, session, datagrams, stats, established, shutdown));
+
+        match timeout(config.handshake_timeout, incoming.recv()).await {
+            Ok(Some(conn)) => Ok(conn),
+            _ => Err(io::Error::new(io::ErrorKind::TimedOut, "connection handshake did not complete"))
+        }
+    }
+}
+
+impl RakClient {
+    /// Pings the RakNet server at the specified address with an UnconnectedPing and returns the
+    /// status it advertises in its UnconnectedPong.
+    pub async fn ping(addr: impl ToSocketAddrs) -> io::Result<ServerStatus> {
+        let addr = lookup_host(addr).await?.next().ok_or_else(|| {
+            io::Error::new(io::ErrorKind::InvalidInput, "could not resolve to any address")
+        })?;
+
+        let socket = UdpSocket::bind(unspecified_addr(addr)).await?;
+
+        let mut outg = BytesMut::new();
+        UnconnectedPing {
+            ping_time: 0.into(),
+            magic: Magic,
+            client_guid: random::<i64>().into()
+        }.write(&mut outg);
+
+        let reply = request(&socket, addr, &outg, &[PacketId::UnconnectedPong]).await?;
+        let reply = UnconnectedPong::read(&mut &reply[1..]).ok_or_else(invalid_reply)?;
+
+        ServerStatus::parse(&reply.data).ok_or_else(invalid_reply)
+    }
+}
+
+/// Returns the unspecified address of the same family as the address passed, with port 0.
+fn unspecified_addr(addr: SocketAddr) -> SocketAddr {
+    if addr.is_ipv4() {
+        (Ipv4Addr::UNSPECIFIED, 0).into()
+    } else {
+        (Ipv6Addr::UNSPECIFIED, 0).into()
+    }
+}
+
+/// Discovers the MTU by sending OpenConnectionRequest1 padded to decreasing sizes until the
+/// server replies. Returns the MTU the server replied with, along with the security cookie it
+/// sent, if any.
+///
+/// The first protocol version of the config is sent first. If the server replies with
+/// IncompatibleProtocol, the request is sent again with the version the server requires, as
+/// long as it is supported as well and was not tried before.
+async fn discover_mtu<T: Transport>(socket: &T, addr: SocketAddr, config: &Config) -> io::Result<(u16, Option<n32>)> {
+    let mut protocol = *config.protocol_versions.first().ok_or_else(|| {
+        io::Error::new(io::ErrorKind::InvalidInput, "no RakNet protocol versions configured")
+    })?;
+    // The versions tried, so that a server that keeps asking for versions already tried does not
+    // keep the client retrying forever.
+    let mut tried = vec![protocol];
+
+    for size in MTU_SIZES.into_iter().filter(|&size| size <= config.max_mtu) {
+        loop {
+            let mut outg = BytesMut::new();
+            OpenConnectionRequest1 {
+                magic: Magic,
+                protocol,
+                max_size: size as usize
+            }.write(&mut outg);
+
+            let reply = match request(socket, addr, &outg, &[PacketId::OpenConnectionReply1, PacketId::IncompatibleProtocol]).await {
+                Ok(reply) => reply,
+                Err(e) if e.kind() == io::ErrorKind::TimedOut => break,
+                Err(e) => return Err(e)
+            };
+
+            if reply[0] == PacketId::IncompatibleProtocol as u8 {
+                let reply = IncompatibleProtocol::read(&mut &reply[1..]).ok_or_else(invalid_reply)?;
+                if !config.protocol_versions.contains(&reply.protocol) {
+                    return Err(io::Error::new(io::ErrorKind::Unsupported, format!("server requires unsupported RakNet protocol version {}", reply.protocol)));
+                }
+                if tried.contains(&reply.protocol) {
+                    return Err(io::Error::new(io::ErrorKind::Unsupported, format!("server rejected RakNet protocol version {} it required", reply.protocol)));
+                }
+
+                protocol = reply.protocol;
+                tried.push(protocol);
+                continue;
+            }
+
+            let reply = OpenConnectionReply1::read(&mut &reply[1..]).ok_or_else(invalid_reply)?;
+            return Ok(((reply.mtu.value() as u16).clamp(MIN_MTU, size), reply.cookie));
+        }
+    }
+
+    Err(io::Error::new(io::ErrorKind::TimedOut, "server did not reply to OpenConnectionRequest1"))
+}
+
+/// Sends the request to the server until it replies with a packet with one of the expected IDs,
+/// and returns the reply including its ID. Fails if the server replies that it cannot accept more
+/// connections.
+async fn request<T: Transport>(socket: &T, addr: SocketAddr, req: &[u8], expected: &[PacketId]) -> io::Result<Bytes> {
+    let mut incm = vec![0; 1500];
+
+    for _ in 0..ATTEMPTS {
+        socket.send_to(req, addr).await?;
+
+        let deadline = Instant::now() + ATTEMPT_TIMEOUT;
+        while let Ok(res) = tokio::time::timeout_at(deadline, socket.recv_from(&mut incm)).await {
+            let (len, from) = res?;
+            if from != addr {
+                continue;
+            }
+
+            match PacketId::decode(&mut &incm[..len]) {
+                Some(id) if expected.contains(&id) => return Ok(Bytes::copy_from_slice(&incm[..len])),
+                Some(PacketId::NoFreeIncomingConnections) => {
+                    return Err(io::Error::new(io::ErrorKind::ConnectionRefused, "server cannot accept more connections"));
+                }
+                _ => {}
+            }
+        }
+    }
+
+    Err(io::Error::new(io::ErrorKind::TimedOut, "server did not reply"))
+}
+
+/// Reads datagrams sent by the server from the socket and passes them to the connection, until
+/// the connection is closed.
+async fn read<T: Transport>(socket: Arc<T>, addr: SocketAddr, datagrams: mpsc::Sender<Bytes>) {
+    let mut incm = vec![0; 1500];
+
+    loop {
+        tokio::select! {
+            res = socket.recv_from(&mut incm) => {
+                let Ok((len, from)) = res else {
+                    continue;
+                };
+
+                if from == addr {
+                    let _ = datagrams.try_send(Bytes::copy_from_slice(&incm[..len]));
+                }
+            }
+            _ = datagrams.closed() => break
+        }
+    }
+}
+
+fn invalid_reply() -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, "server sent an invalid reply")
+}
@@ -0,0 +1,88 @@
+use std::sync::Arc;
+use std::time::Duration;
+use crate::capture::Recorder;
+
+/// The minimum MTU a RakNet peer must support.
+pub const MIN_MTU: u16 = 576;
+
+/// Config holds the limits and timeouts applied to every connection of a listener or client.
+#[derive(Debug, Clone)]
+pub struct Config {
+    /// The amount of sockets bound to the address of the listener with `SO_REUSEPORT`. Each socket
+    /// is read by its own task.
+    pub socket_count: usize,
+    /// The maximum MTU negotiated with peers. Peers requesting a higher MTU are limited to it.
+    pub max_mtu: u16,
+    /// The maximum amount of fragments a single split message may consist of.
+    pub max_split_count: u32,
+    /// The maximum amount of split messages that may be reassembled concurrently by a connection.
+    pub max_concurrent_splits: usize,
+    /// The maximum amount of bytes buffered across all incomplete split messages of a connection.
+    pub max_split_size: usize,
+    /// The duration after which an incomplete split message is discarded.
+    pub split_timeout: Duration,
+    /// The maximum amount of frames held per order channel while waiting for the frames
+    /// preceding them to arrive.
+    pub max_ordered_queue: usize,
+    /// The maximum distance between the lowest reliable message index not yet received and any
+    /// reliable message index accepted.
+    pub max_reliable_window: u32,
+    /// The duration within which the connected-mode handshake must complete after the offline
+    /// handshake, before the connection is dropped.
+    pub handshake_timeout: Duration,
+    /// The interval at which ConnectedPing packets are sent to measure the latency of a connection
+    /// and keep it alive.
+    pub ping_interval: Duration,
+    /// The duration after which a connection is closed if nothing was received from the peer.
+    pub idle_timeout: Duration,
+    /// The maximum duration spent flushing the reliable frames still pending when a connection is
+    /// closed, after which the Disconnect is sent regardless.
+    pub close_timeout: Duration,
+    /// The maximum amount of offline messages handled per second from a single IP address, or zero
+    /// to not limit them. Addresses exceeding it are blocked for the block duration.
+    pub offline_rate_limit: u32,
+    /// The duration for which IP addresses exceeding the offline rate limit are blocked. Offline
+    /// messages from blocked addresses are dropped silently.
+    pub block_duration: Duration,
+    /// The maximum amount of connections of a listener.
+    pub max_connections: usize,
+    /// The maximum amount of connections of a listener from a single IP address.
+    pub max_connections_per_ip: usize,
+    /// Whether a security cookie is sent in OpenConnectionReply1, which the client must echo in
+    /// OpenConnectionRequest2. It prevents connections from being opened with spoofed source
+    /// addresses.
+    pub security_cookies: bool,
+    /// The RakNet protocol versions supported, in order of preference. A listener refuses clients
+    /// using any other version with IncompatibleProtocol, while a client first requests the first
+    /// version and falls back to the version a server requires if it is in the list. Minecraft
+    /// uses version 11, older Bedrock generations used version 10.
+    pub protocol_versions: Vec<u8>,
+    /// The recorder the datagrams and messages of every connection are captured with, if any.
+    pub capture: Option<Arc<Recorder>>
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            socket_count: 1,
+            max_mtu: 1492,
+            max_split_count: 512,
+            max_concurrent_splits: 16,
+            max_split_size: 8 * 1024 * 1024,
+            split_timeout: Duration::from_secs(10),
+            max_ordered_queue: 1024,
+            max_reliable_window: 16384,
+            handshake_timeout: Duration::from_secs(10),
+            ping_interval: Duration::from_secs(5),
+            idle_timeout: Duration::from_secs(10),
+            close_timeout: Duration::from_secs(2),
+            offline_rate_limit: 20,
+            block_duration: Duration::from_secs(10),
+            max_connections: 1024,
+            max_connections_per_ip: 8,
+            security_cookies: false,
+            protocol_versions: vec![11, 10],
+            capture: None
+        }
+    }
+}
@@ -0,0 +1,223 @@
+use std::time::Duration;
+
+/// The upper bound of the retransmission timeout, also used before any round trip was measured.
+const MAX_RTO: Duration = Duration::from_millis(2000);
+
+/// The variance added to the retransmission timeout to account for acknowledgements being
+/// delayed until the next update of the peer.
+const ADDITIONAL_VARIANCE: Duration = Duration::from_millis(30);
+
+/// The weight of a new round trip time sample in the smoothed round trip time.
+const RTT_GAIN: f64 = 0.05;
+
+/// SlidingWindow is a congestion controller modelled after CCRakNetSlidingWindow from RakNet.
+/// The congestion window starts at a single datagram and grows by a datagram per acknowledged
+/// datagram during slow start, after which it grows by roughly a datagram per round trip. It is
+/// halved once per block of datagrams that are negatively acknowledged, and reset to a single
+/// datagram when a datagram has to be resent because it was never acknowledged.
+pub struct SlidingWindow {
+    mtu: f64,
+    /// The congestion window in bytes.
+    cwnd: f64,
+    /// The slow start threshold in bytes. Zero until the first backoff.
+    ss_thresh: f64,
+    /// The smoothed round trip time in seconds, if any round trip was measured yet.
+    rtt: Option<f64>,
+    /// The smoothed deviation of the round trip time in seconds.
+    rtt_deviation: f64,
+    /// The amount of bytes sent in datagrams that were not acknowledged yet.
+    bytes_in_flight: usize,
+    /// The sequence number of the first datagram sent after the last backoff. Only losses of
+    /// datagrams sent after it cause another backoff, so that the losses of a single burst only
+    /// shrink the window once.
+    next_block: u32,
+    /// The sequence number of the next datagram sent.
+    next_sequence: u32
+}
+
+impl SlidingWindow {
+    pub fn new(mtu: usize) -> Self {
+        Self {
+            mtu: mtu as f64,
+            cwnd: mtu as f64,
+            ss_thresh: 0.0,
+            rtt: None,
+            rtt_deviation: 0.0,
+            bytes_in_flight: 0,
+            next_block: 0,
+            next_sequence: 0
+        }
+    }
+
+    fn in_slow_start(&self) -> bool {
+        self.ss_thresh == 0.0 || self.cwnd <= self.ss_thresh
+    }
+
+    /// Returns true if the sequence number was sent after the current congestion control block
+    /// started. Sequence numbers are 24-bit and compared with wrap-around.
+    fn in_current_block(&self, seq: u32) -> bool {
+        (seq.wrapping_sub(self.next_block) & 0xffffff) < 0x800000
+    }
+
+    /// Returns the amount of bytes that may currently be sent without exceeding the window.
+    pub fn transmission_bandwidth(&self) -> usize {
+        (self.cwnd as usize).saturating_sub(self.bytes_in_flight)
+    }
+
+    /// Registers a datagram of the specified size with the sequence number as sent.
+    pub fn on_send(&mut self, seq: u32, size: usize) {
+        self.bytes_in_flight += size;
+        self.next_sequence = (seq + 1) & 0xffffff;
+    }
+
+    /// Registers a datagram of the specified size as acknowledged by the peer after the round
+    /// trip time passed.
+    pub fn on_ack(&mut self, size: usize, rtt: Duration) {
+        self.bytes_in_flight = self.bytes_in_flight.saturating_sub(size);
+
+        let sample = rtt.as_secs_f64();
+        match self.rtt {
+            None => {
+                self.rtt = Some(sample);
+                self.rtt_deviation = sample;
+            }
+            Some(rtt) => {
+                let difference = sample - rtt;
+                self.rtt = Some(rtt + RTT_GAIN * difference);
+                self.rtt_deviation += RTT_GAIN * (difference.abs() - self.rtt_deviation);
+            }
+        }
+
+        if self.in_slow_start() {
+            self.cwnd += self.mtu;
+            if self.ss_thresh != 0.0 && self.cwnd > self.ss_thresh {
+                self.cwnd = self.ss_thresh + self.mtu * self.mtu / self.cwnd;
+            }
+        } else {
+            self.cwnd += self.mtu * self.mtu / self.cwnd;
+        }
+    }
+
+    /// Registers a datagram of the specified size as negatively acknowledged by the peer. The
+    /// window is halved once per congestion control block, and becomes the slow start threshold.
+    pub fn on_nack(&mut self, seq: u32, size: usize) {
+        self.bytes_in_flight = self.bytes_in_flight.saturating_sub(size);
+
+        if self.in_current_block(seq) {
+            self.ss_thresh = (self.cwnd / 2.0).max(self.mtu);
+            self.cwnd = self.ss_thresh;
+            self.next_block = self.next_sequence;
+        }
+    }
+
+    /// Registers a datagram of the specified size as lost because it was not acknowledged within
+    /// the retransmission timeout. The window is reset to a single datagram once per congestion
+    /// control block.
+    pub fn on_resend(&mut self, seq: u32, size: usize) {
+        self.bytes_in_flight = self.bytes_in_flight.saturating_sub(size);
+
+        if self.in_current_block(seq) && self.cwnd > self.mtu * 2.0 {
+            self.ss_thresh = (self.cwnd / 2.0).max(self.mtu);
+            self.cwnd = self.mtu;
+            self.next_block = self.next_sequence;
+        }
+    }
+
+    /// Returns the smoothed round trip time, or zero if no round trip was measured yet.
+    pub fn rtt(&self) -> Duration {
+        Duration::from_secs_f64(self.rtt.unwrap_or_default())
+    }
+
+    /// Returns the duration after which an unacknowledged datagram is resent.
+    pub fn rto(&self) -> Duration {
+        let Some(rtt) = self.rtt else {
+            return MAX_RTO;
+        };
+
+        let rto = Duration::from_secs_f64(2.0 * rtt + 4.0 * self.rtt_deviation) + ADDITIONAL_VARIANCE;
+        rto.min(MAX_RTO)
+    }
+
+    /// Returns the congestion window in bytes.
+    pub fn congestion_window(&self) -> usize {
+        self.cwnd as usize
+    }
+
+    /// Returns the amount of bytes sent in datagrams that were not acknowledged yet.
+    pub fn bytes_in_flight(&self) -> usize {
+        self.bytes_in_flight
+    }
+
+    /// Returns the estimated bandwidth in bytes per second, which is a window's worth of bytes
+    /// per round trip.
+    pub fn bandwidth(&self) -> f64 {
+        match self.rtt {
+            Some(rtt) if rtt > 0.0 => self.cwnd / rtt,
+            _ => 0.0
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MTU: usize = 1000;
+
+    /// Returns a window that grew in slow start by acknowledging the datagrams passed.
+    fn grown(datagrams: u32) -> SlidingWindow {
+        let mut window = SlidingWindow::new(MTU);
+        for seq in 0..datagrams {
+            window.on_send(seq, MTU);
+            window.on_ack(MTU, Duration::from_millis(50));
+        }
+        window
+    }
+
+    #[test]
+    fn grows_in_slow_start() {
+        let window = grown(8);
+        assert_eq!(window.congestion_window(), 9 * MTU);
+        assert_eq!(window.bytes_in_flight(), 0);
+        assert_eq!(window.rtt(), Duration::from_millis(50));
+    }
+
+    #[test]
+    fn backs_off_once_per_block_on_nack() {
+        let mut window = grown(8);
+        for seq in 8..12 {
+            window.on_send(seq, MTU);
+        }
+
+        window.on_nack(8, MTU);
+        assert_eq!(window.congestion_window(), 9 * MTU / 2);
+        assert_eq!(window.bytes_in_flight(), 3 * MTU);
+
+        // The other datagrams of the same block do not shrink the window again.
+        window.on_nack(9, MTU);
+        assert_eq!(window.congestion_window(), 9 * MTU / 2);
+        assert_eq!(window.ss_thresh, 4.5 * MTU as f64);
+
+        // Growth is linear once the window exceeds the slow start threshold.
+        window.on_ack(MTU, Duration::from_millis(50));
+        window.on_ack(MTU, Duration::from_millis(50));
+        let cwnd = window.congestion_window();
+        window.on_ack(MTU, Duration::from_millis(50));
+        assert!(window.congestion_window() - cwnd < MTU);
+    }
+
+    #[test]
+    fn resets_on_resend() {
+        let mut window = grown(8);
+        window.on_send(8, MTU);
+        window.on_send(9, MTU);
+
+        window.on_resend(8, MTU);
+        assert_eq!(window.congestion_window(), MTU);
+        assert_eq!(window.bytes_in_flight(), MTU);
+
+        // The other datagram of the same block does not shrink the window again.
+        window.on_resend(9, MTU);
+        assert_eq!(window.ss_thresh, 4.5 * MTU as f64);
+    }
+}
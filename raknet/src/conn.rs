@@ -1,62 +1,294 @@
+use std::io;
 use std::net::SocketAddr;
-use std::sync::Arc;
-use bytes::BytesMut;
-use tokio::net::UdpSocket;
+use std::sync::{Arc, OnceLock};
+use std::time::Duration;
+use bytes::Bytes;
+use tokio::sync::mpsc::error::TrySendError;
+use tokio::sync::{mpsc, watch, Mutex};
+use tokio::time::{interval, Instant};
+use crate::capture::{Direction, Kind, Recorder};
+use crate::ordering::check_channel;
+use crate::session::{DisconnectReason, Session, State};
+use crate::stats::Stats;
+use crate::transport::Transport;
+use crate::types::Reliability;
 
+/// The interval at which the session of a connection is updated.
+const TICK_INTERVAL: Duration = Duration::from_millis(10);
+
+/// The maximum amount of datagrams queued for a connection before further datagrams are dropped.
+pub(crate) const DATAGRAM_BACKLOG: usize = 1024;
+
+/// The maximum amount of messages queued by a [`RakConn`] before [`RakConn::send`] fails with
+/// [`io::ErrorKind::WouldBlock`].
+const COMMAND_BACKLOG: usize = 1024;
+
+/// The maximum amount of messages received that wait to be read with [`RakConn::recv`]. Once as
+/// many more are buffered by the session, datagrams from the peer are no longer read until the
+/// application catches up, so that the peer is slowed down by the reliability layer.
+const MESSAGE_BACKLOG: usize = 1024;
+
+/// Connections maps the addresses of all connections of a listener to their [`Route`].
+pub(crate) type Connections = papaya::HashMap<SocketAddr, Route>;
+
+/// Route is the handle a listener holds to the task driving the session of a connection.
+#[derive(Clone)]
+pub(crate) struct Route {
+    /// The channel through which datagrams received from the peer are routed to the task.
+    pub datagrams: mpsc::Sender<Bytes>,
+    /// The statistics of the session, as of its last update.
+    pub stats: watch::Receiver<Stats>
+}
+
+/// Capture records the datagrams and messages of a connection if a [`Recorder`] is configured.
+struct Capture {
+    recorder: Option<Arc<Recorder>>,
+    local_addr: SocketAddr,
+    remote_addr: SocketAddr
+}
+
+impl Capture {
+    fn record(&self, direction: Direction, kind: Kind, data: &[u8]) {
+        if let Some(recorder) = &self.recorder {
+            recorder.record(direction, kind, self.local_addr, self.remote_addr, data);
+        }
+    }
+}
+
+/// Command is a message sent from a [`RakConn`] to the task driving its session.
+struct Command {
+    data: Bytes,
+    reliability: Reliability,
+    channel: u8
+}
+
+/// RakConn is a connection with a RakNet peer that completed the connection handshake. Messages
+/// are sent and received through it, while a background task drives the session.
 pub struct RakConn {
     pub local_addr: SocketAddr,
     pub remote_addr: SocketAddr,
-    pub socket: Arc<UdpSocket>
+    pub mtu: u16,
+    commands: mpsc::Sender<Command>,
+    messages: Mutex<mpsc::Receiver<Bytes>>,
+    close: watch::Sender<Option<DisconnectReason>>,
+    stats: watch::Receiver<Stats>,
+    disconnect_reason: Arc<OnceLock<DisconnectReason>>
 }
 
 impl RakConn {
-    pub fn new(local_addr: SocketAddr, remote_addr: SocketAddr) -> Self {
-        let udp_sock = socket2::Socket::new(
-            if local_addr.is_ipv4() {
-                socket2::Domain::IPV4
-            } else {
-                socket2::Domain::IPV6
-            },
-            socket2::Type::DGRAM,
-            None,
-        ).unwrap();
-
-        udp_sock.set_reuse_port(true).unwrap();
-        udp_sock.set_cloexec(true).unwrap();
-        udp_sock.set_nonblocking(true).unwrap();
-        udp_sock.bind(&socket2::SockAddr::from(local_addr)).unwrap();
-
-        let udp_sock: std::net::UdpSocket = udp_sock.into();
-        udp_sock.connect(remote_addr).expect("Cannot connect the UdpSocket to remote addr");
-
-        let socket: UdpSocket = udp_sock.try_into().unwrap();
-
-        Self {
-            local_addr,
-            remote_addr,
-            socket: socket.into()
-        }
+    /// Sends a message to the peer with the specified reliability on the first order channel.
+    pub fn send(&self, data: Bytes, reliability: Reliability) -> io::Result<()> {
+        self.send_on_channel(data, reliability, 0)
+    }
+
+    /// Sends a message to the peer with the specified reliability on the specified order channel.
+    /// The channel must be lower than [`MAX_ORDER_CHANNELS`](crate::ordering::MAX_ORDER_CHANNELS),
+    /// otherwise an error of the kind [`io::ErrorKind::InvalidInput`] is returned.
+    ///
+    /// Messages are queued for the task driving the session. If the queue is full, because the
+    /// peer does not keep up, an error of the kind [`io::ErrorKind::WouldBlock`] is returned and
+    /// [`RakConn::ready`] may be awaited before sending again.
+    pub fn send_on_channel(&self, data: Bytes, reliability: Reliability, channel: u8) -> io::Result<()> {
+        check_channel(channel)?;
+        self.commands
+            .try_send(Command { data, reliability, channel })
+            .map_err(|err| match err {
+                TrySendError::Full(_) => io::Error::new(io::ErrorKind::WouldBlock, "send queue is full"),
+                TrySendError::Closed(_) => io::Error::from(io::ErrorKind::NotConnected)
+            })
+    }
+
+    /// Waits until the send queue has room for another message, so that the next call to
+    /// [`RakConn::send`] does not fail with [`io::ErrorKind::WouldBlock`], unless other tasks send
+    /// on the connection concurrently.
+    pub async fn ready(&self) -> io::Result<()> {
+        self.commands
+            .reserve()
+            .await
+            .map(drop)
+            .map_err(|_| io::Error::from(io::ErrorKind::NotConnected))
+    }
+
+    /// Waits for the next message from the peer. Returns None once the connection is closed, after
+    /// which [`RakConn::disconnect_reason`] returns why it was closed.
+    pub async fn recv(&self) -> Option<Bytes> {
+        self.messages.lock().await.recv().await
+    }
+
+    /// Returns a snapshot of the statistics of the connection, as of its last update.
+    pub fn stats(&self) -> Stats {
+        self.stats.borrow().clone()
+    }
+
+    /// Returns the smoothed latency of the connection, measured with the ConnectedPing packets
+    /// sent periodically. It is zero until the peer answered the first ping.
+    pub fn latency(&self) -> Duration {
+        self.stats.borrow().latency
+    }
+
+    /// Returns the reason the connection was closed, or None if it is still open.
+    pub fn disconnect_reason(&self) -> Option<DisconnectReason> {
+        self.disconnect_reason.get().copied()
+    }
+
+    /// Closes the connection for the reason passed. Messages already sent are flushed to the peer
+    /// first, for at most [`Config::close_timeout`](crate::Config::close_timeout), after which it
+    /// is notified with a Disconnect. Messages sent after closing are discarded.
+    /// [`RakConn::recv`] returns None once the connection is closed.
+    pub fn close(&self, reason: DisconnectReason) {
+        let _ = self.close.send(Some(reason));
     }
+}
+
+/// Drives the session of a connection until it is closed. Datagrams received from the peer are
+/// read from the channel passed and datagrams produced are sent through the socket. The session
+/// is updated every tick, which sends the frames queued since the last tick in as many datagrams
+/// as the congestion window allows. Once the connection handshake completes, the connection is
+/// sent to the established channel.
+///
+/// The statistics of the session are published to the stats channel every tick and once more
+/// when it is closed.
+///
+/// The connection is closed gracefully once the value of the shutdown channel becomes true.
+/// Connections not owned by a listener pass a channel whose sender was dropped, which never
+/// signals.
+#[allow(clippy::too_many_arguments)]
+pub(crate) async fn run<T: Transport>(socket: Arc<T>, local_addr: SocketAddr, remote_addr: SocketAddr, mut session: Session, mut datagrams: mpsc::Receiver<Bytes>, stats: watch::Sender<Stats>, established: mpsc::Sender<RakConn>, mut shutdown: watch::Receiver<bool>) {
+    let (commands_tx, mut commands) = mpsc::channel(COMMAND_BACKLOG);
+    let (messages, messages_rx) = mpsc::channel(MESSAGE_BACKLOG);
+    let (close_tx, mut close) = watch::channel(None);
+    let stats_rx = stats.subscribe();
+    let disconnect_reason = Arc::new(OnceLock::new());
+    let capture = Capture {
+        recorder: session.config().capture.clone(),
+        local_addr,
+        remote_addr
+    };
+
+    let mut conn = Some(RakConn {
+        local_addr,
+        remote_addr,
+        mtu: session.mtu(),
+        commands: commands_tx,
+        messages: Mutex::new(messages_rx),
+        close: close_tx,
+        stats: stats_rx,
+        disconnect_reason: disconnect_reason.clone()
+    });
 
-    pub fn start(&self) {
-        let remote_addr = self.remote_addr.clone();
-        let socket = self.socket.clone();
+    let mut ticker = interval(TICK_INTERVAL);
+    let mut commands_closed = false;
 
-        tokio::spawn(async move {
-            let mut incm = BytesMut::new();
-            let mut outg = BytesMut::new();
+    if *shutdown.borrow_and_update() {
+        session.disconnect(DisconnectReason::Shutdown, Instant::now());
+    }
 
-            loop {
-                incm.resize(1500, 0);
-                outg.clear();
+    loop {
+        tokio::select! {
+            // Datagrams are left in the backlog while the application does not read the messages
+            // already received.
+            datagram = datagrams.recv(), if session.pending_messages() < MESSAGE_BACKLOG => {
+                match datagram {
+                    Some(datagram) => {
+                        capture.record(Direction::Inbound, Kind::Datagram, &datagram);
+                        session.handle(&datagram, Instant::now());
+                    }
+                    // Acknowledgements cannot be received anymore, so there is no point in
+                    // flushing what is still pending.
+                    None => session.disconnect(DisconnectReason::Closed, Instant::now())
+                }
+            }
+            cmd = commands.recv(), if !commands_closed => {
+                match cmd {
+                    Some(cmd) => handle_command(&mut session, &capture, cmd),
+                    None => {
+                        commands_closed = true;
+                        session.close(DisconnectReason::Closed, Instant::now());
+                    }
+                }
+            }
+            Ok(()) = close.changed() => {
+                if let Some(reason) = *close.borrow_and_update() {
+                    // Messages sent before closing are still delivered.
+                    while let Ok(cmd) = commands.try_recv() {
+                        handle_command(&mut session, &capture, cmd);
+                    }
+                    session.close(reason, Instant::now());
+                }
+            }
+            permit = messages.reserve(), if session.pending_messages() > 0 => {
+                match permit {
+                    Ok(permit) => deliver(&mut session, &capture, permit),
+                    // The connection was dropped, so nobody reads the messages anymore.
+                    Err(_) => while session.recv().is_some() {}
+                }
+            }
+            Ok(()) = shutdown.changed() => {
+                if *shutdown.borrow_and_update() {
+                    // Messages sent before the shutdown are still delivered.
+                    while let Ok(cmd) = commands.try_recv() {
+                        handle_command(&mut session, &capture, cmd);
+                    }
+                    session.close(DisconnectReason::Shutdown, Instant::now());
+                }
+            }
+            _ = ticker.tick() => {
+                session.update(Instant::now());
+                stats.send_replace(session.stats());
+            }
+        }
+
+        // The connection is handed over without waiting, so that a full accept backlog does not
+        // hold up the session. It is handed over again on the next tick until there is room.
+        if session.state() == State::Connected {
+            if let Some(c) = conn.take() {
+                match established.try_send(c) {
+                    Ok(()) => {}
+                    Err(TrySendError::Full(c)) => conn = Some(c),
+                    Err(TrySendError::Closed(_)) => session.close(DisconnectReason::Shutdown, Instant::now())
+                }
+            }
+        }
 
-                let len = match socket.recv(&mut incm).await {
-                    Ok(v) => v,
-                    Err(_) => continue
-                };
-                
-                println!("Received {} bytes from {}", len, remote_addr)
+        while session.pending_messages() > 0 {
+            match messages.try_reserve() {
+                Ok(permit) => deliver(&mut session, &capture, permit),
+                Err(TrySendError::Full(())) => break,
+                Err(TrySendError::Closed(())) => while session.recv().is_some() {}
             }
-        });
+        }
+
+        while let Some(datagram) = session.poll_transmit() {
+            capture.record(Direction::Outbound, Kind::Datagram, &datagram);
+            if socket.send_to(&datagram, remote_addr).await.is_err() {
+                break;
+            }
+        }
+
+        if session.state() == State::Closed {
+            if let Some(reason) = session.disconnect_reason() {
+                let _ = disconnect_reason.set(reason);
+            }
+            break;
+        }
+    }
+
+    stats.send_replace(session.stats());
+}
+
+/// Handles a command sent by the [`RakConn`] of a session.
+fn handle_command(session: &mut Session, capture: &Capture, cmd: Command) {
+    if session.state() == State::Connected {
+        capture.record(Direction::Outbound, Kind::Message, &cmd.data);
+        // The channel was checked by RakConn::send_on_channel already.
+        let _ = session.send(cmd.data, cmd.reliability, cmd.channel);
+    }
+}
+
+/// Delivers the next message received by the session to the [`RakConn`] with the permit passed.
+fn deliver(session: &mut Session, capture: &Capture, permit: mpsc::Permit<Bytes>) {
+    if let Some(msg) = session.recv() {
+        capture.record(Direction::Inbound, Kind::Message, &msg);
+        permit.send(msg);
     }
 }
\ No newline at end of file
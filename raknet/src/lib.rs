@@ -0,0 +1,27 @@
+pub mod packet;
+pub mod types;
+pub mod capture;
+pub mod client;
+pub mod config;
+pub mod congestion;
+pub mod conn;
+pub mod limiter;
+pub mod listener;
+pub mod metrics;
+pub mod ordering;
+pub mod session;
+pub mod simulator;
+pub mod split;
+pub mod stats;
+pub mod status;
+pub mod transport;
+
+pub use client::*;
+pub use config::*;
+pub use conn::*;
+pub use listener::*;
+pub use stats::*;
+pub use status::*;
+pub use transport::*;
+pub use session::DisconnectReason;
+pub use types::Reliability;
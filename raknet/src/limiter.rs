@@ -0,0 +1,249 @@
+use std::collections::HashMap;
+use std::hash::{BuildHasher, RandomState};
+use std::net::{IpAddr, SocketAddr};
+use std::sync::Mutex;
+use std::time::Duration;
+use tokio::time::Instant;
+use crate::config::Config;
+
+/// The interval at which the rate limiting state of addresses that went quiet is discarded.
+const SWEEP_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Bucket is the token bucket limiting the rate of offline messages from a single IP address.
+struct Bucket {
+    tokens: f64,
+    updated: Instant
+}
+
+/// State is the state of a [`Limiter`], guarded by a single lock.
+struct State {
+    buckets: HashMap<IpAddr, Bucket>,
+    /// The addresses blocked, along with the time at which they are unblocked.
+    blocked: HashMap<IpAddr, Instant>,
+    /// The amount of connections per IP address.
+    connections: HashMap<IpAddr, usize>,
+    total_connections: usize,
+    last_sweep: Instant
+}
+
+/// Limiter protects a listener from floods of offline messages and connections before the
+/// connection handshake. It limits the rate of offline messages per IP address, blocking those
+/// that exceed it, caps the amount of connections per IP address and in total, and hands out the
+/// security cookies sent in OpenConnectionReply1.
+pub(crate) struct Limiter {
+    offline_rate_limit: u32,
+    block_duration: Duration,
+    max_connections: usize,
+    max_connections_per_ip: usize,
+    /// The keys of the hash that cookies are derived from, random for every listener.
+    cookie_keys: RandomState,
+    state: Mutex<State>
+}
+
+impl Limiter {
+    pub fn new(config: &Config) -> Self {
+        Self {
+            offline_rate_limit: config.offline_rate_limit,
+            block_duration: config.block_duration,
+            max_connections: config.max_connections,
+            max_connections_per_ip: config.max_connections_per_ip,
+            cookie_keys: RandomState::new(),
+            state: Mutex::new(State {
+                buckets: HashMap::new(),
+                blocked: HashMap::new(),
+                connections: HashMap::new(),
+                total_connections: 0,
+                last_sweep: Instant::now()
+            })
+        }
+    }
+
+    /// Returns whether an offline message from the IP address should be handled. Addresses that
+    /// exceed the rate limit are blocked for the block duration.
+    pub fn allow_offline(&self, ip: IpAddr, now: Instant) -> bool {
+        let mut state = self.state.lock().unwrap();
+        let state = &mut *state;
+
+        if now.duration_since(state.last_sweep) >= SWEEP_INTERVAL {
+            let rate = self.offline_rate_limit as f64;
+            state.buckets.retain(|_, bucket| bucket.tokens + now.duration_since(bucket.updated).as_secs_f64() * rate < rate);
+            state.blocked.retain(|_, until| *until > now);
+            state.last_sweep = now;
+        }
+
+        if state.blocked.get(&ip).is_some_and(|&until| until > now) {
+            return false;
+        }
+
+        if self.offline_rate_limit == 0 {
+            return true;
+        }
+
+        let rate = self.offline_rate_limit as f64;
+        let bucket = state.buckets.entry(ip).or_insert(Bucket {
+            tokens: rate,
+            updated: now
+        });
+
+        bucket.tokens = (bucket.tokens + now.duration_since(bucket.updated).as_secs_f64() * rate).min(rate);
+        bucket.updated = now;
+
+        if bucket.tokens < 1.0 {
+            state.buckets.remove(&ip);
+            state.blocked.insert(ip, now + self.block_duration);
+            return false;
+        }
+
+        bucket.tokens -= 1.0;
+        true
+    }
+
+    /// Blocks the IP address until the time passed.
+    pub fn block(&self, ip: IpAddr, until: Instant) {
+        self.state.lock().unwrap().blocked.insert(ip, until);
+    }
+
+    /// Unblocks the IP address.
+    pub fn unblock(&self, ip: IpAddr) {
+        self.state.lock().unwrap().blocked.remove(&ip);
+    }
+
+    /// Reserves a connection for the IP address. Returns false if the connection caps are
+    /// reached, in which case the connection must be refused.
+    pub fn try_connect(&self, ip: IpAddr) -> bool {
+        let mut state = self.state.lock().unwrap();
+        if state.total_connections >= self.max_connections {
+            return false;
+        }
+
+        let count = state.connections.entry(ip).or_insert(0);
+        if *count >= self.max_connections_per_ip {
+            return false;
+        }
+
+        *count += 1;
+        state.total_connections += 1;
+        true
+    }
+
+    /// Releases a connection reserved with [`Limiter::try_connect`] once it is closed.
+    pub fn disconnect(&self, ip: IpAddr) {
+        let mut state = self.state.lock().unwrap();
+        if let Some(count) = state.connections.get_mut(&ip) {
+            *count -= 1;
+            if *count == 0 {
+                state.connections.remove(&ip);
+            }
+            state.total_connections -= 1;
+        }
+    }
+
+    /// Returns the security cookie for the address. Cookies are derived from the address with a
+    /// keyed hash, so that they do not need to be stored, and can only be known by receiving the
+    /// OpenConnectionReply1 sent to the address.
+    pub fn cookie(&self, addr: SocketAddr) -> u32 {
+        self.cookie_keys.hash_one(addr) as u32
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const IP: IpAddr = IpAddr::V4(std::net::Ipv4Addr::new(10, 0, 0, 1));
+    const OTHER_IP: IpAddr = IpAddr::V4(std::net::Ipv4Addr::new(10, 0, 0, 2));
+
+    fn limiter(offline_rate_limit: u32, max_connections: usize, max_connections_per_ip: usize) -> Limiter {
+        Limiter::new(&Config {
+            offline_rate_limit,
+            block_duration: Duration::from_secs(10),
+            max_connections,
+            max_connections_per_ip,
+            ..Default::default()
+        })
+    }
+
+    #[test]
+    fn offline_messages_above_the_rate_block_the_address() {
+        let limiter = limiter(5, 10, 10);
+        let now = Instant::now();
+
+        for _ in 0..5 {
+            assert!(limiter.allow_offline(IP, now));
+        }
+        assert!(!limiter.allow_offline(IP, now));
+        assert!(limiter.allow_offline(OTHER_IP, now));
+
+        // Blocked addresses stay blocked even though their bucket refilled.
+        assert!(!limiter.allow_offline(IP, now + Duration::from_secs(9)));
+        assert!(limiter.allow_offline(IP, now + Duration::from_secs(10)));
+    }
+
+    #[test]
+    fn tokens_refill_at_the_rate() {
+        let limiter = limiter(10, 10, 10);
+        let now = Instant::now();
+
+        for _ in 0..10 {
+            assert!(limiter.allow_offline(IP, now));
+        }
+        // A tenth of a second later, a single token was added.
+        let later = now + Duration::from_millis(100);
+        assert!(limiter.allow_offline(IP, later));
+        assert!(!limiter.allow_offline(IP, later));
+    }
+
+    #[test]
+    fn no_rate_limit_allows_everything() {
+        let limiter = limiter(0, 10, 10);
+        let now = Instant::now();
+
+        assert!((0..1000).all(|_| limiter.allow_offline(IP, now)));
+    }
+
+    #[test]
+    fn blocks_expire_and_can_be_lifted() {
+        let limiter = limiter(0, 10, 10);
+        let now = Instant::now();
+
+        limiter.block(IP, now + Duration::from_secs(5));
+        assert!(!limiter.allow_offline(IP, now));
+        assert!(!limiter.allow_offline(IP, now + Duration::from_secs(4)));
+        assert!(limiter.allow_offline(IP, now + Duration::from_secs(5)));
+
+        limiter.block(IP, now + Duration::from_secs(60));
+        limiter.unblock(IP);
+        assert!(limiter.allow_offline(IP, now));
+    }
+
+    #[test]
+    fn connections_are_capped_per_address_and_in_total() {
+        let limiter = limiter(0, 3, 2);
+
+        assert!(limiter.try_connect(IP));
+        assert!(limiter.try_connect(IP));
+        assert!(!limiter.try_connect(IP));
+        assert!(limiter.try_connect(OTHER_IP));
+        // The total cap is reached, although the other address has room.
+        assert!(!limiter.try_connect(OTHER_IP));
+
+        limiter.disconnect(IP);
+        assert!(limiter.try_connect(OTHER_IP));
+        assert!(!limiter.try_connect(IP));
+
+        // Releasing an address without connections does nothing.
+        limiter.disconnect("10.0.0.3".parse().unwrap());
+        assert!(!limiter.try_connect(IP));
+    }
+
+    #[test]
+    fn cookies_are_bound_to_the_address_and_listener() {
+        let limiter = limiter(0, 10, 10);
+        let addr = SocketAddr::new(IP, 19132);
+
+        assert_eq!(limiter.cookie(addr), limiter.cookie(addr));
+        assert_ne!(limiter.cookie(addr), limiter.cookie(SocketAddr::new(IP, 19133)));
+        assert_ne!(limiter.cookie(addr), limiter.cookie(SocketAddr::new(OTHER_IP, 19132)));
+        assert_ne!(limiter.cookie(addr), Limiter::new(&Config::default()).cookie(addr));
+    }
+}
@@ -1,116 +1,448 @@
-use std::net::SocketAddr;
-use std::str::FromStr;
-use bytes::BytesMut;
-use tokio::net::UdpSocket;
+use std::io;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+use bytes::{Bytes, BytesMut};
+use tokio::net::{lookup_host, ToSocketAddrs, UdpSocket};
+use tokio::sync::{mpsc, watch, Mutex};
+use tokio::task::JoinHandle;
+use tokio::time::Instant;
 use binary::{b64, Decode};
 use rand::random;
-use crate::conn::RakConn;
-use crate::packet::{OpenConnectionReply1, OpenConnectionReply2, OpenConnectionRequest1, OpenConnectionRequest2, Packet, PacketId, UnconnectedPing, UnconnectedPong};
-use crate::types::Magic;
+use crate::config::Config;
+use crate::config::MIN_MTU;
+use crate::conn::{self, Connections, RakConn, Route};
+use crate::limiter::Limiter;
+use crate::metrics::{self, Exposition};
+use crate::packet::{IncompatibleProtocol, NoFreeIncomingConnections, OpenConnectionReply1, OpenConnectionReply2, OpenConnectionRequest1, OpenConnectionRequest2, Packet, PacketId, UnconnectedPing, UnconnectedPong};
+use crate::session::Session;
+use crate::stats::{Counters, ListenerStats, Stats};
+use crate::status::ServerStatus;
+use crate::transport::Transport;
+use crate::types::{Magic, FLAG_VALID};
 
+/// The maximum amount of established connections waiting to be accepted by the application.
+const ACCEPT_BACKLOG: usize = 64;
+
+/// RakListener listens for RakNet connections on a UDP socket. It handles the offline handshake
+/// and hands out connections once their connection handshake has completed.
+///
+/// All connections share the socket of the listener: datagrams received are routed to the
+/// connection they belong to by their source address. If [`Config::socket_count`] is greater
+/// than one, a pool of sockets bound to the same address with `SO_REUSEPORT` is used instead,
+/// and the kernel consistently distributes peers over them by hashing their address.
 pub struct RakListener {
     pub addr: SocketAddr,
+    pub guid: i64,
+    listener: Arc<Listener>,
+    incoming: Mutex<mpsc::Receiver<RakConn>>,
+    /// Set to true once the listener stopped, so that [`RakListener::accept`] stops waiting.
+    stopped: watch::Sender<bool>,
+    /// The tasks receiving from the sockets of the listener.
+    tasks: Vec<JoinHandle<()>>
+}
+
+/// Listener holds the state shared by the tasks receiving from the sockets of a listener.
+struct Listener {
+    addr: SocketAddr,
+    guid: b64,
+    config: Config,
+    status: RwLock<ServerStatus>,
+    connections: Arc<Connections>,
+    established: mpsc::Sender<RakConn>,
+    limiter: Arc<Limiter>,
+    /// Set to true when the listener is shut down. Every connection holds a receiver of it.
+    shutdown: watch::Sender<bool>,
+    counters: Arc<ListenerCounters>
+}
+
+/// ListenerCounters are the counters of a listener, shared by the tasks receiving from its
+/// sockets and those driving its connections.
+#[derive(Default)]
+struct ListenerCounters {
+    connections_opened: AtomicU64,
+    connections_refused: AtomicU64,
+    offline_received: AtomicU64,
+    offline_dropped: AtomicU64,
+    /// The counters of all connections that were closed. Connections are removed from the
+    /// connections of the listener while it is held, so that they are never counted twice.
+    closed: std::sync::Mutex<Counters>
 }
 
 impl RakListener {
-    pub fn new(bind: &str) -> Self {
-        let addr = SocketAddr::from_str(bind).expect("Cannot parse SocketAddr from the specified bind address.");
+    /// Binds a RakListener to the specified address using the default [`Config`].
+    pub async fn bind(addr: impl ToSocketAddrs) -> io::Result<Self> {
+        Self::bind_with_config(addr, Config::default()).await
+    }
+
+    /// Binds a RakListener to the specified address. The config passed applies to all connections
+    /// accepted by the listener.
+    pub async fn bind_with_config(addr: impl ToSocketAddrs, config: Config) -> io::Result<Self> {
+        let addr = lookup_host(addr).await?.next().ok_or_else(|| {
+            io::Error::new(io::ErrorKind::InvalidInput, "could not resolve to any address")
+        })?;
+
+        // The first socket is bound before the others so that they share its port if the address
+        // passed did not specify one.
+        let first = bind_socket(addr)?;
+        let addr = first.local_addr()?;
+
+        let mut sockets = vec![first];
+        for _ in 1..config.socket_count {
+            sockets.push(bind_socket(addr)?);
+        }
+
+        Self::with_transports(sockets, config)
+    }
 
-        Self {
+    /// Creates a RakListener receiving from the transports passed instead of binding UDP sockets,
+    /// for example to run over a simulated [`Network`](crate::simulator::Network). All transports
+    /// must be bound to the same address, and peers must consistently be received from by the
+    /// same transport.
+    pub fn with_transports<T: Transport>(transports: Vec<T>, config: Config) -> io::Result<Self> {
+        let addr = transports.first().ok_or_else(|| {
+            io::Error::new(io::ErrorKind::InvalidInput, "no transports passed")
+        })?.local_addr()?;
+
+        let guid = random();
+        let (established, incoming) = mpsc::channel(ACCEPT_BACKLOG);
+
+        let status = ServerStatus {
+            server_guid: guid,
+            ..Default::default()
+        };
+
+        let listener = Arc::new(Listener {
             addr,
+            guid: b64::new(guid),
+            status: RwLock::new(status),
+            connections: Arc::new(Connections::new()),
+            established,
+            limiter: Arc::new(Limiter::new(&config)),
+            shutdown: watch::Sender::new(false),
+            counters: Arc::new(ListenerCounters::default()),
+            config
+        });
+
+        let tasks = transports
+            .into_iter()
+            .map(|transport| tokio::spawn(listener.clone().listen(Arc::new(transport))))
+            .collect();
+
+        Ok(Self {
+            addr,
+            guid,
+            listener,
+            incoming: Mutex::new(incoming),
+            stopped: watch::Sender::new(false),
+            tasks
+        })
+    }
+
+    /// Returns the status currently advertised to clients pinging the listener.
+    pub fn status(&self) -> ServerStatus {
+        self.listener.status.read().unwrap().clone()
+    }
+
+    /// Updates the status advertised to clients pinging the listener. It takes effect for the
+    /// next ping received. The server GUID of the status is replaced with that of the listener.
+    pub fn set_status(&self, mut status: ServerStatus) {
+        status.server_guid = self.guid;
+        *self.listener.status.write().unwrap() = status;
+    }
+
+    /// Returns a snapshot of the statistics of the listener, including the counters of all
+    /// connections it opened.
+    pub fn stats(&self) -> ListenerStats {
+        let listener = &self.listener;
+        let closed = listener.counters.closed.lock().unwrap();
+
+        let mut stats = ListenerStats {
+            connections_opened: listener.counters.connections_opened.load(Ordering::Relaxed),
+            connections_refused: listener.counters.connections_refused.load(Ordering::Relaxed),
+            offline_received: listener.counters.offline_received.load(Ordering::Relaxed),
+            offline_dropped: listener.counters.offline_dropped.load(Ordering::Relaxed),
+            counters: *closed,
+            ..Default::default()
+        };
+
+        for route in listener.connections.pin().values() {
+            let conn = route.stats.borrow();
+            stats.connections += 1;
+            stats.queued_frames += conn.queued_frames;
+            stats.bytes_in_flight += conn.bytes_in_flight;
+            stats.counters += conn.counters;
         }
+
+        stats
     }
 
-    pub fn listen(&self) {
-        let udp_sock = socket2::Socket::new(
-            if self.addr.is_ipv4() {
-                socket2::Domain::IPV4
-            } else {
-                socket2::Domain::IPV6
-            },
-            socket2::Type::DGRAM,
-            None,
-        ).unwrap();
+    /// Returns a snapshot of the statistics of every open connection, as of their last update.
+    pub fn connection_stats(&self) -> Vec<(SocketAddr, Stats)> {
+        self.listener.connections
+            .pin()
+            .iter()
+            .map(|(&addr, route)| (addr, route.stats.borrow().clone()))
+            .collect()
+    }
 
-        udp_sock.set_reuse_port(true).unwrap();
-        udp_sock.set_cloexec(true).unwrap();
-        udp_sock.set_nonblocking(true).unwrap();
-        udp_sock.bind(&socket2::SockAddr::from(self.addr)).unwrap();
+    /// Writes the metrics of the listener in the Prometheus text format, for example to be served
+    /// with [`metrics::serve`]. The counters of the connections are summed up.
+    pub fn write_metrics(&self, e: &mut Exposition) {
+        metrics::write_listener(e, &self.stats());
+    }
 
-        let udp_sock: std::net::UdpSocket = udp_sock.into();
-        let socket: UdpSocket = udp_sock.try_into().unwrap();
+    /// Writes the metrics of each open connection in the Prometheus text format, labelled with
+    /// their remote address. Every address creates new time series, so these metrics are only
+    /// meant for listeners with few, known peers, such as in tests or on a proxy.
+    pub fn write_connection_metrics(&self, e: &mut Exposition) {
+        metrics::write_connections(e, &self.connection_stats());
+    }
 
-        let guid = b64::new(random());
-        let local_addr = self.addr.clone();
+    /// Waits for the next connection that completed the connection handshake. Returns None once
+    /// the listener stopped.
+    pub async fn accept(&self) -> Option<RakConn> {
+        let mut stopped = self.stopped.subscribe();
+        let mut incoming = self.incoming.lock().await;
 
-        tokio::spawn(async move {
-            let mut incm = BytesMut::new();
-            let mut outg = BytesMut::new();
+        tokio::select! {
+            biased;
+            _ = stopped.wait_for(|stopped| *stopped) => None,
+            conn = incoming.recv() => conn
+        }
+    }
 
-            loop {
-                incm.resize(1500, 0);
-                outg.clear();
+    /// Blocks the IP address for the duration passed. Offline messages from the address are dropped
+    /// silently, so that it cannot open new connections. Connections already open are unaffected.
+    pub fn block(&self, ip: IpAddr, duration: Duration) {
+        self.listener.limiter.block(ip, Instant::now() + duration);
+    }
 
-                let (len, addr) = match socket.recv_from(&mut incm).await {
-                    Ok(v) => v,
-                    Err(_) => continue
-                };
+    /// Unblocks the IP address, whether it was blocked by [`RakListener::block`] or because it
+    /// exceeded the offline rate limit.
+    pub fn unblock(&self, ip: IpAddr) {
+        self.listener.limiter.unblock(ip);
+    }
 
-                let r = &mut &incm[..len];
+    /// Shuts the listener down. New connections are refused and all connections are closed
+    /// gracefully, as if [`RakConn::close`] was called on them. Once every connection is closed,
+    /// the sockets stop being read and [`RakListener::accept`] returns None.
+    pub async fn shutdown(&self) {
+        self.listener.shutdown.send_replace(true);
+        self.listener.shutdown.closed().await;
 
-                println!("Received #1 {:?} bytes from {}", r, addr);
+        for task in &self.tasks {
+            task.abort();
+        }
 
-                let id = PacketId::decode(r).unwrap();
+        // Calls to accept that are waiting hold the receiver of incoming connections, so they
+        // are woken up instead of closing it.
+        self.stopped.send_replace(true);
+    }
+}
 
-                if id == PacketId::UnconnectedPing {
-                    let pk = UnconnectedPing::read(r).unwrap();
-                    println!("{:?} sent {} bytes => {:?}", addr, len, pk);
+impl Drop for RakListener {
+    fn drop(&mut self) {
+        for task in &self.tasks {
+            task.abort();
+        }
+    }
+}
 
-                    let pk = UnconnectedPong {
-                        pong_time: pk.ping_time,
-                        guid: guid.clone(),
-                        magic: Magic,
-                        data: "MCPE;Dedicated Server;390;1.14.60;0;10;13253860892328930865;Bedrock level;Survival;1;19132;19133;".into(),
-                    };
+impl Listener {
+    /// Receives datagrams from the socket. Datagrams from connected peers are routed to their
+    /// connection, all others are handled as part of the offline handshake.
+    async fn listen<T: Transport>(self: Arc<Self>, socket: Arc<T>) {
+        let mut incm = BytesMut::new();
 
-                    pk.write(&mut outg);
-                    socket.send_to(&outg[..], addr).await.unwrap();
-                }
+        loop {
+            incm.resize(1500, 0);
 
-                if id == PacketId::OpenConnectionRequest1 {
-                    let pk = OpenConnectionRequest1::read(r).unwrap();
-                    println!("{:?} sent {} bytes => {:?}", addr, len, pk);
+            let (len, addr) = match socket.recv_from(&mut incm).await {
+                Ok(v) => v,
+                Err(_) => continue
+            };
 
-                    let pk = OpenConnectionReply1 {
-                        magic: Magic,
-                        guid: guid.clone(),
-                        secure: false,
-                        mtu: 1492.into(),
-                    };
+            if len == 0 {
+                continue;
+            }
 
-                    pk.write(&mut outg);
-                    socket.send_to(&outg[..], addr).await.unwrap();
+            if incm[0] & FLAG_VALID != 0 {
+                let datagrams = self.connections.pin().get(&addr).map(|route| route.datagrams.clone());
+                if let Some(datagrams) = datagrams {
+                    // A full queue means the connection cannot keep up, so the datagram is
+                    // dropped as if it was lost in transit.
+                    let _ = datagrams.try_send(Bytes::copy_from_slice(&incm[..len]));
                 }
+                continue;
+            }
 
-                if id == PacketId::OpenConnectionRequest2 {
-                    let pk = OpenConnectionRequest2::read(r).unwrap();
-                    println!("{:?} sent {} bytes => {:?}", addr, len, pk);
+            self.counters.offline_received.fetch_add(1, Ordering::Relaxed);
+            if !self.limiter.allow_offline(addr.ip(), Instant::now()) {
+                self.counters.offline_dropped.fetch_add(1, Ordering::Relaxed);
+                continue;
+            }
+
+            self.handle_offline(&socket, &incm[..len], addr).await;
+        }
+    }
 
-                    let pk = OpenConnectionReply2 {
-                        magic: Magic,
-                        guid: guid.clone(),
-                        addr,
-                        mtu: pk.mtu,
-                        secure: false,
-                    };
+    /// Handles a datagram that is part of the offline handshake or an unconnected ping. Datagrams
+    /// that cannot be decoded, including those with an invalid magic, are dropped silently.
+    async fn handle_offline<T: Transport>(&self, socket: &Arc<T>, mut r: &[u8], addr: SocketAddr) {
+        let mut outg = BytesMut::new();
 
-                    pk.write(&mut outg);
-                    socket.send_to(&outg[..], addr).await.unwrap();
+        let Some(id) = PacketId::decode(&mut r) else {
+            return;
+        };
 
-                    let conn = RakConn::new(local_addr, addr);
-                    conn.start();
-                }
+        // Pings are still answered while shutting down, but no new connections are opened.
+        if id != PacketId::UnconnectedPing && *self.shutdown.borrow() {
+            return;
+        }
+
+        if id == PacketId::UnconnectedPing {
+            let Some(pk) = UnconnectedPing::read(&mut r) else {
+                return;
+            };
+
+            let data = self.status.read().unwrap().to_string();
+
+            let pk = UnconnectedPong {
+                pong_time: pk.ping_time,
+                guid: self.guid.clone(),
+                magic: Magic,
+                data: data.as_str().into(),
+            };
+
+            pk.write(&mut outg);
+            let _ = socket.send_to(&outg[..], addr).await;
+        }
+
+        if id == PacketId::OpenConnectionRequest1 {
+            let Some(pk) = OpenConnectionRequest1::read(&mut r) else {
+                return;
+            };
+
+            if !self.config.protocol_versions.contains(&pk.protocol) {
+                IncompatibleProtocol {
+                    protocol: self.config.protocol_versions.first().copied().unwrap_or_default(),
+                    magic: Magic,
+                    guid: self.guid.clone()
+                }.write(&mut outg);
+
+                let _ = socket.send_to(&outg[..], addr).await;
+                return;
             }
-        });
+
+            let pk = OpenConnectionReply1 {
+                magic: Magic,
+                guid: self.guid.clone(),
+                cookie: self.config.security_cookies.then(|| self.limiter.cookie(addr).into()),
+                mtu: (pk.max_size.min(self.config.max_mtu as usize) as i16).into(),
+            };
+
+            pk.write(&mut outg);
+            let _ = socket.send_to(&outg[..], addr).await;
+        }
+
+        if id == PacketId::OpenConnectionRequest2 {
+            let pk = if self.config.security_cookies {
+                OpenConnectionRequest2::read_with_cookie(&mut r)
+            } else {
+                OpenConnectionRequest2::read(&mut r)
+            };
+
+            let Some(pk) = pk else {
+                return;
+            };
+
+            if self.config.security_cookies && pk.cookie.map(|cookie| cookie.value()) != Some(self.limiter.cookie(addr)) {
+                return;
+            }
+
+            let mtu = (pk.mtu.value() as u16).clamp(MIN_MTU, self.config.max_mtu);
+
+            // The peer may resend OpenConnectionRequest2 if our reply was lost, in which case the
+            // connection already exists and only the reply is sent again.
+            let exists = self.connections.pin().contains_key(&addr);
+            if !exists && !self.limiter.try_connect(addr.ip()) {
+                self.counters.connections_refused.fetch_add(1, Ordering::Relaxed);
+                NoFreeIncomingConnections {
+                    magic: Magic,
+                    guid: self.guid.clone()
+                }.write(&mut outg);
+
+                let _ = socket.send_to(&outg[..], addr).await;
+                return;
+            }
+
+            let pk = OpenConnectionReply2 {
+                magic: Magic,
+                guid: self.guid.clone(),
+                addr,
+                mtu: (mtu as i16).into(),
+                secure: false,
+            };
+
+            pk.write(&mut outg);
+            let _ = socket.send_to(&outg[..], addr).await;
+
+            if exists {
+                return;
+            }
+
+            // The listener may have been shut down while replying.
+            if *self.shutdown.borrow() {
+                self.limiter.disconnect(addr.ip());
+                return;
+            }
+
+            let session = Session::new(addr, mtu, &self.config, Instant::now());
+
+            let (datagrams_tx, datagrams) = mpsc::channel(conn::DATAGRAM_BACKLOG);
+            let (stats, stats_rx) = watch::channel(session.stats());
+            self.connections.pin().insert(addr, Route {
+                datagrams: datagrams_tx,
+                stats: stats_rx
+            });
+            self.counters.connections_opened.fetch_add(1, Ordering::Relaxed);
+
+            let (socket, connections, established) = (socket.clone(), self.connections.clone(), self.established.clone());
+            let (local_addr, shutdown, limiter, counters) = (self.addr, self.shutdown.subscribe(), self.limiter.clone(), self.counters.clone());
+
+            tokio::spawn(async move {
+                conn::run(socket, local_addr, addr, session, datagrams, stats.clone(), established, shutdown).await;
+
+                let mut closed = counters.closed.lock().unwrap();
+                connections.pin().remove(&addr);
+                *closed += stats.borrow().counters;
+                drop(closed);
+
+                limiter.disconnect(addr.ip());
+            });
+        }
     }
-}
\ No newline at end of file
+}
+
+/// Binds a non-blocking UDP socket with `SO_REUSEPORT` to the address.
+fn bind_socket(addr: SocketAddr) -> io::Result<UdpSocket> {
+    let udp_sock = socket2::Socket::new(
+        if addr.is_ipv4() {
+            socket2::Domain::IPV4
+        } else {
+            socket2::Domain::IPV6
+        },
+        socket2::Type::DGRAM,
+        None,
+    )?;
+
+    udp_sock.set_reuse_port(true)?;
+    udp_sock.set_cloexec(true)?;
+    udp_sock.set_nonblocking(true)?;
+    udp_sock.bind(&socket2::SockAddr::from(addr))?;
+
+    let udp_sock: std::net::UdpSocket = udp_sock.into();
+    udp_sock.try_into()
+}
@@ -1,18 +1,40 @@
-use std::time::Duration;
-use tokio::time::sleep;
-use crate::listener::RakListener;
-
-pub mod packet;
-pub mod types;
-pub mod conn;
-pub mod listener;
+use std::sync::Arc;
+use raknet::metrics::{self, Exposition};
+use raknet::RakListener;
+use tokio::net::TcpListener;
 
 #[tokio::main]
 async fn main() {
-    let listener = RakListener::new("0.0.0.0:19132");
-    listener.listen();
-    
+    let listener = Arc::new(RakListener::bind("0.0.0.0:19132").await.expect("Cannot bind the RakListener to the specified address."));
+
+    let metrics_listener = TcpListener::bind("127.0.0.1:9100").await.expect("Cannot bind the metrics endpoint to the specified address.");
+    let rak_listener = listener.clone();
+    tokio::spawn(metrics::serve(metrics_listener, move || {
+        let mut e = Exposition::new();
+        rak_listener.write_metrics(&mut e);
+        e.finish()
+    }));
+
     loop {
-        sleep(Duration::from_secs(100)).await;
+        let conn = tokio::select! {
+            conn = listener.accept() => match conn {
+                Some(conn) => conn,
+                None => break
+            },
+            _ = tokio::signal::ctrl_c() => break
+        };
+
+        println!("Connection established with {}", conn.remote_addr);
+
+        tokio::spawn(async move {
+            while let Some(msg) = conn.recv().await {
+                println!("Received message of {} bytes from {}", msg.len(), conn.remote_addr);
+            }
+
+            println!("Connection closed with {}: {:?}", conn.remote_addr, conn.disconnect_reason());
+        });
     }
-}
\ No newline at end of file
+
+    println!("Shutting down");
+    listener.shutdown().await;
+}
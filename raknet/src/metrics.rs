@@ -0,0 +1,276 @@
+use std::fmt::Write as _;
+use std::io;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::time::timeout;
+use crate::stats::{Counters, ListenerStats, Stats};
+
+/// The content type of the Prometheus text exposition format.
+const CONTENT_TYPE: &str = "text/plain; version=0.0.4; charset=utf-8";
+
+/// The path metrics are served at.
+const METRICS_PATH: &str = "/metrics";
+
+/// The maximum size of the head of a request to the metrics endpoint.
+const MAX_REQUEST_SIZE: usize = 8192;
+
+/// The time after which a client of the metrics endpoint that did not send its request is
+/// disconnected.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// MetricType is the type of a metric family in the Prometheus text format.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MetricType {
+    /// A value that only ever increases.
+    Counter,
+    /// A value that may go up and down.
+    Gauge
+}
+
+/// Exposition builds a document in the Prometheus text exposition format. Every metric family is
+/// described once with [`Exposition::describe`], after which its samples are added with
+/// [`Exposition::sample`].
+#[derive(Debug, Default)]
+pub struct Exposition {
+    buf: String
+}
+
+impl Exposition {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Describes a metric family with its type and help text.
+    pub fn describe(&mut self, name: &str, ty: MetricType, help: &str) {
+        let ty = match ty {
+            MetricType::Counter => "counter",
+            MetricType::Gauge => "gauge"
+        };
+
+        let help = help.replace('\\', "\\\\").replace('\n', "\\n");
+        let _ = writeln!(self.buf, "# HELP {name} {help}");
+        let _ = writeln!(self.buf, "# TYPE {name} {ty}");
+    }
+
+    /// Adds a sample of a metric family with the labels passed.
+    pub fn sample(&mut self, name: &str, labels: &[(&str, &str)], value: f64) {
+        self.buf.push_str(name);
+
+        if !labels.is_empty() {
+            self.buf.push('{');
+            for (i, (label, value)) in labels.iter().enumerate() {
+                if i > 0 {
+                    self.buf.push(',');
+                }
+                let value = value.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n");
+                let _ = write!(self.buf, "{label}=\"{value}\"");
+            }
+            self.buf.push('}');
+        }
+
+        let _ = writeln!(self.buf, " {value}");
+    }
+
+    /// Describes a counter without labels and adds its only sample.
+    pub fn counter(&mut self, name: &str, help: &str, value: u64) {
+        self.describe(name, MetricType::Counter, help);
+        self.sample(name, &[], value as f64);
+    }
+
+    /// Describes a gauge without labels and adds its only sample.
+    pub fn gauge(&mut self, name: &str, help: &str, value: f64) {
+        self.describe(name, MetricType::Gauge, help);
+        self.sample(name, &[], value);
+    }
+
+    /// Returns the document built.
+    pub fn finish(self) -> String {
+        self.buf
+    }
+}
+
+/// CounterMetric is the name and help text of the metric family of a counter of connections,
+/// along with the function reading the counter.
+type CounterMetric = (&'static str, &'static str, fn(&Counters) -> u64);
+
+/// The counters of a connection, along with the name and help text of their metric families.
+const COUNTERS: [CounterMetric; 11] = [
+    ("bytes_sent_total", "Bytes sent in datagrams.", |c| c.bytes_sent),
+    ("bytes_received_total", "Bytes received in datagrams.", |c| c.bytes_received),
+    ("datagrams_sent_total", "Datagrams sent.", |c| c.datagrams_sent),
+    ("datagrams_received_total", "Datagrams received.", |c| c.datagrams_received),
+    ("messages_sent_total", "Messages sent, including internal RakNet packets.", |c| c.messages_sent),
+    ("messages_received_total", "Messages received, including internal RakNet packets.", |c| c.messages_received),
+    ("resends_total", "Datagrams lost or not acknowledged in time.", |c| c.resends),
+    ("nacks_sent_total", "Datagrams negatively acknowledged to the peer.", |c| c.nacks_sent),
+    ("nacks_received_total", "Datagrams negatively acknowledged by the peer.", |c| c.nacks_received),
+    ("splits_sent_total", "Messages sent split into fragments.", |c| c.splits_sent),
+    ("splits_received_total", "Split messages received and reassembled.", |c| c.splits_received)
+];
+
+/// Writes the metrics of a listener, with the counters summed up over all its connections.
+pub fn write_listener(e: &mut Exposition, stats: &ListenerStats) {
+    e.gauge("raknet_connections", "Connections currently open.", stats.connections as f64);
+    e.counter("raknet_connections_opened_total", "Connections opened.", stats.connections_opened);
+    e.counter("raknet_connections_refused_total", "Connections refused because of the connection limits.", stats.connections_refused);
+    e.counter("raknet_offline_received_total", "Offline messages received.", stats.offline_received);
+    e.counter("raknet_offline_dropped_total", "Offline messages dropped by the rate limiter or block list.", stats.offline_dropped);
+    e.gauge("raknet_queued_frames", "Frames queued over all connections.", stats.queued_frames as f64);
+    e.gauge("raknet_bytes_in_flight", "Unacknowledged bytes over all connections.", stats.bytes_in_flight as f64);
+
+    for (name, help, value) in COUNTERS {
+        e.counter(&format!("raknet_{name}"), help, value(&stats.counters));
+    }
+}
+
+/// Writes the metrics of every connection passed, labelled with their remote address. As the
+/// label has a value for every peer, this is left to be opted into, see
+/// [`RakListener::write_connection_metrics`](crate::RakListener::write_connection_metrics).
+pub fn write_connections(e: &mut Exposition, connections: &[(SocketAddr, Stats)]) {
+    let labels: Vec<String> = connections.iter().map(|(addr, _)| addr.to_string()).collect();
+
+    let mut family = |name: &str, ty: MetricType, help: &str, value: &dyn Fn(&Stats) -> f64| {
+        let name = format!("raknet_connection_{name}");
+        e.describe(&name, ty, help);
+
+        for ((_, stats), addr) in connections.iter().zip(&labels) {
+            e.sample(&name, &[("remote_addr", addr)], value(stats));
+        }
+    };
+
+    family("rtt_seconds", MetricType::Gauge, "Smoothed round trip time of datagrams.", &|s| s.rtt.as_secs_f64());
+    family("latency_seconds", MetricType::Gauge, "Smoothed round trip time of connected pings.", &|s| s.latency.as_secs_f64());
+    family("congestion_window_bytes", MetricType::Gauge, "Bytes that may be unacknowledged at once.", &|s| s.congestion_window as f64);
+    family("bytes_in_flight", MetricType::Gauge, "Unacknowledged bytes.", &|s| s.bytes_in_flight as f64);
+    family("queued_frames", MetricType::Gauge, "Frames queued that were not yet sent.", &|s| s.queued_frames as f64);
+    family("ordered_frames", MetricType::Gauge, "Frames received out of order waiting in the order channels.", &|s| s.ordered_frames as f64);
+    family("pending_splits", MetricType::Gauge, "Split messages not yet fully received.", &|s| s.pending_splits as f64);
+
+    for (name, help, value) in COUNTERS {
+        family(name, MetricType::Counter, help, &|s| value(&s.counters) as f64);
+    }
+}
+
+/// Serves metrics over HTTP from the TCP listener passed. Every GET request for `/metrics` is
+/// answered with the document returned by the function passed, which is expected to be in the
+/// Prometheus text format. Only a single request is handled per connection. It returns once
+/// accepting connections fails.
+pub async fn serve(listener: TcpListener, render: impl Fn() -> String + Send + Sync + 'static) -> io::Result<()> {
+    let render = Arc::new(render);
+
+    loop {
+        let (stream, _) = listener.accept().await?;
+        let render = render.clone();
+
+        tokio::spawn(async move {
+            let _ = respond(stream, &*render).await;
+        });
+    }
+}
+
+/// Reads a single request from the stream and writes the response to it.
+async fn respond(mut stream: TcpStream, render: &(impl Fn() -> String + ?Sized)) -> io::Result<()> {
+    let mut buf = Vec::with_capacity(1024);
+
+    let head = timeout(REQUEST_TIMEOUT, async {
+        loop {
+            if let Some(end) = buf.windows(4).position(|w| w == b"\r\n\r\n") {
+                return Ok(end);
+            }
+            if buf.len() >= MAX_REQUEST_SIZE {
+                return Err(io::Error::from(io::ErrorKind::InvalidData));
+            }
+
+            let mut chunk = [0; 1024];
+            let len = stream.read(&mut chunk).await?;
+            if len == 0 {
+                return Err(io::Error::from(io::ErrorKind::UnexpectedEof));
+            }
+            buf.extend_from_slice(&chunk[..len]);
+        }
+    }).await.map_err(|_| io::Error::from(io::ErrorKind::TimedOut))??;
+
+    let head = String::from_utf8_lossy(&buf[..head]);
+    let mut request_line = head.lines().next().unwrap_or_default().split(' ');
+    let (method, path) = (request_line.next().unwrap_or_default(), request_line.next().unwrap_or_default());
+
+    let (status, body) = match (method, path.split('?').next().unwrap_or_default()) {
+        ("GET", METRICS_PATH) => ("200 OK", render()),
+        ("GET", _) => ("404 Not Found", String::from("Not Found\n")),
+        _ => ("405 Method Not Allowed", String::from("Method Not Allowed\n"))
+    };
+
+    let allow = if method == "GET" { "" } else { "Allow: GET\r\n" };
+    let response = format!(
+        "HTTP/1.1 {status}\r\nContent-Type: {CONTENT_TYPE}\r\nContent-Length: {}\r\n{allow}Connection: close\r\n\r\n{body}",
+        body.len()
+    );
+
+    stream.write_all(response.as_bytes()).await?;
+    stream.shutdown().await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn renders_the_text_format() {
+        let mut e = Exposition::new();
+        e.counter("requests_total", "Requests.\nAll of them.", 3);
+        e.describe("temperature", MetricType::Gauge, "Temperature by room.");
+        e.sample("temperature", &[("room", "a \"b\"\\c"), ("floor", "1")], 20.5);
+
+        assert_eq!(e.finish(), concat!(
+            "# HELP requests_total Requests.\\nAll of them.\n",
+            "# TYPE requests_total counter\n",
+            "requests_total 3\n",
+            "# HELP temperature Temperature by room.\n",
+            "# TYPE temperature gauge\n",
+            "temperature{room=\"a \\\"b\\\"\\\\c\",floor=\"1\"} 20.5\n"
+        ));
+    }
+
+    #[test]
+    fn writes_listener_metrics() {
+        let mut e = Exposition::new();
+        let stats = ListenerStats { connections: 2, counters: Counters { resends: 7, ..Default::default() }, ..Default::default() };
+        write_listener(&mut e, &stats);
+
+        let doc = e.finish();
+        assert!(doc.contains("# TYPE raknet_connections gauge\nraknet_connections 2\n"));
+        assert!(doc.contains("# TYPE raknet_resends_total counter\nraknet_resends_total 7\n"));
+    }
+
+    /// Sends the request passed to the metrics endpoint and returns the response.
+    async fn request(addr: SocketAddr, request: &str) -> String {
+        let mut stream = TcpStream::connect(addr).await.unwrap();
+        stream.write_all(request.as_bytes()).await.unwrap();
+
+        let mut response = String::new();
+        stream.read_to_string(&mut response).await.unwrap();
+        response
+    }
+
+    #[tokio::test]
+    async fn serves_metrics_over_http() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(serve(listener, || String::from("up 1\n")));
+
+        let response = request(addr, "GET /metrics?x=1 HTTP/1.1\r\nHost: a\r\n\r\n").await;
+        assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+        assert!(response.contains(&format!("Content-Type: {CONTENT_TYPE}\r\n")));
+        assert!(response.ends_with("Content-Length: 5\r\nConnection: close\r\n\r\nup 1\n"));
+
+        let response = request(addr, "GET / HTTP/1.1\r\n\r\n").await;
+        assert!(response.starts_with("HTTP/1.1 404 Not Found\r\n"));
+
+        let response = request(addr, "POST /metrics HTTP/1.1\r\n\r\n").await;
+        assert!(response.starts_with("HTTP/1.1 405 Method Not Allowed\r\n"));
+        assert!(response.contains("Allow: GET\r\n"));
+    }
+}
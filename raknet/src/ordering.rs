@@ -0,0 +1,274 @@
+use std::cmp::Ordering;
+use std::io;
+use std::collections::BinaryHeap;
+use bytes::Bytes;
+use crate::types::Frame;
+
+/// The amount of order channels supported by RakNet. Bedrock only uses channel 0, but other
+/// RakNet applications may use all of them.
+pub const MAX_ORDER_CHANNELS: usize = 32;
+
+/// Returns an error of the kind [`io::ErrorKind::InvalidInput`] if the order channel is not lower
+/// than [`MAX_ORDER_CHANNELS`].
+pub fn check_channel(channel: u8) -> io::Result<()> {
+    if channel as usize >= MAX_ORDER_CHANNELS {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, format!("order channel {channel} must be lower than {MAX_ORDER_CHANNELS}")));
+    }
+    Ok(())
+}
+
+/// The size of the range of 24-bit indices.
+const U24_RANGE: u64 = 1 << 24;
+
+/// OrderingError is returned when a frame cannot be accepted by an [`OrderingChannel`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum OrderingError {
+    /// The frame arrived too far ahead of the frame expected next and the channel has no room
+    /// left to hold it until the gap is filled.
+    QueueFull
+}
+
+/// QueuedFrame is a frame held by an [`OrderingChannel`] until all frames before it are received.
+struct QueuedFrame {
+    index: u64,
+    /// The sequence index of the frame if it is sequenced, or None if it is ordered.
+    sequence: Option<u32>,
+    body: Bytes
+}
+
+impl QueuedFrame {
+    /// Returns the key frames are delivered in: by their order index, with the sequenced frames
+    /// sent before an ordered frame ahead of it, in the order they were sent.
+    fn key(&self) -> (u64, bool, Option<u32>) {
+        (self.index, self.sequence.is_none(), self.sequence)
+    }
+}
+
+impl PartialEq for QueuedFrame {
+    fn eq(&self, other: &Self) -> bool {
+        self.key() == other.key()
+    }
+}
+
+impl Eq for QueuedFrame {}
+
+impl PartialOrd for QueuedFrame {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for QueuedFrame {
+    fn cmp(&self, other: &Self) -> Ordering {
+        // Reversed so that the BinaryHeap pops the lowest index first.
+        other.key().cmp(&self.key())
+    }
+}
+
+/// OrderingChannel holds the receiving state of a single order channel. Ordered frames that
+/// arrive before the frames preceding them are held in a bounded heap until the gap is filled.
+/// Sequenced frames sent after ordered frames that did not arrive yet are held in the same heap,
+/// and sequenced frames older than the newest one delivered are dropped.
+pub struct OrderingChannel {
+    /// The index of the next ordered frame to deliver. Indices are unwrapped to 64 bits so that
+    /// frames keep their order when the 24-bit index on the wire wraps around.
+    expected: u64,
+    /// The lowest sequence index still accepted for the current order index.
+    sequence: u64,
+    heap: BinaryHeap<QueuedFrame>,
+    max_queued: usize
+}
+
+impl OrderingChannel {
+    pub fn new(max_queued: usize) -> Self {
+        Self {
+            expected: 0,
+            sequence: 0,
+            heap: BinaryHeap::new(),
+            max_queued
+        }
+    }
+
+    /// Unwraps a 24-bit index relative to the base index. Indices within half the range ahead
+    /// of the base are considered newer, all others older.
+    fn unwrap(base: u64, index: u32) -> Option<u64> {
+        let offset = (index as u64).wrapping_sub(base) & (U24_RANGE - 1);
+        if offset >= U24_RANGE / 2 {
+            return None;
+        }
+
+        Some(base + offset)
+    }
+
+    /// Handles an ordered or sequenced frame received on this channel and pushes all messages
+    /// that are ready to be delivered, in order, to `out`.
+    pub fn handle(&mut self, frame: Frame, out: &mut impl Extend<Bytes>) -> Result<(), OrderingError> {
+        let Some(index) = Self::unwrap(self.expected, frame.order_index) else {
+            // The frame was already delivered and is a duplicate, or it is a sequenced frame sent
+            // before an ordered frame already delivered.
+            return Ok(());
+        };
+
+        let sequence = frame.reliability.is_sequenced().then_some(frame.sequence_index);
+        if index != self.expected {
+            if self.heap.len() >= self.max_queued {
+                return Err(OrderingError::QueueFull);
+            }
+
+            self.heap.push(QueuedFrame { index, sequence, body: frame.body });
+            return Ok(());
+        }
+
+        match sequence {
+            Some(sequence) => {
+                self.deliver_sequenced(sequence, frame.body, out);
+                return Ok(());
+            }
+            None => {
+                out.extend(Some(frame.body));
+                self.advance();
+            }
+        }
+
+        while self.heap.peek().is_some_and(|f| f.index <= self.expected) {
+            let queued = self.heap.pop().unwrap();
+
+            // Duplicates of a frame may have been queued more than once.
+            if queued.index != self.expected {
+                continue;
+            }
+
+            match queued.sequence {
+                Some(sequence) => self.deliver_sequenced(sequence, queued.body, out),
+                None => {
+                    out.extend(Some(queued.body));
+                    self.advance();
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Delivers a sequenced frame for the current order index, unless it is older than a
+    /// sequenced frame already delivered.
+    fn deliver_sequenced(&mut self, sequence: u32, body: Bytes, out: &mut impl Extend<Bytes>) {
+        if let Some(index) = Self::unwrap(self.sequence, sequence) {
+            self.sequence = index + 1;
+            out.extend(Some(body));
+        }
+    }
+
+    fn advance(&mut self) {
+        self.expected += 1;
+        self.sequence = 0;
+    }
+
+    /// Returns the amount of frames held until the frames preceding them are received.
+    pub fn queued(&self) -> usize {
+        self.heap.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::types::Reliability;
+    use super::*;
+
+    fn ordered(order_index: u32, body: &'static [u8]) -> Frame {
+        Frame { reliability: Reliability::ReliableOrdered, order_index, body: Bytes::from_static(body), ..Default::default() }
+    }
+
+    fn sequenced(order_index: u32, sequence_index: u32, body: &'static [u8]) -> Frame {
+        Frame { reliability: Reliability::UnreliableSequenced, order_index, sequence_index, body: Bytes::from_static(body), ..Default::default() }
+    }
+
+    #[test]
+    fn delivers_in_order() {
+        let mut channel = OrderingChannel::new(16);
+        let mut out = Vec::new();
+
+        channel.handle(ordered(2, b"c"), &mut out).unwrap();
+        channel.handle(ordered(1, b"b"), &mut out).unwrap();
+        channel.handle(ordered(1, b"b"), &mut out).unwrap();
+        assert!(out.is_empty());
+        assert_eq!(channel.queued(), 3);
+
+        channel.handle(ordered(0, b"a"), &mut out).unwrap();
+        channel.handle(ordered(0, b"a"), &mut out).unwrap();
+        assert_eq!(out, [&b"a"[..], b"b", b"c"]);
+        assert_eq!(channel.queued(), 0);
+    }
+
+    #[test]
+    fn keeps_order_across_u24_wraparound() {
+        let mut channel = OrderingChannel::new(16);
+        channel.expected = U24_RANGE - 2;
+        let mut out = Vec::new();
+
+        channel.handle(ordered(1, b"d"), &mut out).unwrap();
+        channel.handle(ordered(0, b"c"), &mut out).unwrap();
+        channel.handle(ordered(0xffffff, b"b"), &mut out).unwrap();
+        assert!(out.is_empty());
+
+        channel.handle(ordered(0xfffffe, b"a"), &mut out).unwrap();
+        assert_eq!(out, [&b"a"[..], b"b", b"c", b"d"]);
+
+        // Frames from before the wraparound are duplicates.
+        channel.handle(ordered(0xffffff, b"b"), &mut out).unwrap();
+        assert_eq!(out.len(), 4);
+        assert_eq!(channel.queued(), 0);
+    }
+
+    #[test]
+    fn limits_queued_frames() {
+        let mut channel = OrderingChannel::new(1);
+        let mut out = Vec::new();
+
+        channel.handle(ordered(1, b"b"), &mut out).unwrap();
+        assert_eq!(channel.handle(ordered(2, b"c"), &mut out), Err(OrderingError::QueueFull));
+    }
+
+    #[test]
+    fn drops_stale_sequenced_frames() {
+        let mut channel = OrderingChannel::new(16);
+        let mut out = Vec::new();
+
+        channel.handle(sequenced(0, 1, b"b"), &mut out).unwrap();
+        channel.handle(sequenced(0, 0, b"a"), &mut out).unwrap();
+        channel.handle(sequenced(0, 2, b"c"), &mut out).unwrap();
+        assert_eq!(out, [&b"b"[..], b"c"]);
+    }
+
+    #[test]
+    fn holds_sequenced_frames_ahead_of_ordered_frames() {
+        let mut channel = OrderingChannel::new(16);
+        let mut out = Vec::new();
+
+        // Sequenced frames sent after the ordered frame 0 are held until it arrives, and are
+        // delivered after it in the order they were sent.
+        channel.handle(sequenced(1, 1, b"c"), &mut out).unwrap();
+        channel.handle(sequenced(1, 0, b"b"), &mut out).unwrap();
+        channel.handle(sequenced(2, 0, b"e"), &mut out).unwrap();
+        assert!(out.is_empty());
+        assert_eq!(channel.queued(), 3);
+
+        channel.handle(ordered(0, b"a"), &mut out).unwrap();
+        assert_eq!(out, [&b"a"[..], b"b", b"c"]);
+
+        // Stale sequenced frames and those sent before an ordered frame delivered are dropped.
+        channel.handle(sequenced(1, 0, b"x"), &mut out).unwrap();
+        channel.handle(sequenced(0, 5, b"y"), &mut out).unwrap();
+        assert_eq!(out.len(), 3);
+
+        channel.handle(ordered(1, b"d"), &mut out).unwrap();
+        assert_eq!(out, [&b"a"[..], b"b", b"c", b"d", b"e"]);
+        assert_eq!(channel.queued(), 0);
+    }
+
+    #[test]
+    fn rejects_channels_out_of_range() {
+        assert!(check_channel(MAX_ORDER_CHANNELS as u8 - 1).is_ok());
+        assert_eq!(check_channel(MAX_ORDER_CHANNELS as u8).unwrap_err().kind(), io::ErrorKind::InvalidInput);
+    }
+}
@@ -0,0 +1,9 @@
+use binary::b64;
+use derive::{Decode, Encode, Packet};
+
+#[derive(Debug, Encode, Decode, Packet)]
+pub struct ConnectionRequest {
+    pub client_guid: b64,
+    pub request_time: b64,
+    pub secure: bool
+}
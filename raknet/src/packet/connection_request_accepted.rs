@@ -0,0 +1,49 @@
+use std::net::SocketAddr;
+use bytes::Buf;
+use binary::{b64, n16, Decode, Encode, Reader, Writer};
+use derive::Packet;
+use crate::packet::TIMESTAMPS_SIZE;
+
+#[derive(Debug, Packet)]
+pub struct ConnectionRequestAccepted {
+    pub client_addr: SocketAddr,
+    pub system_index: n16,
+    /// The internal addresses of the server. Vanilla RakNet sends 10 of them, Minecraft sends 20.
+    pub system_addresses: Vec<SocketAddr>,
+    pub request_time: b64,
+    pub accepted_time: b64
+}
+
+impl Encode for ConnectionRequestAccepted {
+    fn encode(&self, w: &mut Writer) {
+        self.client_addr.encode(w);
+        self.system_index.encode(w);
+
+        for addr in &self.system_addresses {
+            addr.encode(w);
+        }
+
+        self.request_time.encode(w);
+        self.accepted_time.encode(w);
+    }
+}
+
+impl Decode<'_> for ConnectionRequestAccepted {
+    fn decode(r: &mut Reader<'_>) -> Option<Self> {
+        let client_addr = SocketAddr::decode(r)?;
+        let system_index = n16::decode(r)?;
+
+        let mut system_addresses = Vec::new();
+        while r.remaining() > TIMESTAMPS_SIZE {
+            system_addresses.push(SocketAddr::decode(r)?);
+        }
+
+        Some(Self {
+            client_addr,
+            system_index,
+            system_addresses,
+            request_time: b64::decode(r)?,
+            accepted_time: b64::decode(r)?
+        })
+    }
+}
@@ -0,0 +1,4 @@
+use derive::{Decode, Encode, Packet};
+
+#[derive(Debug, Encode, Decode, Packet)]
+pub struct Disconnect;
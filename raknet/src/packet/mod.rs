@@ -7,6 +7,11 @@ pub mod open_connection_request_2;
 pub mod open_connection_reply_2;
 pub mod incompatible_protocol;
 pub mod unconnected_pong;
+pub mod connection_request;
+pub mod connection_request_accepted;
+pub mod new_incoming_connection;
+pub mod no_free_incoming_connections;
+pub mod disconnect;
 pub mod game;
 
 pub use connected_ping::*;
@@ -18,11 +23,30 @@ pub use open_connection_request_2::*;
 pub use open_connection_reply_2::*;
 pub use incompatible_protocol::*;
 pub use unconnected_pong::*;
+pub use connection_request::*;
+pub use connection_request_accepted::*;
+pub use new_incoming_connection::*;
+pub use no_free_incoming_connections::*;
+pub use disconnect::*;
 pub use game::*;
 
+use std::net::{Ipv4Addr, SocketAddr};
 use binary::{Decode, Encode, Reader, Writer};
 use derive::{Decode, Encode};
 
+/// The size of the request and accepted timestamps that terminate the list of system addresses
+/// in [`ConnectionRequestAccepted`] and [`NewIncomingConnection`].
+pub(crate) const TIMESTAMPS_SIZE: usize = 16;
+
+/// The amount of system addresses Minecraft writes in [`ConnectionRequestAccepted`] and
+/// [`NewIncomingConnection`].
+pub const SYSTEM_ADDRESS_COUNT: usize = 20;
+
+/// Returns the list of placeholder system addresses sent during the connection handshake.
+pub fn system_addresses() -> Vec<SocketAddr> {
+    vec![SocketAddr::new(Ipv4Addr::BROADCAST.into(), 19132); SYSTEM_ADDRESS_COUNT]
+}
+
 #[derive(Debug, Clone, PartialOrd, PartialEq, Encode, Decode)]
 #[encoding(type = u8)]
 pub enum PacketId {
@@ -38,6 +62,7 @@ pub enum PacketId {
     ConnectionRequest,
     ConnectionRequestAccepted = 16,
     NewIncomingConnection = 19,
+    NoFreeIncomingConnections = 20,
     Disconnect = 21,
     IncompatibleProtocol = 25,
     UnconnectedPong = 28,
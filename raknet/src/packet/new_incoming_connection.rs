@@ -0,0 +1,45 @@
+use std::net::SocketAddr;
+use bytes::Buf;
+use binary::{b64, Decode, Encode, Reader, Writer};
+use derive::Packet;
+use crate::packet::TIMESTAMPS_SIZE;
+
+#[derive(Debug, Packet)]
+pub struct NewIncomingConnection {
+    pub server_addr: SocketAddr,
+    /// The internal addresses of the client. Vanilla RakNet sends 10 of them, Minecraft sends 20.
+    pub system_addresses: Vec<SocketAddr>,
+    pub request_time: b64,
+    pub accepted_time: b64
+}
+
+impl Encode for NewIncomingConnection {
+    fn encode(&self, w: &mut Writer) {
+        self.server_addr.encode(w);
+
+        for addr in &self.system_addresses {
+            addr.encode(w);
+        }
+
+        self.request_time.encode(w);
+        self.accepted_time.encode(w);
+    }
+}
+
+impl Decode<'_> for NewIncomingConnection {
+    fn decode(r: &mut Reader<'_>) -> Option<Self> {
+        let server_addr = SocketAddr::decode(r)?;
+
+        let mut system_addresses = Vec::new();
+        while r.remaining() > TIMESTAMPS_SIZE {
+            system_addresses.push(SocketAddr::decode(r)?);
+        }
+
+        Some(Self {
+            server_addr,
+            system_addresses,
+            request_time: b64::decode(r)?,
+            accepted_time: b64::decode(r)?
+        })
+    }
+}
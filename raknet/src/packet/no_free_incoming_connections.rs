@@ -0,0 +1,9 @@
+use binary::b64;
+use derive::{Decode, Encode, Packet};
+use crate::types::Magic;
+
+#[derive(Debug, Encode, Decode, Packet)]
+pub struct NoFreeIncomingConnections {
+    pub magic: Magic,
+    pub guid: b64
+}
\ No newline at end of file
@@ -1,11 +1,40 @@
-use binary::{b16, b64};
-use derive::{Decode, Encode, Packet};
+use binary::{b16, b64, n32, Decode, Encode, Reader, Writer};
+use derive::Packet;
 use crate::types::Magic;
 
-#[derive(Debug, Encode, Decode, Packet)]
+#[derive(Debug, Packet)]
 pub struct OpenConnectionReply1 {
     pub magic: Magic,
     pub guid: b64,
-    pub secure: bool,
+    /// The security cookie the client must echo in OpenConnectionRequest2. The server is marked
+    /// as secure if it is set.
+    pub cookie: Option<n32>,
     pub mtu: b16
-}
\ No newline at end of file
+}
+
+impl Encode for OpenConnectionReply1 {
+    fn encode(&self, w: &mut Writer) {
+        self.magic.encode(w);
+        self.guid.encode(w);
+        self.cookie.is_some().encode(w);
+        if let Some(cookie) = &self.cookie {
+            cookie.encode(w);
+        }
+        self.mtu.encode(w);
+    }
+}
+
+impl Decode<'_> for OpenConnectionReply1 {
+    fn decode(r: &mut Reader<'_>) -> Option<Self> {
+        let magic = Magic::decode(r)?;
+        let guid = b64::decode(r)?;
+        let cookie = if bool::decode(r)? { Some(n32::decode(r)?) } else { None };
+
+        Some(Self {
+            magic,
+            guid,
+            cookie,
+            mtu: b16::decode(r)?
+        })
+    }
+}
@@ -1,4 +1,4 @@
-use bytes::Buf;
+use bytes::BufMut;
 use binary::{Decode, Encode, Reader, Writer};
 use derive::Packet;
 use crate::types::Magic;
@@ -14,17 +14,16 @@ impl Encode for OpenConnectionRequest1 {
     fn encode(&self, w: &mut Writer) {
         self.magic.encode(w);
         self.protocol.encode(w);
-        w.advance(self.max_size - w.len() - 28) // IP Header: 20 bytes, UDP Header: 8 bytes
+        w.put_bytes(0, self.max_size - w.len() - 28) // IP Header: 20 bytes, UDP Header: 8 bytes
     }
 }
 
 impl Decode<'_> for OpenConnectionRequest1 {
     fn decode(r: &mut Reader<'_>) -> Option<Self> {
         let max_size = r.len() + 20 + 8 + 1; // IP Header: 20 bytes, UDP Header: 8 bytes, Packet ID: 1 byte
-        r.advance(16);
         
         Some(Self{
-            magic: Magic,
+            magic: Magic::decode(r)?,
             protocol: u8::decode(r)?,
             max_size
         })
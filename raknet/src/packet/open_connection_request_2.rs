@@ -1,12 +1,67 @@
 use std::net::SocketAddr;
-use binary::{b16, b64};
-use derive::{Decode, Encode, Packet};
+use bytes::Buf;
+use binary::{b16, b64, n32, Decode, Encode, Reader, Writer};
+use derive::Packet;
 use crate::types::Magic;
 
-#[derive(Debug, Encode, Decode, Packet)]
+/// The size of the challenge a client may write after the cookie in OpenConnectionRequest2.
+const CHALLENGE_SIZE: usize = 64;
+
+#[derive(Debug, Packet)]
 pub struct OpenConnectionRequest2 {
     pub magic: Magic,
+    /// The security cookie received in OpenConnectionReply1, if the server sent one.
+    pub cookie: Option<n32>,
     pub addr: SocketAddr,
     pub mtu: b16,
     pub guid: b64
-}
\ No newline at end of file
+}
+
+impl OpenConnectionRequest2 {
+    /// Decodes an OpenConnectionRequest2 sent to a server that sent a security cookie in its
+    /// OpenConnectionReply1. The challenge that may follow the cookie is skipped.
+    pub fn read_with_cookie(r: &mut Reader<'_>) -> Option<Self> {
+        let magic = Magic::decode(r)?;
+        let cookie = n32::decode(r)?;
+
+        if bool::decode(r)? {
+            if r.remaining() < CHALLENGE_SIZE {
+                return None;
+            }
+            r.advance(CHALLENGE_SIZE);
+        }
+
+        Some(Self {
+            magic,
+            cookie: Some(cookie),
+            addr: SocketAddr::decode(r)?,
+            mtu: b16::decode(r)?,
+            guid: b64::decode(r)?
+        })
+    }
+}
+
+impl Encode for OpenConnectionRequest2 {
+    fn encode(&self, w: &mut Writer) {
+        self.magic.encode(w);
+        if let Some(cookie) = &self.cookie {
+            cookie.encode(w);
+            false.encode(w);
+        }
+        self.addr.encode(w);
+        self.mtu.encode(w);
+        self.guid.encode(w);
+    }
+}
+
+impl Decode<'_> for OpenConnectionRequest2 {
+    fn decode(r: &mut Reader<'_>) -> Option<Self> {
+        Some(Self {
+            magic: Magic::decode(r)?,
+            cookie: None,
+            addr: SocketAddr::decode(r)?,
+            mtu: b16::decode(r)?,
+            guid: b64::decode(r)?
+        })
+    }
+}
@@ -0,0 +1,834 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::io;
+use std::net::SocketAddr;
+use std::time::Duration;
+use bytes::{Bytes, BytesMut};
+use tokio::time::Instant;
+use binary::{Decode, Encode};
+use crate::config::Config;
+use crate::congestion::SlidingWindow;
+use crate::packet::{system_addresses, ConnectedPing, ConnectedPong, ConnectionRequest, ConnectionRequestAccepted, Disconnect, NewIncomingConnection, Packet, PacketId};
+use crate::ordering::{check_channel, OrderingChannel, MAX_ORDER_CHANNELS};
+use crate::split::SplitAssembler;
+use crate::stats::{Counters, Stats};
+use crate::types::{Acknowledgement, Datagram, Frame, Reliability, Split, DATAGRAM_HEADER_SIZE, FLAG_ACK, FLAG_CONTINUOUS_SEND, FLAG_NACK, FLAG_NEEDS_B_AND_AS, FLAG_VALID, UDP_HEADER_SIZE};
+
+/// Sequence numbers and indices in RakNet are 24-bit integers that wrap around.
+const U24_MASK: u32 = 0xffffff;
+
+/// The maximum amount of missing datagrams that are negatively acknowledged at once when a
+/// gap in the sequence numbers is detected.
+const MAX_NACK_GAP: u32 = 512;
+
+/// ReliableWindow keeps track of the reliable message indices received, so that frames that
+/// are received more than once because their datagram was resent are only handled once.
+struct ReliableWindow {
+    /// The lowest reliable index not yet received.
+    base: u32,
+    /// The reliable indices received above the base.
+    received: HashSet<u32>,
+    max_size: u32
+}
+
+impl ReliableWindow {
+    fn new(max_size: u32) -> Self {
+        Self {
+            base: 0,
+            received: HashSet::new(),
+            max_size
+        }
+    }
+
+    /// Marks the reliable index as received. Returns false if it was already received before,
+    /// or if it lies too far ahead of the lowest index not yet received.
+    fn receive(&mut self, index: u32) -> bool {
+        let offset = index.wrapping_sub(self.base) & U24_MASK;
+        if offset >= self.max_size || !self.received.insert(index) {
+            return false;
+        }
+
+        while self.received.remove(&self.base) {
+            self.base = (self.base + 1) & U24_MASK;
+        }
+
+        true
+    }
+}
+
+/// Recovery is a datagram sent to the peer that was not yet acknowledged.
+struct Recovery {
+    /// The reliable frames in the datagram, which are resent if the datagram is lost.
+    frames: Vec<Frame>,
+    size: usize,
+    sent: Instant
+}
+
+/// State is the state of the connected-mode handshake of a [`Session`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum State {
+    /// The session was created after the offline handshake and is waiting for the client to
+    /// send a ConnectionRequest.
+    Connecting,
+    /// ConnectionRequest was sent to the server and the session is waiting for it to reply with
+    /// ConnectionRequestAccepted.
+    Requested,
+    /// ConnectionRequestAccepted was sent and the session is waiting for the client to send
+    /// NewIncomingConnection.
+    Accepted,
+    /// The handshake completed. Messages received are now delivered to the application.
+    Connected,
+    /// The session is being closed: the reliable frames still pending are flushed before the
+    /// Disconnect is sent.
+    Closing,
+    /// The session was closed. The reason is returned by [`Session::disconnect_reason`].
+    Closed
+}
+
+/// DisconnectReason is the reason a session was closed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DisconnectReason {
+    /// The session was closed locally.
+    Closed,
+    /// The peer sent a Disconnect packet.
+    Disconnected,
+    /// The connection handshake did not complete in time.
+    HandshakeTimedOut,
+    /// Nothing was received from the peer within the idle timeout.
+    TimedOut,
+    /// The listener the connection belonged to was shut down.
+    Shutdown
+}
+
+/// Session holds the state of the reliability layer of a single connection and drives the
+/// connected-mode handshake. It does not perform any IO itself: datagrams received are passed
+/// to [`Session::handle`], messages to send are passed to [`Session::send`], and the datagrams
+/// produced are taken from [`Session::poll_transmit`].
+pub struct Session {
+    remote_addr: SocketAddr,
+    mtu: usize,
+    config: Config,
+
+    state: State,
+    disconnect_reason: Option<DisconnectReason>,
+    /// The reason the session is being closed for and the time by which the Disconnect must be
+    /// sent, while in [`State::Closing`].
+    closing: Option<(DisconnectReason, Instant)>,
+    created: Instant,
+    /// The time the last datagram was received from the peer.
+    last_received: Instant,
+    /// The time the last ConnectedPing was sent to the peer.
+    last_ping: Option<Instant>,
+    /// The smoothed round trip time of ConnectedPing packets answered by the peer.
+    latency: Option<Duration>,
+    counters: Counters,
+
+    datagram_sequence: u32,
+    reliable_index: u32,
+    order_indices: [u32; MAX_ORDER_CHANNELS],
+    sequence_indices: [u32; MAX_ORDER_CHANNELS],
+    split_id: u16,
+
+    /// Frames waiting to be packed into datagrams.
+    queue: VecDeque<Frame>,
+    /// Datagrams sent that were not yet acknowledged, indexed by their sequence number.
+    recovery: HashMap<u32, Recovery>,
+    congestion: SlidingWindow,
+
+    expected_sequence: u32,
+    acks: Vec<u32>,
+    nacks: Vec<u32>,
+    reliable_window: ReliableWindow,
+    splits: SplitAssembler,
+    channels: Vec<OrderingChannel>,
+
+    /// Messages that passed the reliability layer, including internal RakNet packets.
+    received: VecDeque<Bytes>,
+    /// Messages to be delivered to the application.
+    messages: VecDeque<Bytes>,
+    transmit: VecDeque<Bytes>
+}
+
+impl Session {
+    pub fn new(remote_addr: SocketAddr, mtu: u16, config: &Config, now: Instant) -> Self {
+        Self {
+            remote_addr,
+            mtu: mtu as usize,
+            config: config.clone(),
+
+            state: State::Connecting,
+            disconnect_reason: None,
+            closing: None,
+            created: now,
+            last_received: now,
+            last_ping: None,
+            latency: None,
+            counters: Counters::default(),
+
+            datagram_sequence: 0,
+            reliable_index: 0,
+            order_indices: [0; MAX_ORDER_CHANNELS],
+            sequence_indices: [0; MAX_ORDER_CHANNELS],
+            split_id: 0,
+
+            queue: VecDeque::new(),
+            recovery: HashMap::new(),
+            congestion: SlidingWindow::new(mtu as usize),
+
+            expected_sequence: 0,
+            acks: Vec::new(),
+            nacks: Vec::new(),
+            reliable_window: ReliableWindow::new(config.max_reliable_window),
+            splits: SplitAssembler::new(config),
+            channels: (0..MAX_ORDER_CHANNELS).map(|_| OrderingChannel::new(config.max_ordered_queue)).collect(),
+
+            received: VecDeque::new(),
+            messages: VecDeque::new(),
+            transmit: VecDeque::new()
+        }
+    }
+
+    /// Returns the MTU negotiated with the peer.
+    pub fn mtu(&self) -> u16 {
+        self.mtu as u16
+    }
+
+    /// Returns the config the session was created with.
+    pub fn config(&self) -> &Config {
+        &self.config
+    }
+
+    /// Returns the current state of the session.
+    pub fn state(&self) -> State {
+        self.state
+    }
+
+    /// Returns the reason the session was closed, or None if it is not closed.
+    pub fn disconnect_reason(&self) -> Option<DisconnectReason> {
+        self.disconnect_reason
+    }
+
+    /// Returns the smoothed latency measured with ConnectedPing packets, or None if the peer did
+    /// not answer any ping yet.
+    pub fn latency(&self) -> Option<Duration> {
+        self.latency
+    }
+
+    /// Marks the session as closed for the reason passed.
+    fn set_closed(&mut self, reason: DisconnectReason) {
+        self.state = State::Closed;
+        self.disconnect_reason.get_or_insert(reason);
+    }
+
+    /// Returns the time elapsed since the session was created in milliseconds. It is used for
+    /// the timestamps sent in the handshake.
+    fn timestamp(&self, now: Instant) -> i64 {
+        now.duration_since(self.created).as_millis() as i64
+    }
+
+    /// Returns the maximum amount of frame bytes that fit in a single datagram.
+    fn max_payload_size(&self) -> usize {
+        self.mtu - UDP_HEADER_SIZE - DATAGRAM_HEADER_SIZE
+    }
+
+    /// Queues a message to be sent with the specified reliability on the specified order channel.
+    /// The channel is only relevant for ordered and sequenced reliabilities. Messages that do not
+    /// fit in a single datagram are split into fragments, which are always sent reliably.
+    ///
+    /// Returns an error of the kind [`io::ErrorKind::InvalidInput`] if the channel is not lower
+    /// than [`MAX_ORDER_CHANNELS`].
+    pub fn send(&mut self, body: Bytes, reliability: Reliability, channel: u8) -> io::Result<()> {
+        check_channel(channel)?;
+        self.queue_message(body, reliability, channel);
+        Ok(())
+    }
+
+    /// Queues a message on an order channel that is known to be valid.
+    fn queue_message(&mut self, body: Bytes, reliability: Reliability, channel: u8) {
+        let max_size = self.max_payload_size();
+        let ch = channel as usize;
+        self.counters.messages_sent += 1;
+
+        let mut frame = Frame {
+            reliability,
+            ..Default::default()
+        };
+
+        if reliability.is_ordered() {
+            frame.order_channel = channel;
+            frame.order_index = self.order_indices[ch];
+        }
+
+        if reliability.is_sequenced() {
+            frame.sequence_index = self.sequence_indices[ch];
+            self.sequence_indices[ch] = (self.sequence_indices[ch] + 1) & U24_MASK;
+        } else if reliability.is_ordered() {
+            self.order_indices[ch] = (self.order_indices[ch] + 1) & U24_MASK;
+            self.sequence_indices[ch] = 0;
+        }
+
+        if body.len() + Frame::header_size(reliability, false) <= max_size {
+            frame.body = body;
+            self.push_frame(frame);
+            return;
+        }
+
+        frame.reliability = reliability.reliable();
+
+        let fragment_size = max_size - Frame::header_size(frame.reliability, true);
+        let count = body.len().div_ceil(fragment_size) as u32;
+
+        let id = self.split_id;
+        self.split_id = self.split_id.wrapping_add(1);
+        self.counters.splits_sent += 1;
+
+        for (index, chunk) in body.chunks(fragment_size).enumerate() {
+            let mut fragment = frame.clone();
+            fragment.split = Some(Split { count, id, index: index as u32 });
+            fragment.body = body.slice_ref(chunk);
+
+            self.push_frame(fragment);
+        }
+    }
+
+    fn push_frame(&mut self, mut frame: Frame) {
+        if frame.reliability.is_reliable() {
+            frame.reliable_index = self.reliable_index;
+            self.reliable_index = (self.reliable_index + 1) & U24_MASK;
+        }
+
+        self.queue.push_back(frame);
+    }
+
+    /// Handles a datagram received from the peer.
+    pub fn handle(&mut self, mut buf: &[u8], now: Instant) {
+        let Some(&flags) = buf.first() else {
+            return;
+        };
+
+        if flags & FLAG_VALID == 0 {
+            return;
+        }
+
+        self.last_received = now;
+        self.counters.bytes_received += buf.len() as u64;
+        self.counters.datagrams_received += 1;
+
+        if flags & FLAG_ACK != 0 {
+            buf = &buf[1..];
+            if let Some(ack) = Acknowledgement::decode(&mut buf) {
+                for seq in ack.sequences {
+                    if let Some(recovery) = self.recovery.remove(&seq) {
+                        self.congestion.on_ack(recovery.size, now.duration_since(recovery.sent));
+                    }
+                }
+            }
+            return;
+        }
+
+        if flags & FLAG_NACK != 0 {
+            buf = &buf[1..];
+            if let Some(nack) = Acknowledgement::decode(&mut buf) {
+                let mut lost = Vec::new();
+                self.counters.nacks_received += nack.sequences.len() as u64;
+                for seq in nack.sequences {
+                    if let Some(recovery) = self.recovery.remove(&seq) {
+                        self.congestion.on_nack(seq, recovery.size);
+                        lost.push(recovery);
+                    }
+                }
+                self.requeue(lost);
+            }
+            return;
+        }
+
+        let Some(datagram) = Datagram::decode(&mut buf) else {
+            return;
+        };
+
+        self.handle_sequence(datagram.sequence);
+
+        for frame in datagram.frames {
+            self.handle_frame(frame, now);
+        }
+
+        while let Some(msg) = self.received.pop_front() {
+            self.handle_message(msg, now);
+        }
+    }
+
+    /// Records the sequence number of a datagram received to be acknowledged, and negatively
+    /// acknowledges any datagrams that were skipped.
+    fn handle_sequence(&mut self, seq: u32) {
+        self.acks.push(seq);
+        self.nacks.retain(|&n| n != seq);
+
+        let gap = seq.wrapping_sub(self.expected_sequence) & U24_MASK;
+        if gap > U24_MASK / 2 {
+            // The datagram is older than the one expected: it was either resent or reordered.
+            return;
+        }
+
+        for missing in 0..gap.min(MAX_NACK_GAP) {
+            self.nacks.push((self.expected_sequence + missing) & U24_MASK);
+        }
+
+        self.expected_sequence = (seq + 1) & U24_MASK;
+    }
+
+    fn handle_frame(&mut self, frame: Frame, now: Instant) {
+        if frame.reliability.is_reliable() && !self.reliable_window.receive(frame.reliable_index) {
+            return;
+        }
+
+        let frame = if frame.split.is_some() {
+            match self.splits.insert(frame, now) {
+                Ok(Some(frame)) => {
+                    self.counters.splits_received += 1;
+                    frame
+                }
+                Ok(None) | Err(_) => return
+            }
+        } else {
+            frame
+        };
+
+        if !frame.reliability.is_ordered() {
+            self.received.push_back(frame.body);
+            return;
+        }
+
+        let Some(channel) = self.channels.get_mut(frame.order_channel as usize) else {
+            return;
+        };
+
+        // A peer that keeps a channel from progressing until the queue fills up is misbehaving.
+        // The frame is dropped, which stalls the channel for good.
+        let _ = channel.handle(frame, &mut self.received);
+    }
+
+    /// Handles a message that passed the reliability layer. Internal RakNet packets drive the
+    /// handshake, all other messages are delivered to the application once connected.
+    fn handle_message(&mut self, msg: Bytes, now: Instant) {
+        self.counters.messages_received += 1;
+
+        let mut r = &msg[..];
+        let Some(id) = PacketId::decode(&mut r) else {
+            // Only the IDs of internal RakNet packets are intercepted. Other messages belong to
+            // the application and are passed on once the connection is established.
+            if self.state == State::Connected && !msg.is_empty() {
+                self.messages.push_back(msg);
+            }
+            return;
+        };
+
+        match (id, self.state) {
+            (PacketId::ConnectionRequest, State::Connecting) => {
+                let Some(pk) = ConnectionRequest::read(&mut r) else {
+                    return;
+                };
+
+                self.state = State::Accepted;
+                self.send_packet(&ConnectionRequestAccepted {
+                    client_addr: self.remote_addr,
+                    system_index: 0.into(),
+                    system_addresses: system_addresses(),
+                    request_time: pk.request_time,
+                    accepted_time: self.timestamp(now).into()
+                });
+            }
+            (PacketId::ConnectionRequestAccepted, State::Requested) => {
+                let Some(pk) = ConnectionRequestAccepted::read(&mut r) else {
+                    return;
+                };
+
+                self.state = State::Connected;
+                self.send_packet(&NewIncomingConnection {
+                    server_addr: self.remote_addr,
+                    system_addresses: system_addresses(),
+                    request_time: pk.accepted_time,
+                    accepted_time: self.timestamp(now).into()
+                });
+            }
+            (PacketId::NewIncomingConnection, State::Accepted) => {
+                if NewIncomingConnection::read(&mut r).is_none() {
+                    return;
+                }
+
+                self.state = State::Connected;
+            }
+            (PacketId::ConnectedPing, _) => {
+                let Some(pk) = ConnectedPing::read(&mut r) else {
+                    return;
+                };
+
+                self.send_packet_immediate(&ConnectedPong {
+                    ping_time: pk.ping_time,
+                    pong_time: self.timestamp(now).into()
+                });
+            }
+            (PacketId::ConnectedPong, _) => {
+                let Some(pk) = ConnectedPong::read(&mut r) else {
+                    return;
+                };
+
+                let sent = pk.ping_time.value();
+                let elapsed = self.timestamp(now) - sent;
+                if sent < 0 || elapsed < 0 {
+                    return;
+                }
+
+                let sample = Duration::from_millis(elapsed as u64);
+                self.latency = Some(match self.latency {
+                    Some(latency) => (latency * 7 + sample) / 8,
+                    None => sample
+                });
+            }
+            (PacketId::Disconnect, _) => {
+                self.set_closed(DisconnectReason::Disconnected);
+            }
+            (PacketId::ConnectionRequest | PacketId::ConnectionRequestAccepted | PacketId::NewIncomingConnection, _) => {}
+            (_, State::Connected) => {
+                self.messages.push_back(msg);
+            }
+            _ => {}
+        }
+    }
+
+    /// Starts the connection handshake as a client by sending a ConnectionRequest to the server.
+    pub fn connect(&mut self, guid: i64, now: Instant) {
+        self.state = State::Requested;
+        self.send_packet(&ConnectionRequest {
+            client_guid: guid.into(),
+            request_time: self.timestamp(now).into(),
+            secure: false
+        });
+    }
+
+    /// Sends an internal RakNet packet reliably on the first order channel.
+    fn send_packet<'a>(&mut self, pk: &impl Packet<'a>) {
+        let mut w = BytesMut::new();
+        pk.write(&mut w);
+
+        self.queue_message(w.freeze(), Reliability::ReliableOrdered, 0);
+    }
+
+    /// Sends an internal RakNet packet unreliably ahead of all queued frames. It is used for
+    /// ConnectedPing and ConnectedPong, so that the latency measured does not include the time
+    /// spent waiting in the queue.
+    fn send_packet_immediate<'a>(&mut self, pk: &impl Packet<'a>) {
+        let mut w = BytesMut::new();
+        pk.write(&mut w);
+
+        self.queue.push_front(Frame {
+            reliability: Reliability::Unreliable,
+            body: w.freeze(),
+            ..Default::default()
+        });
+    }
+
+    /// Starts closing the session gracefully. The session keeps being updated until all reliable
+    /// frames queued or in flight were acknowledged, or until the close timeout passes, after
+    /// which the peer is notified with a Disconnect packet.
+    pub fn close(&mut self, reason: DisconnectReason, now: Instant) {
+        if matches!(self.state, State::Closing | State::Closed) {
+            return;
+        }
+
+        self.state = State::Closing;
+        self.closing = Some((reason, now + self.config.close_timeout));
+
+        if self.queue.is_empty() && self.recovery.is_empty() {
+            self.disconnect(reason, now);
+        }
+    }
+
+    /// Closes the session right away, notifying the peer with a Disconnect packet. Frames not yet
+    /// sent are discarded. The Disconnect is sent regardless of the congestion window, as the
+    /// session is not updated anymore once closed.
+    pub fn disconnect(&mut self, reason: DisconnectReason, now: Instant) {
+        if self.state == State::Closed {
+            return;
+        }
+
+        self.queue.clear();
+        self.send_packet(&Disconnect);
+        self.set_closed(reason);
+
+        if let Some(frame) = self.queue.pop_back() {
+            self.transmit_datagram(vec![frame], 0, now);
+        }
+    }
+
+    /// Updates the session: the peer is pinged if due, acknowledgements are flushed, expired split
+    /// messages are discarded and queued frames are packed into datagrams. The session is closed
+    /// if the handshake did not complete in time or if the peer has been silent for longer than
+    /// the idle timeout.
+    pub fn update(&mut self, now: Instant) {
+        if self.state == State::Closed {
+            return;
+        }
+
+        if !matches!(self.state, State::Connected | State::Closing) && now.duration_since(self.created) > self.config.handshake_timeout {
+            self.set_closed(DisconnectReason::HandshakeTimedOut);
+            return;
+        }
+
+        if now.duration_since(self.last_received) > self.config.idle_timeout {
+            self.set_closed(DisconnectReason::TimedOut);
+            return;
+        }
+
+        if self.state == State::Connected && self.last_ping.is_none_or(|last| now.duration_since(last) >= self.config.ping_interval) {
+            self.last_ping = Some(now);
+            self.send_packet_immediate(&ConnectedPing {
+                ping_time: self.timestamp(now).into()
+            });
+        }
+
+        if !self.acks.is_empty() {
+            let ack = Acknowledgement { sequences: std::mem::take(&mut self.acks) };
+            self.transmit_acknowledgement(FLAG_ACK, ack);
+        }
+        if !self.nacks.is_empty() {
+            let nack = Acknowledgement { sequences: std::mem::take(&mut self.nacks) };
+            self.counters.nacks_sent += nack.sequences.len() as u64;
+            self.transmit_acknowledgement(FLAG_NACK, nack);
+        }
+
+        self.splits.expire(now);
+        self.resend_expired(now);
+        self.flush(now);
+
+        if let Some((reason, deadline)) = self.closing {
+            if (self.queue.is_empty() && self.recovery.is_empty()) || now >= deadline {
+                self.disconnect(reason, now);
+            }
+        }
+    }
+
+    /// Resends the reliable frames of all datagrams that were not acknowledged within the
+    /// retransmission timeout.
+    fn resend_expired(&mut self, now: Instant) {
+        let rto = self.congestion.rto();
+
+        let expired: Vec<u32> = self.recovery
+            .iter()
+            .filter(|(_, recovery)| now.duration_since(recovery.sent) >= rto)
+            .map(|(&seq, _)| seq)
+            .collect();
+
+        let mut lost = Vec::with_capacity(expired.len());
+        for seq in expired {
+            let recovery = self.recovery.remove(&seq).unwrap();
+            self.congestion.on_resend(seq, recovery.size);
+            lost.push(recovery);
+        }
+
+        self.requeue(lost);
+    }
+
+    /// Puts the reliable frames of lost datagrams back at the front of the queue, keeping the
+    /// order in which they were originally sent.
+    fn requeue(&mut self, mut lost: Vec<Recovery>) {
+        lost.sort_by_key(|recovery| recovery.sent);
+        self.counters.resends += lost.len() as u64;
+
+        for recovery in lost.into_iter().rev() {
+            for frame in recovery.frames.into_iter().rev() {
+                self.queue.push_front(frame);
+            }
+        }
+    }
+
+    fn transmit_acknowledgement(&mut self, flag: u8, ack: Acknowledgement) {
+        let mut w = BytesMut::new();
+
+        (FLAG_VALID | flag).encode(&mut w);
+        ack.encode(&mut w);
+
+        self.transmit.push_back(w.freeze());
+    }
+
+    /// Packs queued frames into as few datagrams as possible, as long as the congestion window
+    /// allows more bytes to be sent. Frames that do not fit in the window stay queued until the
+    /// next update.
+    fn flush(&mut self, now: Instant) {
+        let max_size = self.max_payload_size();
+        let mut sent = 0;
+
+        while !self.queue.is_empty() {
+            // A datagram may always be sent if none are in flight, so that a window smaller
+            // than a single datagram never stalls the session.
+            if self.congestion.transmission_bandwidth() == 0 && self.congestion.bytes_in_flight() != 0 {
+                break;
+            }
+
+            let mut frames = Vec::new();
+            let mut size = 0;
+
+            while let Some(frame) = self.queue.front() {
+                if size + frame.size() > max_size && !frames.is_empty() {
+                    break;
+                }
+
+                size += frame.size();
+                frames.push(self.queue.pop_front().unwrap());
+            }
+
+            self.transmit_datagram(frames, sent, now);
+            sent += 1;
+        }
+    }
+
+    /// Encodes the frames in a datagram to be sent to the peer and tracks it until acknowledged.
+    /// `index` is the index of the datagram within the current burst of datagrams.
+    fn transmit_datagram(&mut self, frames: Vec<Frame>, index: usize, now: Instant) {
+        let mut datagram = Datagram {
+            flags: FLAG_NEEDS_B_AND_AS,
+            sequence: self.datagram_sequence,
+            frames
+        };
+
+        if index > 0 {
+            datagram.flags |= FLAG_CONTINUOUS_SEND;
+        }
+
+        self.datagram_sequence = (self.datagram_sequence + 1) & U24_MASK;
+
+        let mut w = BytesMut::with_capacity(self.mtu);
+        datagram.encode(&mut w);
+
+        let size = w.len();
+        self.transmit.push_back(w.freeze());
+        self.congestion.on_send(datagram.sequence, size);
+
+        self.recovery.insert(datagram.sequence, Recovery {
+            frames: datagram.frames.into_iter().filter(|f| f.reliability.is_reliable()).collect(),
+            size,
+            sent: now
+        });
+    }
+
+    /// Returns a snapshot of the statistics of the session.
+    pub fn stats(&self) -> Stats {
+        Stats {
+            rtt: self.congestion.rtt(),
+            rto: self.congestion.rto(),
+            congestion_window: self.congestion.congestion_window(),
+            bytes_in_flight: self.congestion.bytes_in_flight(),
+            bandwidth: self.congestion.bandwidth(),
+            queued_frames: self.queue.len(),
+            unacknowledged_datagrams: self.recovery.len(),
+            ordered_frames: self.channels.iter().map(OrderingChannel::queued).sum(),
+            pending_splits: self.splits.len(),
+            latency: self.latency.unwrap_or_default(),
+            counters: self.counters
+        }
+    }
+
+    /// Returns the amount of messages received from the peer that were not yet taken with
+    /// [`Session::recv`].
+    pub fn pending_messages(&self) -> usize {
+        self.messages.len()
+    }
+
+    /// Returns the next message received from the peer, if any.
+    pub fn recv(&mut self) -> Option<Bytes> {
+        self.messages.pop_front()
+    }
+
+    /// Returns the next datagram that should be sent to the peer, if any.
+    pub fn poll_transmit(&mut self) -> Option<Bytes> {
+        let datagram = self.transmit.pop_front()?;
+        self.counters.bytes_sent += datagram.len() as u64;
+        self.counters.datagrams_sent += 1;
+
+        Some(datagram)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MTU: u16 = 1400;
+
+    /// Delivers the datagrams the first session has to send to the second at the time passed.
+    fn deliver(from: &mut Session, to: &mut Session, now: Instant) {
+        while let Some(datagram) = from.poll_transmit() {
+            to.handle(&datagram, now);
+        }
+    }
+
+    /// Returns a client and a server session that completed the connection handshake at the
+    /// time passed.
+    fn connected(now: Instant) -> (Session, Session) {
+        let config = Config::default();
+        let mut client = Session::new("10.0.0.1:19132".parse().unwrap(), MTU, &config, now);
+        let mut server = Session::new("10.0.0.2:50000".parse().unwrap(), MTU, &config, now);
+
+        client.connect(1, now);
+        for _ in 0..4 {
+            client.update(now);
+            deliver(&mut client, &mut server, now);
+            server.update(now);
+            deliver(&mut server, &mut client, now);
+        }
+
+        assert_eq!(client.state(), State::Connected);
+        assert_eq!(server.state(), State::Connected);
+        (client, server)
+    }
+
+    #[test]
+    fn handshake_times_out() {
+        let now = Instant::now();
+        let config = Config::default();
+        let mut server = Session::new("10.0.0.2:50000".parse().unwrap(), MTU, &config, now);
+
+        server.update(now + config.handshake_timeout);
+        assert_eq!(server.disconnect_reason(), None);
+
+        server.update(now + config.handshake_timeout + Duration::from_millis(1));
+        assert_eq!(server.state(), State::Closed);
+        assert_eq!(server.disconnect_reason(), Some(DisconnectReason::HandshakeTimedOut));
+    }
+
+    #[test]
+    fn idle_sessions_time_out() {
+        let now = Instant::now();
+        let (mut client, mut server) = connected(now);
+        let idle_timeout = client.config().idle_timeout;
+
+        // Receiving a datagram from the peer resets the idle timeout.
+        let later = now + idle_timeout / 2;
+        server.update(later);
+        deliver(&mut server, &mut client, later);
+        client.update(now + idle_timeout + Duration::from_millis(1));
+        assert_eq!(client.disconnect_reason(), None);
+
+        client.update(later + idle_timeout + Duration::from_millis(1));
+        assert_eq!(client.state(), State::Closed);
+        assert_eq!(client.disconnect_reason(), Some(DisconnectReason::TimedOut));
+    }
+
+    #[test]
+    fn latency_is_smoothed() {
+        let now = Instant::now();
+        let (mut client, mut server) = connected(now);
+        let ping_interval = client.config().ping_interval;
+        assert_eq!(client.latency(), Some(Duration::ZERO));
+
+        // Every ping takes 100ms to reach the server and its pong 100ms to come back.
+        // Each sample of 200ms moves the latency an eighth of the way towards it.
+        for (i, expected) in [(1, 25_000), (2, 46_875)] {
+            let sent = now + ping_interval * i;
+            client.update(sent);
+            deliver(&mut client, &mut server, sent + Duration::from_millis(100));
+            server.update(sent + Duration::from_millis(100));
+            deliver(&mut server, &mut client, sent + Duration::from_millis(200));
+
+            assert_eq!(client.latency(), Some(Duration::from_micros(expected)));
+        }
+    }
+}
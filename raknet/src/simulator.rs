@@ -0,0 +1,214 @@
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap};
+use std::io;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use bytes::Bytes;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use tokio::sync::Notify;
+use tokio::time::{sleep_until, Instant};
+use crate::transport::Transport;
+use crate::types::UDP_HEADER_SIZE;
+
+/// Conditions are the network conditions applied to every datagram sent over a simulated
+/// [`Network`]. Probabilities range from 0 to 1. Probabilities outside that range are clamped to it
+/// when the conditions are applied to a network, and NaN is treated as 0.
+#[derive(Debug, Clone, Default)]
+pub struct Conditions {
+    /// The probability of a datagram being lost.
+    pub loss: f64,
+    /// The probability of a datagram being delivered twice.
+    pub duplication: f64,
+    /// The probability of a datagram being held back, so that datagrams sent after it overtake it.
+    pub reordering: f64,
+    /// The delay after which datagrams are delivered.
+    pub latency: Duration,
+    /// The maximum random delay added to the latency of every datagram.
+    pub jitter: Duration,
+    /// The delay added to datagrams that are held back for reordering.
+    pub reordering_delay: Duration,
+    /// The MTU of the network. Datagrams that exceed it along with their IP and UDP headers are
+    /// dropped. An MTU of 0 does not limit the size of datagrams.
+    pub mtu: usize
+}
+
+impl Conditions {
+    /// Returns the conditions with their probabilities clamped to the range from 0 to 1, so that
+    /// they can be passed to [`Rng::gen_bool`], which panics for values outside of it.
+    fn clamped(mut self) -> Self {
+        for p in [&mut self.loss, &mut self.duplication, &mut self.reordering] {
+            *p = if p.is_nan() { 0.0 } else { p.clamp(0.0, 1.0) };
+        }
+        self
+    }
+}
+
+/// Delivery is a datagram in flight on a simulated network, ordered by the time it is delivered
+/// at and then by the order in which it was sent.
+#[derive(PartialEq, Eq, PartialOrd, Ord)]
+struct Delivery {
+    at: Instant,
+    seq: u64,
+    from: SocketAddr,
+    data: Bytes
+}
+
+/// Inbox holds the datagrams in flight to a [`SimSocket`].
+#[derive(Default)]
+struct Inbox {
+    queue: Mutex<BinaryHeap<Reverse<Delivery>>>,
+    notify: Notify
+}
+
+/// State is the state of a [`Network`], guarded by a single lock.
+struct State {
+    conditions: Conditions,
+    rng: StdRng,
+    inboxes: HashMap<SocketAddr, Arc<Inbox>>,
+    seq: u64
+}
+
+/// Network is an in-memory network that datagrams are sent over between [`SimSocket`]s. The
+/// network conditions are simulated with a seeded random number generator, so that a test run
+/// with the same seed drops, duplicates and delays the same datagrams. Combined with a runtime
+/// whose time is paused, connections over the network behave deterministically.
+#[derive(Clone)]
+pub struct Network {
+    state: Arc<Mutex<State>>
+}
+
+impl Network {
+    /// Creates a network applying the conditions passed, simulated with a random number generator
+    /// seeded with the seed passed.
+    pub fn new(conditions: Conditions, seed: u64) -> Self {
+        Self {
+            state: Arc::new(Mutex::new(State {
+                conditions: conditions.clamped(),
+                rng: StdRng::seed_from_u64(seed),
+                inboxes: HashMap::new(),
+                seq: 0
+            }))
+        }
+    }
+
+    /// Replaces the conditions of the network. It applies to datagrams sent from now on.
+    pub fn set_conditions(&self, conditions: Conditions) {
+        self.state.lock().unwrap().conditions = conditions.clamped();
+    }
+
+    /// Binds a socket to the address passed. Fails if the address is already in use.
+    pub fn bind(&self, addr: SocketAddr) -> io::Result<SimSocket> {
+        let mut state = self.state.lock().unwrap();
+        if state.inboxes.contains_key(&addr) {
+            return Err(io::Error::from(io::ErrorKind::AddrInUse));
+        }
+
+        let inbox = Arc::new(Inbox::default());
+        state.inboxes.insert(addr, inbox.clone());
+
+        Ok(SimSocket {
+            addr,
+            network: self.clone(),
+            inbox
+        })
+    }
+
+    /// Sends a datagram from one address to another, applying the conditions of the network.
+    /// Datagrams sent to addresses no socket is bound to are lost.
+    fn send(&self, data: &[u8], from: SocketAddr, to: SocketAddr) {
+        let mut state = self.state.lock().unwrap();
+        let state = &mut *state;
+
+        let Some(inbox) = state.inboxes.get(&to).cloned() else {
+            return;
+        };
+
+        let conditions = &state.conditions;
+        if conditions.mtu != 0 && data.len() + UDP_HEADER_SIZE > conditions.mtu {
+            return;
+        }
+        if state.rng.gen_bool(conditions.loss) {
+            return;
+        }
+
+        let copies = if state.rng.gen_bool(conditions.duplication) { 2 } else { 1 };
+        let data = Bytes::copy_from_slice(data);
+        let now = Instant::now();
+
+        for _ in 0..copies {
+            let mut delay = conditions.latency;
+            if !conditions.jitter.is_zero() {
+                delay += state.rng.gen_range(Duration::ZERO..conditions.jitter);
+            }
+            if state.rng.gen_bool(conditions.reordering) {
+                delay += conditions.reordering_delay;
+            }
+
+            state.seq += 1;
+            inbox.queue.lock().unwrap().push(Reverse(Delivery {
+                at: now + delay,
+                seq: state.seq,
+                from,
+                data: data.clone()
+            }));
+        }
+
+        inbox.notify.notify_one();
+    }
+}
+
+/// SimSocket is a socket bound to an address of a simulated [`Network`]. It implements
+/// [`Transport`], so that listeners and clients can run over it.
+pub struct SimSocket {
+    addr: SocketAddr,
+    network: Network,
+    inbox: Arc<Inbox>
+}
+
+impl Drop for SimSocket {
+    fn drop(&mut self) {
+        self.network.state.lock().unwrap().inboxes.remove(&self.addr);
+    }
+}
+
+impl Transport for SimSocket {
+    fn local_addr(&self) -> io::Result<SocketAddr> {
+        Ok(self.addr)
+    }
+
+    async fn send_to(&self, buf: &[u8], addr: SocketAddr) -> io::Result<usize> {
+        self.network.send(buf, self.addr, addr);
+        Ok(buf.len())
+    }
+
+    async fn recv_from(&self, buf: &mut [u8]) -> io::Result<(usize, SocketAddr)> {
+        loop {
+            let notified = self.inbox.notify.notified();
+
+            let next = {
+                let mut queue = self.inbox.queue.lock().unwrap();
+                match queue.peek() {
+                    Some(Reverse(delivery)) if delivery.at <= Instant::now() => {
+                        let Reverse(delivery) = queue.pop().unwrap();
+                        let len = delivery.data.len().min(buf.len());
+                        buf[..len].copy_from_slice(&delivery.data[..len]);
+
+                        return Ok((len, delivery.from));
+                    }
+                    Some(Reverse(delivery)) => Some(delivery.at),
+                    None => None
+                }
+            };
+
+            match next {
+                Some(at) => tokio::select! {
+                    _ = sleep_until(at) => {}
+                    _ = notified => {}
+                },
+                None => notified.await
+            }
+        }
+    }
+}
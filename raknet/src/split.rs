@@ -0,0 +1,220 @@
+use std::collections::HashMap;
+use std::time::Duration;
+use bytes::{BufMut, BytesMut};
+use tokio::time::Instant;
+use crate::config::Config;
+use crate::types::Frame;
+
+/// SplitError is returned when a fragment cannot be accepted by the [`SplitAssembler`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SplitError {
+    /// The fragment's split count is zero or exceeds the configured maximum.
+    InvalidCount,
+    /// The fragment's split index is out of range, or its split count differs from that of
+    /// the other fragments sharing its split ID.
+    InvalidIndex,
+    /// Accepting the fragment would exceed the maximum amount of concurrent split messages.
+    TooManySplits,
+    /// Accepting the fragment would exceed the maximum amount of buffered split bytes.
+    TooLarge
+}
+
+/// PendingSplit is a split message of which not all fragments have been received yet.
+struct PendingSplit {
+    fragments: Vec<Option<Frame>>,
+    received: u32,
+    size: usize,
+    created: Instant
+}
+
+/// SplitAssembler reassembles split messages from their fragments. It enforces limits on the
+/// amount of split messages that are reassembled concurrently and on the total amount of bytes
+/// they hold, so that a peer cannot exhaust memory by sending fragments that never complete.
+pub struct SplitAssembler {
+    pending: HashMap<u16, PendingSplit>,
+    size: usize,
+    max_count: u32,
+    max_concurrent: usize,
+    max_size: usize,
+    timeout: Duration
+}
+
+impl SplitAssembler {
+    pub fn new(config: &Config) -> Self {
+        Self {
+            pending: HashMap::new(),
+            size: 0,
+            max_count: config.max_split_count,
+            max_concurrent: config.max_concurrent_splits,
+            max_size: config.max_split_size,
+            timeout: config.split_timeout
+        }
+    }
+
+    /// Adds a fragment to the split message it belongs to. If the fragment completes the
+    /// message, the reassembled frame is returned. The reassembled frame carries the
+    /// reliability and indices of the first fragment.
+    pub fn insert(&mut self, frame: Frame, now: Instant) -> Result<Option<Frame>, SplitError> {
+        let split = frame.split.expect("frame passed to SplitAssembler must be split");
+
+        if split.count == 0 || split.count > self.max_count {
+            return Err(SplitError::InvalidCount);
+        }
+        if split.index >= split.count {
+            return Err(SplitError::InvalidIndex);
+        }
+        if self.size + frame.body.len() > self.max_size {
+            return Err(SplitError::TooLarge);
+        }
+
+        if !self.pending.contains_key(&split.id) {
+            if self.pending.len() >= self.max_concurrent {
+                return Err(SplitError::TooManySplits);
+            }
+
+            self.pending.insert(split.id, PendingSplit {
+                fragments: vec![None; split.count as usize],
+                received: 0,
+                size: 0,
+                created: now
+            });
+        }
+
+        let pending = self.pending.get_mut(&split.id).unwrap();
+
+        if pending.fragments.len() != split.count as usize {
+            return Err(SplitError::InvalidIndex);
+        }
+
+        let slot = &mut pending.fragments[split.index as usize];
+        if slot.is_some() {
+            // Duplicate fragment, which may happen if a datagram was resent.
+            return Ok(None);
+        }
+
+        self.size += frame.body.len();
+        pending.size += frame.body.len();
+        pending.received += 1;
+        *slot = Some(frame);
+
+        if pending.received < split.count {
+            return Ok(None);
+        }
+
+        let pending = self.pending.remove(&split.id).unwrap();
+        self.size -= pending.size;
+
+        let mut body = BytesMut::with_capacity(pending.size);
+        let mut fragments = pending.fragments.into_iter().flatten();
+
+        let mut frame = fragments.next().unwrap();
+        body.put_slice(&frame.body);
+
+        for fragment in fragments {
+            body.put_slice(&fragment.body);
+        }
+
+        frame.split = None;
+        frame.body = body.freeze();
+
+        Ok(Some(frame))
+    }
+
+    /// Discards all split messages that were not completed within the timeout.
+    pub fn expire(&mut self, now: Instant) {
+        let timeout = self.timeout;
+        let mut size = self.size;
+
+        self.pending.retain(|_, pending| {
+            let keep = now.duration_since(pending.created) < timeout;
+            if !keep {
+                size -= pending.size;
+            }
+            keep
+        });
+
+        self.size = size;
+    }
+
+    /// Returns the amount of split messages currently being reassembled.
+    pub fn len(&self) -> usize {
+        self.pending.len()
+    }
+
+    /// Returns true if no split messages are currently being reassembled.
+    pub fn is_empty(&self) -> bool {
+        self.pending.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bytes::Bytes;
+    use super::*;
+
+    fn fragment(id: u16, index: u32, count: u32, body: &'static [u8]) -> Frame {
+        Frame { split: Some(crate::types::Split { count, id, index }), body: Bytes::from_static(body), ..Default::default() }
+    }
+
+    /// Inserts the fragment and returns the body of the message it completed, if any.
+    fn insert(splits: &mut SplitAssembler, frame: Frame, now: Instant) -> Result<Option<Bytes>, SplitError> {
+        splits.insert(frame, now).map(|frame| frame.map(|frame| frame.body))
+    }
+
+    #[test]
+    fn reassembles_out_of_order_and_duplicate_fragments() {
+        let mut splits = SplitAssembler::new(&Config::default());
+        let now = Instant::now();
+
+        assert_eq!(insert(&mut splits, fragment(1, 2, 3, b"c"), now), Ok(None));
+        assert_eq!(insert(&mut splits, fragment(1, 0, 3, b"a"), now), Ok(None));
+        assert_eq!(insert(&mut splits, fragment(1, 0, 3, b"a"), now), Ok(None));
+
+        let frame = splits.insert(fragment(1, 1, 3, b"b"), now).unwrap().unwrap();
+        assert_eq!(frame.body.as_ref(), b"abc");
+        assert!(frame.split.is_none());
+        assert!(splits.is_empty());
+    }
+
+    #[test]
+    fn rejects_invalid_counts_and_indices() {
+        let mut splits = SplitAssembler::new(&Config { max_split_count: 4, ..Default::default() });
+        let now = Instant::now();
+
+        assert_eq!(insert(&mut splits, fragment(1, 0, 0, b"a"), now), Err(SplitError::InvalidCount));
+        assert_eq!(insert(&mut splits, fragment(1, 0, 5, b"a"), now), Err(SplitError::InvalidCount));
+        assert_eq!(insert(&mut splits, fragment(1, 4, 4, b"a"), now), Err(SplitError::InvalidIndex));
+
+        assert_eq!(insert(&mut splits, fragment(1, 0, 4, b"a"), now), Ok(None));
+        assert_eq!(insert(&mut splits, fragment(1, 1, 3, b"a"), now), Err(SplitError::InvalidIndex));
+    }
+
+    #[test]
+    fn limits_concurrent_splits_and_size() {
+        let mut splits = SplitAssembler::new(&Config { max_concurrent_splits: 2, max_split_size: 4, ..Default::default() });
+        let now = Instant::now();
+
+        assert_eq!(insert(&mut splits, fragment(1, 0, 2, b"a"), now), Ok(None));
+        assert_eq!(insert(&mut splits, fragment(2, 0, 2, b"b"), now), Ok(None));
+        assert_eq!(insert(&mut splits, fragment(3, 0, 2, b"c"), now), Err(SplitError::TooManySplits));
+        assert_eq!(insert(&mut splits, fragment(1, 1, 2, b"abcd"), now), Err(SplitError::TooLarge));
+
+        // Completing a split releases its bytes.
+        assert!(insert(&mut splits, fragment(1, 1, 2, b"aa"), now).unwrap().is_some());
+        assert_eq!(insert(&mut splits, fragment(3, 0, 2, b"ccc"), now), Ok(None));
+    }
+
+    #[test]
+    fn expires_incomplete_splits() {
+        let mut splits = SplitAssembler::new(&Config { max_split_size: 2, split_timeout: Duration::from_secs(1), ..Default::default() });
+        let now = Instant::now();
+
+        assert_eq!(insert(&mut splits, fragment(1, 0, 2, b"ab"), now), Ok(None));
+        splits.expire(now + Duration::from_millis(500));
+        assert_eq!(splits.len(), 1);
+
+        splits.expire(now + Duration::from_secs(1));
+        assert!(splits.is_empty());
+        assert_eq!(insert(&mut splits, fragment(2, 0, 2, b"cd"), now), Ok(None));
+    }
+}
@@ -0,0 +1,100 @@
+use std::ops::AddAssign;
+use std::time::Duration;
+
+/// Stats is a snapshot of the statistics of a connection.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Stats {
+    /// The smoothed round trip time of datagrams.
+    pub rtt: Duration,
+    /// The duration after which an unacknowledged datagram is resent.
+    pub rto: Duration,
+    /// The congestion window in bytes: the amount of bytes that may be unacknowledged at once.
+    pub congestion_window: usize,
+    /// The amount of bytes sent in datagrams that were not acknowledged yet.
+    pub bytes_in_flight: usize,
+    /// The estimated bandwidth of the connection in bytes per second.
+    pub bandwidth: f64,
+    /// The amount of frames queued that were not yet sent in a datagram.
+    pub queued_frames: usize,
+    /// The amount of datagrams sent that were not acknowledged yet.
+    pub unacknowledged_datagrams: usize,
+    /// The amount of frames received out of order, waiting in the order channels for the frames
+    /// before them.
+    pub ordered_frames: usize,
+    /// The amount of split messages of which only some fragments were received.
+    pub pending_splits: usize,
+    /// The smoothed round trip time of ConnectedPing packets answered by the peer. It is zero
+    /// until the first ConnectedPong is received.
+    pub latency: Duration,
+    /// The counters of the connection since it was opened.
+    pub counters: Counters
+}
+
+/// Counters are the totals of a connection since it was opened. They only ever increase, so that
+/// the counters of several connections can be summed up.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Counters {
+    /// The amount of bytes sent in datagrams, including acknowledgements.
+    pub bytes_sent: u64,
+    /// The amount of bytes received in datagrams, including acknowledgements.
+    pub bytes_received: u64,
+    pub datagrams_sent: u64,
+    pub datagrams_received: u64,
+    /// The amount of messages sent, including internal RakNet packets.
+    pub messages_sent: u64,
+    /// The amount of messages that passed the reliability layer, including internal RakNet
+    /// packets.
+    pub messages_received: u64,
+    /// The amount of datagrams that were lost or not acknowledged in time, of which the reliable
+    /// frames were queued to be sent again.
+    pub resends: u64,
+    /// The amount of datagrams negatively acknowledged to the peer.
+    pub nacks_sent: u64,
+    /// The amount of datagrams the peer negatively acknowledged.
+    pub nacks_received: u64,
+    /// The amount of messages sent that were split into fragments.
+    pub splits_sent: u64,
+    /// The amount of split messages received that were reassembled.
+    pub splits_received: u64
+}
+
+impl AddAssign for Counters {
+    fn add_assign(&mut self, rhs: Self) {
+        self.bytes_sent += rhs.bytes_sent;
+        self.bytes_received += rhs.bytes_received;
+        self.datagrams_sent += rhs.datagrams_sent;
+        self.datagrams_received += rhs.datagrams_received;
+        self.messages_sent += rhs.messages_sent;
+        self.messages_received += rhs.messages_received;
+        self.resends += rhs.resends;
+        self.nacks_sent += rhs.nacks_sent;
+        self.nacks_received += rhs.nacks_received;
+        self.splits_sent += rhs.splits_sent;
+        self.splits_received += rhs.splits_received;
+    }
+}
+
+/// ListenerStats is a snapshot of the statistics of a listener and the connections it accepted.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ListenerStats {
+    /// The amount of connections currently open, including those still in the connection
+    /// handshake.
+    pub connections: usize,
+    /// The amount of connections opened since the listener was bound.
+    pub connections_opened: u64,
+    /// The amount of connections refused because the listener was at its connection limits.
+    pub connections_refused: u64,
+    /// The amount of offline messages received, such as unconnected pings and the messages of the
+    /// offline handshake.
+    pub offline_received: u64,
+    /// The amount of offline messages dropped because their address was blocked or exceeded the
+    /// offline rate limit.
+    pub offline_dropped: u64,
+    /// The amount of frames queued over all open connections.
+    pub queued_frames: usize,
+    /// The amount of unacknowledged bytes over all open connections.
+    pub bytes_in_flight: usize,
+    /// The counters of all connections opened since the listener was bound, whether they are
+    /// still open or not.
+    pub counters: Counters
+}
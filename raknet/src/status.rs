@@ -0,0 +1,147 @@
+use std::fmt::{Display, Formatter};
+
+/// ServerStatus is the status of a server advertised in UnconnectedPong to clients listing
+/// servers. It is written as a list of fields separated by semicolons.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ServerStatus {
+    /// The edition of the game, MCPE for Bedrock Edition or MCEE for Education Edition.
+    pub edition: String,
+    /// The first line of the MOTD shown in the server list.
+    pub motd: String,
+    /// The Bedrock protocol version supported by the server.
+    pub protocol: i32,
+    /// The game version supported by the server, such as 1.21.40.
+    pub version: String,
+    pub player_count: i32,
+    pub max_player_count: i32,
+    /// The RakNet GUID of the server. A listener overwrites it with its own GUID. Servers advertise
+    /// it either signed or unsigned, and both are parsed into the same GUID.
+    pub server_guid: i64,
+    /// The second line of the MOTD shown in the server list.
+    pub sub_motd: String,
+    /// The name of the default game mode of the server, such as Survival.
+    pub game_mode: String,
+    /// The numeric ID of the default game mode of the server.
+    pub game_mode_id: i32,
+    /// The port the server listens on for IPv4 connections.
+    pub ipv4_port: u16,
+    /// The port the server listens on for IPv6 connections.
+    pub ipv6_port: u16
+}
+
+impl Default for ServerStatus {
+    fn default() -> Self {
+        Self {
+            edition: "MCPE".into(),
+            motd: "Dedicated Server".into(),
+            protocol: 390,
+            version: "1.14.60".into(),
+            player_count: 0,
+            max_player_count: 10,
+            server_guid: 0,
+            sub_motd: "Bedrock level".into(),
+            game_mode: "Survival".into(),
+            game_mode_id: 1,
+            ipv4_port: 19132,
+            ipv6_port: 19133
+        }
+    }
+}
+
+impl ServerStatus {
+    /// Parses a status from the data of an UnconnectedPong. Only the edition, MOTD, protocol,
+    /// version and player counts are required: older servers omit the remaining fields.
+    pub fn parse(data: &str) -> Option<Self> {
+        let mut fields = data.split(';');
+        let mut status = Self {
+            edition: fields.next()?.into(),
+            motd: fields.next()?.into(),
+            protocol: fields.next()?.parse().ok()?,
+            version: fields.next()?.into(),
+            player_count: fields.next()?.parse().ok()?,
+            max_player_count: fields.next()?.parse().ok()?,
+            ..Default::default()
+        };
+
+        status.server_guid = fields.next().and_then(parse_guid).unwrap_or_default();
+        status.sub_motd = fields.next().unwrap_or_default().into();
+        status.game_mode = fields.next().unwrap_or_default().into();
+        status.game_mode_id = fields.next().and_then(|f| f.parse().ok()).unwrap_or_default();
+        status.ipv4_port = fields.next().and_then(|f| f.parse().ok()).unwrap_or_default();
+        status.ipv6_port = fields.next().and_then(|f| f.parse().ok()).unwrap_or_default();
+
+        Some(status)
+    }
+}
+
+/// Parses a GUID written as either a signed or an unsigned 64-bit integer.
+fn parse_guid(field: &str) -> Option<i64> {
+    field.parse().ok().or_else(|| field.parse::<u64>().ok().map(|guid| guid as i64))
+}
+
+impl Display for ServerStatus {
+    /// Writes the status in the format of the data of an UnconnectedPong. Semicolons in the text
+    /// fields are removed, as they would otherwise be read as field separators.
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let text = |s: &str| s.replace(';', "");
+
+        write!(
+            f,
+            "{};{};{};{};{};{};{};{};{};{};{};{};",
+            text(&self.edition),
+            text(&self.motd),
+            self.protocol,
+            text(&self.version),
+            self.player_count,
+            self.max_player_count,
+            self.server_guid,
+            text(&self.sub_motd),
+            text(&self.game_mode),
+            self.game_mode_id,
+            self.ipv4_port,
+            self.ipv6_port
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const VANILLA: &str = "MCPE;Dedicated Server;748;1.21.40;2;10;-6385961468291245862;Bedrock level;Survival;1;19132;19133;";
+
+    #[test]
+    fn parses_and_writes_vanilla_status() {
+        let status = ServerStatus::parse(VANILLA).unwrap();
+        assert_eq!(status.protocol, 748);
+        assert_eq!(status.player_count, 2);
+        assert_eq!(status.server_guid, -6385961468291245862);
+        assert_eq!(status.ipv6_port, 19133);
+
+        assert_eq!(status.to_string(), VANILLA);
+        assert_eq!(ServerStatus::parse(&status.to_string()), Some(status));
+    }
+
+    #[test]
+    fn unsigned_guids_are_reinterpreted() {
+        let status = ServerStatus::parse("MCPE;a;748;1.21.40;0;10;18446744073709551615;").unwrap();
+        assert_eq!(status.server_guid, -1);
+    }
+
+    #[test]
+    fn optional_fields_may_be_missing() {
+        let status = ServerStatus::parse("MCPE;Old Server;390;1.14.60;0;20").unwrap();
+        assert_eq!(status.motd, "Old Server");
+        assert_eq!(status.server_guid, 0);
+        assert_eq!(status.sub_motd, "");
+
+        assert_eq!(ServerStatus::parse("MCPE;a;b;1.14.60;0;20"), None);
+        assert_eq!(ServerStatus::parse("MCPE;a;390"), None);
+    }
+
+    #[test]
+    fn semicolons_are_removed_from_text() {
+        let status = ServerStatus { motd: String::from("a;b"), ..Default::default() };
+        assert_eq!(ServerStatus::parse(&status.to_string()).unwrap().motd, "ab");
+    }
+}
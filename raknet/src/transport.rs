@@ -0,0 +1,33 @@
+use std::future::Future;
+use std::io;
+use std::net::SocketAddr;
+use tokio::net::UdpSocket;
+
+/// Transport sends and receives the datagrams of RakNet connections. It is implemented by
+/// [`UdpSocket`], and by [`SimSocket`](crate::simulator::SimSocket) to run connections over a
+/// simulated network.
+pub trait Transport: Send + Sync + 'static {
+    /// Returns the local address the transport is bound to.
+    fn local_addr(&self) -> io::Result<SocketAddr>;
+
+    /// Sends a datagram to the address passed.
+    fn send_to(&self, buf: &[u8], addr: SocketAddr) -> impl Future<Output = io::Result<usize>> + Send;
+
+    /// Waits for the next datagram and writes it to the buffer passed. Returns the length of the
+    /// datagram and the address it was sent from.
+    fn recv_from(&self, buf: &mut [u8]) -> impl Future<Output = io::Result<(usize, SocketAddr)>> + Send;
+}
+
+impl Transport for UdpSocket {
+    fn local_addr(&self) -> io::Result<SocketAddr> {
+        UdpSocket::local_addr(self)
+    }
+
+    fn send_to(&self, buf: &[u8], addr: SocketAddr) -> impl Future<Output = io::Result<usize>> + Send {
+        UdpSocket::send_to(self, buf, addr)
+    }
+
+    fn recv_from(&self, buf: &mut [u8]) -> impl Future<Output = io::Result<(usize, SocketAddr)>> + Send {
+        UdpSocket::recv_from(self, buf)
+    }
+}
@@ -0,0 +1,117 @@
+use binary::{n16, Decode, Encode, Reader, Writer};
+use crate::types::U24;
+
+/// The maximum amount of sequence numbers decoded from a single acknowledgement. Ranges in
+/// an acknowledgement are expanded, so without a limit a few bytes could allocate megabytes.
+const MAX_ACKNOWLEDGED: usize = 8192;
+
+/// Acknowledgement holds the sequence numbers of datagrams acknowledged (ACK) or negatively
+/// acknowledged (NACK) by a peer. The sequence numbers are written as a list of records, each
+/// either a single sequence number or a range of consecutive ones.
+#[derive(Debug, Clone, Default)]
+pub struct Acknowledgement {
+    pub sequences: Vec<u32>
+}
+
+impl Encode for Acknowledgement {
+    fn encode(&self, w: &mut Writer) {
+        let mut sequences = self.sequences.clone();
+        sequences.sort_unstable();
+        sequences.dedup();
+
+        let mut records = Vec::new();
+        let mut iter = sequences.into_iter();
+
+        if let Some(first) = iter.next() {
+            let (mut start, mut end) = (first, first);
+
+            for seq in iter {
+                if seq == end + 1 {
+                    end = seq;
+                    continue;
+                }
+
+                records.push((start, end));
+                (start, end) = (seq, seq);
+            }
+
+            records.push((start, end));
+        }
+
+        n16::new(records.len() as u16).encode(w);
+
+        for (start, end) in records {
+            if start == end {
+                true.encode(w);
+                U24::new(start).encode(w);
+            } else {
+                false.encode(w);
+                U24::new(start).encode(w);
+                U24::new(end).encode(w);
+            }
+        }
+    }
+}
+
+impl Decode<'_> for Acknowledgement {
+    fn decode(r: &mut Reader<'_>) -> Option<Self> {
+        let count = n16::decode(r)?.value();
+        let mut sequences = Vec::new();
+
+        for _ in 0..count {
+            let single = bool::decode(r)?;
+            let start = U24::decode(r)?.value();
+            let end = if single { start } else { U24::decode(r)?.value() };
+
+            for seq in start..=end {
+                if sequences.len() >= MAX_ACKNOWLEDGED {
+                    return Some(Self { sequences });
+                }
+                sequences.push(seq);
+            }
+        }
+
+        Some(Self { sequences })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bytes::BytesMut;
+    use super::*;
+
+    fn round_trip(sequences: Vec<u32>) -> (Vec<u8>, Vec<u32>) {
+        let mut w = BytesMut::new();
+        Acknowledgement { sequences }.encode(&mut w);
+        let ack = Acknowledgement::decode(&mut &w[..]).unwrap();
+        (w.to_vec(), ack.sequences)
+    }
+
+    #[test]
+    fn encodes_ranges_and_single_sequences() {
+        let (buf, sequences) = round_trip(vec![7, 1, 2, 3, 3, 5]);
+        assert_eq!(sequences, [1, 2, 3, 5, 7]);
+
+        // Three records: the range 1-3 and the single sequences 5 and 7.
+        assert_eq!(&buf[..2], &[0, 3]);
+        assert_eq!(buf.len(), 2 + 7 + 4 + 4);
+    }
+
+    #[test]
+    fn encodes_sequences_at_the_end_of_the_u24_range() {
+        let (_, sequences) = round_trip(vec![0xfffffe, 0xffffff]);
+        assert_eq!(sequences, [0xfffffe, 0xffffff]);
+    }
+
+    #[test]
+    fn limits_decoded_sequences() {
+        let mut w = BytesMut::new();
+        n16::new(1).encode(&mut w);
+        false.encode(&mut w);
+        U24::new(0).encode(&mut w);
+        U24::new(0xffffff).encode(&mut w);
+
+        let ack = Acknowledgement::decode(&mut &w[..]).unwrap();
+        assert_eq!(ack.sequences.len(), MAX_ACKNOWLEDGED);
+    }
+}
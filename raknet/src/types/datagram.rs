@@ -0,0 +1,53 @@
+use bytes::Buf;
+use binary::{Decode, Encode, Reader, Writer};
+use crate::types::{Frame, U24};
+
+/// Flag set on every datagram sent by a connected peer.
+pub const FLAG_VALID: u8 = 0x80;
+/// Flag set on datagrams holding acknowledgements.
+pub const FLAG_ACK: u8 = 0x40;
+/// Flag set on datagrams holding negative acknowledgements.
+pub const FLAG_NACK: u8 = 0x20;
+/// Flag set on datagrams sent as part of a continuous stream of datagrams.
+pub const FLAG_CONTINUOUS_SEND: u8 = 0x08;
+/// Flag set on datagrams to request the receiver to calculate bandwidth and send rate.
+pub const FLAG_NEEDS_B_AND_AS: u8 = 0x04;
+
+/// The size of the header preceding the frames in a datagram: the flags and the sequence number.
+pub const DATAGRAM_HEADER_SIZE: usize = 4;
+
+/// The size of the IP and UDP headers that are included in the MTU negotiated by the peers.
+pub const UDP_HEADER_SIZE: usize = 20 + 8;
+
+/// Datagram is a single UDP packet sent by a connected peer holding one or more frames.
+#[derive(Debug, Clone, Default)]
+pub struct Datagram {
+    pub flags: u8,
+    pub sequence: u32,
+    pub frames: Vec<Frame>
+}
+
+impl Encode for Datagram {
+    fn encode(&self, w: &mut Writer) {
+        (self.flags | FLAG_VALID).encode(w);
+        U24::new(self.sequence).encode(w);
+
+        for frame in &self.frames {
+            frame.encode(w);
+        }
+    }
+}
+
+impl Decode<'_> for Datagram {
+    fn decode(r: &mut Reader<'_>) -> Option<Self> {
+        let flags = u8::decode(r)?;
+        let sequence = U24::decode(r)?.value();
+
+        let mut frames = Vec::new();
+        while r.has_remaining() {
+            frames.push(Frame::decode(r)?);
+        }
+
+        Some(Self { flags, sequence, frames })
+    }
+}
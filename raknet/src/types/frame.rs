@@ -0,0 +1,130 @@
+use bytes::{Buf, BufMut, Bytes};
+use binary::{n16, n32, Decode, Encode, Reader, Writer};
+use crate::types::{Reliability, U24};
+
+/// Flag set in the frame header if the frame is a fragment of a larger message.
+const FLAG_SPLIT: u8 = 0x10;
+
+/// Split holds the information carried by a frame that is a fragment of a message that did not
+/// fit into a single datagram.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Split {
+    /// The total amount of fragments the message was split into.
+    pub count: u32,
+    /// The identifier shared by all fragments of the same message.
+    pub id: u16,
+    /// The index of this fragment within the message.
+    pub index: u32
+}
+
+/// Frame is a single message, or a fragment of one, encapsulated in a datagram.
+#[derive(Debug, Clone, Default)]
+pub struct Frame {
+    pub reliability: Reliability,
+    /// The reliable message index, only present if the reliability is reliable.
+    pub reliable_index: u32,
+    /// The sequence index, only present if the reliability is sequenced.
+    pub sequence_index: u32,
+    /// The order index, only present if the reliability is ordered or sequenced.
+    pub order_index: u32,
+    /// The channel the order and sequence indices apply to.
+    pub order_channel: u8,
+    pub split: Option<Split>,
+    pub body: Bytes
+}
+
+impl Frame {
+    /// Returns the amount of bytes this frame occupies once encoded in a datagram.
+    pub fn size(&self) -> usize {
+        Self::header_size(self.reliability, self.split.is_some()) + self.body.len()
+    }
+
+    /// Returns the size of the header of a frame with the specified reliability.
+    pub fn header_size(reliability: Reliability, split: bool) -> usize {
+        let mut size = 3;
+
+        if reliability.is_reliable() {
+            size += 3;
+        }
+        if reliability.is_sequenced() {
+            size += 3;
+        }
+        if reliability.is_ordered() {
+            size += 4;
+        }
+        if split {
+            size += 10;
+        }
+
+        size
+    }
+}
+
+impl Encode for Frame {
+    fn encode(&self, w: &mut Writer) {
+        let mut header = (self.reliability as u8) << 5;
+        if self.split.is_some() {
+            header |= FLAG_SPLIT;
+        }
+
+        header.encode(w);
+        n16::new((self.body.len() << 3) as u16).encode(w);
+
+        if self.reliability.is_reliable() {
+            U24::new(self.reliable_index).encode(w);
+        }
+        if self.reliability.is_sequenced() {
+            U24::new(self.sequence_index).encode(w);
+        }
+        if self.reliability.is_ordered() {
+            U24::new(self.order_index).encode(w);
+            self.order_channel.encode(w);
+        }
+        if let Some(split) = &self.split {
+            n32::new(split.count).encode(w);
+            n16::new(split.id).encode(w);
+            n32::new(split.index).encode(w);
+        }
+
+        w.put_slice(&self.body);
+    }
+}
+
+impl Decode<'_> for Frame {
+    fn decode(r: &mut Reader<'_>) -> Option<Self> {
+        let header = u8::decode(r)?;
+        let len = (n16::decode(r)?.value() as usize + 7) >> 3;
+
+        let mut frame = Self {
+            reliability: Reliability::from_byte(header >> 5)?,
+            ..Default::default()
+        };
+
+        if frame.reliability.is_reliable() {
+            frame.reliable_index = U24::decode(r)?.value();
+        }
+        if frame.reliability.is_sequenced() {
+            frame.sequence_index = U24::decode(r)?.value();
+        }
+        if frame.reliability.is_ordered() {
+            frame.order_index = U24::decode(r)?.value();
+            frame.order_channel = u8::decode(r)?;
+        }
+        if header & FLAG_SPLIT != 0 {
+            frame.split = Some(Split {
+                count: n32::decode(r)?.value(),
+                id: n16::decode(r)?.value(),
+                index: n32::decode(r)?.value()
+            });
+        }
+
+        if len == 0 || r.remaining() < len {
+            return None;
+        }
+
+        frame.body = Bytes::copy_from_slice(&r[..len]);
+        r.advance(len);
+
+        Some(frame)
+    }
+}
@@ -1,22 +1,27 @@
 use bytes::{Buf, BufMut};
 use binary::{Decode, Encode, Reader, Writer};
 
+/// The magic bytes found in every offline message, used to tell offline messages apart from
+/// datagrams of connections.
+pub const MAGIC: [u8; 16] = [0x00, 0xff, 0xff, 0x00, 0xfe, 0xfe, 0xfe, 0xfe, 0xfd, 0xfd, 0xfd, 0xfd, 0x12, 0x34, 0x56, 0x78];
+
 #[derive(Debug)]
 pub struct Magic;
 
 impl Encode for Magic {
     fn encode(&self, w: &mut Writer) {
-        w.put_slice(&[0x00, 0xff, 0xff, 0x00, 0xfe, 0xfe, 0xfe, 0xfe, 0xfd, 0xfd, 0xfd, 0xfd, 0x12, 0x34, 0x56, 0x78]);
+        w.put_slice(&MAGIC);
     }
 }
 
 impl Decode<'_> for Magic {
+    /// Decodes the magic, failing if the bytes read do not match it.
     fn decode(r: &mut Reader<'_>) -> Option<Self> {
-        if r.remaining() < 16 {
+        if r.remaining() < MAGIC.len() || r[..MAGIC.len()] != MAGIC {
             return None;
         }
         
-        r.advance(16);
+        r.advance(MAGIC.len());
         Some(Magic)
     }
 }
\ No newline at end of file
@@ -1,6 +1,13 @@
+pub mod ack;
+pub mod datagram;
+pub mod frame;
 pub mod magic;
+pub mod reliability;
 pub mod triad;
 
+pub use ack::*;
+pub use datagram::*;
+pub use frame::*;
 pub use magic::*;
+pub use reliability::*;
 pub use triad::*;
-
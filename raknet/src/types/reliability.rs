@@ -0,0 +1,64 @@
+/// Reliability specifies the delivery guarantees RakNet provides for a single frame. It is
+/// encoded in the upper three bits of the frame header.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum Reliability {
+    /// The frame may be lost, duplicated or arrive out of order.
+    Unreliable,
+    /// The frame may be lost, but older frames arriving after a newer one are dropped.
+    UnreliableSequenced,
+    /// The frame is resent until it is acknowledged, but may arrive out of order.
+    Reliable,
+    /// The frame is resent until it is acknowledged and is delivered in order on its channel.
+    #[default]
+    ReliableOrdered,
+    /// The frame is resent until it is acknowledged, but older frames arriving after a newer
+    /// one are dropped.
+    ReliableSequenced,
+    UnreliableWithAckReceipt,
+    ReliableWithAckReceipt,
+    ReliableOrderedWithAckReceipt
+}
+
+impl Reliability {
+    pub fn from_byte(byte: u8) -> Option<Self> {
+        match byte {
+            0 => Some(Self::Unreliable),
+            1 => Some(Self::UnreliableSequenced),
+            2 => Some(Self::Reliable),
+            3 => Some(Self::ReliableOrdered),
+            4 => Some(Self::ReliableSequenced),
+            5 => Some(Self::UnreliableWithAckReceipt),
+            6 => Some(Self::ReliableWithAckReceipt),
+            7 => Some(Self::ReliableOrderedWithAckReceipt),
+            _ => None
+        }
+    }
+
+    /// Returns true if frames of this reliability carry a reliable message index and must be
+    /// acknowledged by the receiver.
+    pub fn is_reliable(&self) -> bool {
+        matches!(self, Self::Reliable | Self::ReliableOrdered | Self::ReliableSequenced | Self::ReliableWithAckReceipt | Self::ReliableOrderedWithAckReceipt)
+    }
+
+    /// Returns true if frames of this reliability carry a sequence index.
+    pub fn is_sequenced(&self) -> bool {
+        matches!(self, Self::UnreliableSequenced | Self::ReliableSequenced)
+    }
+
+    /// Returns true if frames of this reliability carry an order index and order channel.
+    pub fn is_ordered(&self) -> bool {
+        matches!(self, Self::ReliableOrdered | Self::ReliableOrderedWithAckReceipt) || self.is_sequenced()
+    }
+
+    /// Returns the reliable counterpart of this reliability. Split frames are always sent
+    /// reliably because losing a single fragment would lose the entire message.
+    pub fn reliable(self) -> Self {
+        match self {
+            Self::Unreliable => Self::Reliable,
+            Self::UnreliableSequenced => Self::ReliableSequenced,
+            Self::UnreliableWithAckReceipt => Self::ReliableWithAckReceipt,
+            v => v
+        }
+    }
+}
@@ -0,0 +1,116 @@
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+use bytes::Bytes;
+use raknet::capture::{self, native, pcapng, replay, replay_to, Direction, Format, Kind, Record, Recorder};
+use raknet::simulator::{Conditions, Network};
+use raknet::{Config, RakClient, RakListener, Reliability};
+use tokio::time::Instant;
+
+const SERVER: &str = "10.0.0.1:19132";
+const CLIENT: &str = "[2001:db8::2]:50000";
+
+fn records() -> Vec<Record> {
+    let record = |millis, direction, kind, data: Vec<u8>| Record {
+        time: Duration::from_millis(millis),
+        direction,
+        kind,
+        local_addr: SERVER.parse().unwrap(),
+        remote_addr: if millis % 2 == 0 { "10.0.0.2:50000".parse().unwrap() } else { CLIENT.parse().unwrap() },
+        data: Bytes::from(data)
+    };
+
+    vec![
+        record(0, Direction::Inbound, Kind::Datagram, vec![0x84, 0, 0, 0]),
+        record(15, Direction::Outbound, Kind::Message, vec![0xfe, 1, 2, 3, 4]),
+        record(20, Direction::Inbound, Kind::Packet, vec![0x01, 0x02]),
+        // Reassembled split messages are larger than fits the length fields of IP and UDP.
+        record(1500, Direction::Inbound, Kind::Message, vec![0xfe; 100_000])
+    ]
+}
+
+#[test]
+fn native_captures_round_trip() {
+    let mut buf = Vec::new();
+    native::write_header(&mut buf).unwrap();
+    for record in &records() {
+        native::write_record(&mut buf, record).unwrap();
+    }
+
+    assert_eq!(capture::read_from(&buf).unwrap(), records());
+}
+
+#[test]
+fn pcapng_captures_round_trip() {
+    let start_time = SystemTime::now();
+    let mut buf = Vec::new();
+    pcapng::write_header(&mut buf).unwrap();
+    for record in &records() {
+        pcapng::write_record(&mut buf, record, start_time).unwrap();
+    }
+    // The IPv4 total length of the oversized message is clamped rather than truncated.
+    assert!(buf.windows(4).any(|w| w == [0x45, 0, 0xff, 0xff]));
+
+    assert_eq!(capture::read_from(&buf).unwrap(), records());
+}
+
+#[test]
+fn invalid_captures_are_rejected() {
+    assert!(capture::read_from(b"not a capture").is_err());
+
+    let mut buf = Vec::new();
+    native::write_header(&mut buf).unwrap();
+    native::write_record(&mut buf, &records()[1]).unwrap();
+    buf.pop();
+    assert!(capture::read_from(&buf).is_err());
+}
+
+#[tokio::test(start_paused = true)]
+async fn replay_keeps_the_time_between_records() {
+    let records = records();
+    let start = Instant::now();
+    let mut times = Vec::new();
+    replay(&records, 5.0, |_| times.push(start.elapsed())).await;
+    assert_eq!(times, [0, 3, 4, 300].map(Duration::from_millis));
+
+    let start = Instant::now();
+    replay(&records, f64::INFINITY, |_| {}).await;
+    assert_eq!(start.elapsed(), Duration::ZERO);
+}
+
+#[tokio::test(start_paused = true)]
+async fn connections_are_recorded() {
+    let path = std::env::temp_dir().join(format!("raknet-capture-{}.pcapng", std::process::id()));
+    let recorder = Arc::new(Recorder::create(&path, Format::Pcapng).unwrap());
+    let config = Config { capture: Some(recorder.clone()), ..Default::default() };
+
+    let net = Network::new(Conditions::default(), 1);
+    let server: SocketAddr = SERVER.parse().unwrap();
+    let listener = RakListener::with_transports(vec![net.bind(server).unwrap()], Config::default()).unwrap();
+    let client = RakClient::connect_with_transport(net.bind("10.0.0.2:50000".parse().unwrap()).unwrap(), server, config).await.unwrap();
+    let conn = listener.accept().await.unwrap();
+
+    client.send(Bytes::from_static(&[0xfe, 1, 2, 3]), Reliability::ReliableOrdered).unwrap();
+    conn.recv().await.unwrap();
+    recorder.flush().unwrap();
+
+    let records = capture::read(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert!(records.iter().any(|r| r.kind == Kind::Datagram && r.direction == Direction::Inbound));
+    let sent: Vec<&Record> = records.iter().filter(|r| r.kind == Kind::Message && r.direction == Direction::Outbound).collect();
+    assert!(sent.iter().any(|r| r.data.as_ref() == [0xfe, 1, 2, 3] && r.remote_addr == server));
+}
+
+#[tokio::test]
+async fn messages_are_replayed_at_a_server() {
+    let listener = RakListener::bind("127.0.0.1:0").await.unwrap();
+    let messages = records();
+
+    let (client, conn) = tokio::join!(
+        replay_to(&messages, listener.addr, Direction::Outbound, f64::INFINITY, Config::default()),
+        listener.accept()
+    );
+    let (_client, conn) = (client.unwrap(), conn.unwrap());
+
+    assert_eq!(conn.recv().await.unwrap(), messages[1].data);
+}
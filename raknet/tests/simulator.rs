@@ -0,0 +1,236 @@
+use std::io;
+use std::net::SocketAddr;
+use std::time::Duration;
+use binary::b64;
+use bytes::{Bytes, BytesMut};
+use raknet::packet::{IncompatibleProtocol, OpenConnectionRequest1, Packet};
+use raknet::simulator::{Conditions, Network};
+use raknet::types::Magic;
+use raknet::{Config, RakClient, RakConn, RakListener, Reliability, Stats, Transport};
+
+const SERVER: &str = "10.0.0.1:19132";
+const CLIENT: &str = "10.0.0.2:50000";
+
+/// Returns conditions that lose, duplicate and reorder datagrams at the rates passed.
+fn lossy(loss: f64, duplication: f64, reordering: f64) -> Conditions {
+    Conditions {
+        loss,
+        duplication,
+        reordering,
+        latency: Duration::from_millis(20),
+        jitter: Duration::from_millis(10),
+        reordering_delay: Duration::from_millis(30),
+        mtu: 0
+    }
+}
+
+/// Connects a client to a listener over the network and returns the listener along with both ends
+/// of the connection.
+async fn connect(net: &Network) -> (RakListener, RakConn, RakConn) {
+    let server: SocketAddr = SERVER.parse().unwrap();
+    let listener = RakListener::with_transports(vec![net.bind(server).unwrap()], Config::default()).unwrap();
+    let client = RakClient::connect_with_transport(net.bind(CLIENT.parse().unwrap()).unwrap(), server, Config::default()).await.unwrap();
+    let conn = listener.accept().await.unwrap();
+
+    (listener, client, conn)
+}
+
+/// Returns a message of the size passed, starting with the ID of game packets and filled with the
+/// index passed.
+fn message(index: u8, size: usize) -> Bytes {
+    let mut msg = vec![index; size];
+    msg[0] = 0xfe;
+    Bytes::from(msg)
+}
+
+/// Sends the messages from one end of a connection and checks that the other end receives them
+/// all, in order. Returns the stats of the sending end.
+async fn transfer(sender: &RakConn, receiver: &RakConn, messages: &[Bytes]) -> Stats {
+    for msg in messages {
+        sender.send(msg.clone(), Reliability::ReliableOrdered).unwrap();
+    }
+    for msg in messages {
+        assert_eq!(&receiver.recv().await.unwrap(), msg);
+    }
+    sender.stats()
+}
+
+#[tokio::test(start_paused = true)]
+async fn reliable_ordered_delivery_over_lossy_network() {
+    let net = Network::new(lossy(0.1, 0.05, 0.1), 1);
+    let (_listener, client, conn) = connect(&net).await;
+
+    // Small messages are interleaved with messages split into many fragments.
+    let messages: Vec<Bytes> = (0..100u8)
+        .map(|i| message(i, if i % 10 == 0 { 20_000 } else { 50 }))
+        .collect();
+
+    let stats = transfer(&client, &conn, &messages).await;
+    assert!(stats.counters.splits_sent >= 10);
+    assert!(stats.counters.resends + stats.counters.nacks_received > 0);
+
+    // The other direction is just as reliable.
+    transfer(&conn, &client, &messages).await;
+}
+
+#[tokio::test(start_paused = true)]
+async fn duplicated_datagrams_are_delivered_once() {
+    let net = Network::new(lossy(0.0, 0.5, 0.0), 2);
+    let (_listener, client, conn) = connect(&net).await;
+
+    let messages: Vec<Bytes> = (0..50u8).map(|i| message(i, 2_000)).collect();
+    transfer(&client, &conn, &messages).await;
+
+    client.send(message(0xff, 10), Reliability::ReliableOrdered).unwrap();
+    assert_eq!(conn.recv().await.unwrap(), message(0xff, 10));
+    assert!(tokio::time::timeout(Duration::from_secs(1), conn.recv()).await.is_err());
+}
+
+#[tokio::test(start_paused = true)]
+async fn congestion_window_backs_off_under_loss() {
+    let messages: Vec<Bytes> = (0..50u8).map(|i| message(i, 20_000)).collect();
+
+    // Without jitter, datagrams arrive in the order they were sent, so none are ever resent.
+    let net = Network::new(Conditions { latency: Duration::from_millis(20), ..Default::default() }, 3);
+    let (_listener, client, conn) = connect(&net).await;
+    let clean = transfer(&client, &conn, &messages).await;
+
+    let net = Network::new(lossy(0.2, 0.0, 0.0), 3);
+    let (_listener, client, conn) = connect(&net).await;
+    let congested = transfer(&client, &conn, &messages).await;
+
+    assert_eq!(clean.counters.resends, 0);
+    assert!(congested.counters.resends + congested.counters.nacks_received > 0);
+    assert!(congested.congestion_window < clean.congestion_window, "{} >= {}", congested.congestion_window, clean.congestion_window);
+}
+
+#[tokio::test(start_paused = true)]
+async fn unknown_message_ids_are_passed_on() {
+    let net = Network::new(Conditions::default(), 4);
+    let (_listener, client, conn) = connect(&net).await;
+
+    client.send(Bytes::from_static(&[0x86, 1, 2]), Reliability::ReliableOrdered).unwrap();
+    assert_eq!(conn.recv().await.unwrap().as_ref(), &[0x86, 1, 2]);
+}
+
+#[tokio::test(start_paused = true)]
+async fn order_channels_out_of_range_are_rejected() {
+    let net = Network::new(Conditions::default(), 5);
+    let (_listener, client, conn) = connect(&net).await;
+
+    let err = client.send_on_channel(message(0, 10), Reliability::ReliableOrdered, 32).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+
+    // The connection keeps working.
+    client.send_on_channel(message(1, 10), Reliability::ReliableOrdered, 31).unwrap();
+    assert_eq!(conn.recv().await.unwrap(), message(1, 10));
+}
+
+#[tokio::test(start_paused = true)]
+async fn probabilities_out_of_range_are_clamped() {
+    let net = Network::new(Conditions { loss: 2.0, duplication: f64::NAN, reordering: -1.0, ..Default::default() }, 6);
+    let server = net.bind(SERVER.parse().unwrap()).unwrap();
+    let client = net.bind(CLIENT.parse().unwrap()).unwrap();
+    let mut buf = [0; 16];
+
+    // A loss above 1 loses every datagram.
+    client.send_to(&[1], SERVER.parse().unwrap()).await.unwrap();
+    assert!(tokio::time::timeout(Duration::from_secs(1), server.recv_from(&mut buf)).await.is_err());
+
+    // A loss below 0 loses none, and a duplication above 1 duplicates every datagram.
+    net.set_conditions(Conditions { loss: -1.0, duplication: 5.0, ..Default::default() });
+    client.send_to(&[2], SERVER.parse().unwrap()).await.unwrap();
+    for _ in 0..2 {
+        assert_eq!(server.recv_from(&mut buf).await.unwrap(), (1, CLIENT.parse().unwrap()));
+        assert_eq!(buf[0], 2);
+    }
+}
+
+#[tokio::test(start_paused = true)]
+async fn shutdown_wakes_up_accept() {
+    let net = Network::new(Conditions::default(), 7);
+    let (listener, client, _conn) = connect(&net).await;
+
+    // Accept is already waiting for the next connection when the listener is shut down.
+    let (accepted, _) = tokio::time::timeout(Duration::from_secs(30), async {
+        tokio::join!(listener.accept(), listener.shutdown())
+    }).await.unwrap();
+
+    assert!(accepted.is_none());
+    assert!(listener.accept().await.is_none());
+    assert!(client.recv().await.is_none());
+}
+
+#[tokio::test(start_paused = true)]
+async fn mtu_discovery_falls_back_to_smaller_sizes() {
+    for (mtu, negotiated) in [(1400, 1200), (1000, 576)] {
+        let net = Network::new(Conditions { mtu, ..Default::default() }, 8);
+        let (_listener, client, conn) = connect(&net).await;
+
+        assert_eq!(client.mtu, negotiated);
+        assert_eq!(conn.mtu, negotiated);
+
+        // Messages larger than the MTU are split into datagrams that fit through the network.
+        client.send(message(1, 4000), Reliability::ReliableOrdered).unwrap();
+        assert_eq!(conn.recv().await.unwrap(), message(1, 4000));
+    }
+}
+
+/// Binds a listener accepting only the RakNet protocol versions passed to the network.
+fn listener_with_versions(net: &Network, protocol_versions: Vec<u8>) -> RakListener {
+    let config = Config { protocol_versions, ..Default::default() };
+    RakListener::with_transports(vec![net.bind(SERVER.parse().unwrap()).unwrap()], config).unwrap()
+}
+
+#[tokio::test(start_paused = true)]
+async fn client_retries_with_the_protocol_version_required() {
+    let net = Network::new(Conditions::default(), 9);
+    let listener = listener_with_versions(&net, vec![10]);
+
+    // The client requests version 11 first and falls back to version 10.
+    let client = RakClient::connect_with_transport(net.bind(CLIENT.parse().unwrap()).unwrap(), SERVER.parse().unwrap(), Config::default()).await.unwrap();
+    let conn = listener.accept().await.unwrap();
+
+    client.send(message(1, 10), Reliability::ReliableOrdered).unwrap();
+    assert_eq!(conn.recv().await.unwrap(), message(1, 10));
+}
+
+#[tokio::test(start_paused = true)]
+async fn unsupported_protocol_versions_are_rejected() {
+    let net = Network::new(Conditions::default(), 10);
+    let _listener = listener_with_versions(&net, vec![10]);
+
+    let config = Config { protocol_versions: vec![11], ..Default::default() };
+    let err = RakClient::connect_with_transport(net.bind(CLIENT.parse().unwrap()).unwrap(), SERVER.parse().unwrap(), config).await.err().unwrap();
+    assert_eq!(err.kind(), io::ErrorKind::Unsupported);
+    assert!(err.to_string().contains("unsupported RakNet protocol version 10"), "{err}");
+}
+
+#[tokio::test(start_paused = true)]
+async fn protocol_versions_are_tried_once() {
+    let net = Network::new(Conditions::default(), 11);
+    let server = net.bind(SERVER.parse().unwrap()).unwrap();
+
+    // A server that requires whichever version the client did not request.
+    tokio::spawn(async move {
+        let mut buf = [0; 1500];
+        loop {
+            let (n, addr) = server.recv_from(&mut buf).await.unwrap();
+            let Some(pk) = OpenConnectionRequest1::read(&mut &buf[1..n]) else {
+                continue;
+            };
+
+            let mut outg = BytesMut::new();
+            IncompatibleProtocol {
+                protocol: if pk.protocol == 11 { 10 } else { 11 },
+                magic: Magic,
+                guid: b64::new(1)
+            }.write(&mut outg);
+            server.send_to(&outg, addr).await.unwrap();
+        }
+    });
+
+    let err = RakClient::connect_with_transport(net.bind(CLIENT.parse().unwrap()).unwrap(), SERVER.parse().unwrap(), Config::default()).await.err().unwrap();
+    assert_eq!(err.kind(), io::ErrorKind::Unsupported);
+    assert!(err.to_string().contains("rejected RakNet protocol version 11"), "{err}");
+}